@@ -0,0 +1,139 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use tokio::spawn;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use nihility_common::{
+    core_authentication_core_init, set_core_public_key_path, set_default_receiver_submodule,
+    set_submodule_key_dir, set_submodule_name, AuthenticationMode, BindAddr, ClientType,
+    ConnParams, ConnectionType, GrpcClient, GrpcClientConfig, GrpcServer, GrpcServerConfig,
+    InstructEntity, NihilityClient, NihilityServer, ResponseCode, SubmoduleInfo,
+};
+
+const CORE_ARG: &str = "--core";
+const BENCH_IP: &str = "127.0.0.1";
+const BENCH_PORT: u16 = 5060;
+const MESSAGE_COUNT: usize = 1000;
+const STREAM_BUFFER: usize = 64;
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    if env::args().any(|arg| arg == CORE_ARG) {
+        runtime.block_on(run_core());
+    } else {
+        runtime.block_on(run_bench());
+    }
+}
+
+struct CoreProcess(Child);
+
+impl Drop for CoreProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+fn key_dir() -> PathBuf {
+    env::temp_dir().join("nihility-common-bench")
+}
+
+fn server_config() -> GrpcServerConfig {
+    GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str(BENCH_IP).unwrap()],
+        bind_port: BENCH_PORT,
+        ..Default::default()
+    }
+}
+
+async fn run_core() {
+    core_authentication_core_init(key_dir()).unwrap();
+    let mut server = GrpcServer::init(server_config(), CancellationToken::new());
+    let (module_tx, mut module_rx) = mpsc::unbounded_channel();
+    let (instruct_tx, mut instruct_rx) = mpsc::unbounded_channel();
+    server.set_submodule_operate_sender(module_tx).unwrap();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    server.start().unwrap().ready().await.unwrap();
+    spawn(async move { while module_rx.recv().await.is_some() {} });
+    while instruct_rx.recv().await.is_some() {}
+}
+
+async fn start_core() -> CoreProcess {
+    let core = Command::new(env::current_exe().unwrap())
+        .arg(CORE_ARG)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let client_config =
+        GrpcClientConfig::try_from(server_config().create_connection_params()).unwrap();
+    let mut client = GrpcClient::init(client_config);
+    while client.connection_submodule_operate_server().await.is_err() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    CoreProcess(core)
+}
+
+async fn run_bench() {
+    let _core = start_core().await;
+    set_submodule_name("bench");
+    set_default_receiver_submodule("bench");
+    set_core_public_key_path(key_dir().join("id_rsa.pub").to_str().unwrap());
+    set_submodule_key_dir(key_dir().join("submodule").to_str().unwrap());
+    let mut client_config =
+        GrpcClientConfig::try_from(server_config().create_connection_params()).unwrap();
+    for mode in [AuthenticationMode::Rsa, AuthenticationMode::Hmac] {
+        // 注册时按签名方式协商会话密钥，每种方式使用单独注册的客户端
+        client_config.authentication_mode = mode.clone();
+        let mut client = GrpcClient::init(client_config.clone());
+        client
+            .set_submodule_info(SubmoduleInfo {
+                default_instruct: vec![],
+                conn_params: ConnParams {
+                    connection_type: ConnectionType::GrpcType,
+                    client_type: ClientType::NotReceiveType,
+                    conn_config: Default::default(),
+                },
+            })
+            .unwrap();
+        client.connection_submodule_operate_server().await.unwrap();
+        client.connection_instruct_server().await.unwrap();
+        let resp = client.register().await.unwrap();
+        assert!(matches!(resp.code(), ResponseCode::Success));
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let start = Instant::now();
+        spawn(async move {
+            for i in 0..MESSAGE_COUNT {
+                let instruct = InstructEntity::new_text(format!("bench instruct {}", i));
+                if tx.send(instruct).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut resp_rx = client.multiple_text_instruct(rx).await.unwrap();
+        let mut received = 0;
+        while received < MESSAGE_COUNT {
+            let Some(resp) = resp_rx.recv().await else {
+                break;
+            };
+            assert!(matches!(resp.code(), ResponseCode::Success));
+            received += 1;
+        }
+        let elapsed = start.elapsed();
+        assert_eq!(received, MESSAGE_COUNT, "{:?} Stream Ended Early", mode);
+        println!(
+            "send_multiple_text_instruct {:?}: {} instructs in {:?} ({:.0} instructs/s)",
+            mode,
+            received,
+            elapsed,
+            received as f64 / elapsed.as_secs_f64()
+        );
+        client.offline().await.unwrap();
+    }
+}
@@ -5,6 +5,9 @@ fn main() {
                 "proto/manipulate.proto",
                 "proto/instruct.proto",
                 "proto/submodule.proto",
+                "proto/subscribe.proto",
+                "proto/session.proto",
+                "proto/admin.proto",
                 "proto/response_code.proto",
             ],
             &["proto"],
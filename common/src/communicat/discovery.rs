@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::{spawn, time};
+use tracing::{debug, info, warn};
+
+use crate::communicat::grpc::config::GrpcClientConfig;
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::utils::auth::key_fingerprint;
+
+const RUNTIME_DIR_ENV: &str = "XDG_RUNTIME_DIR";
+const HOME_ENV: &str = "HOME";
+const DISCOVERY_DIR_NAME: &str = "nihility";
+const HOME_DISCOVERY_DIR_NAME: &str = ".nihility";
+const DISCOVERY_FILE_NAME: &str = "core.json";
+const DISCOVERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 核心模块启动后写入发现文件的连接信息
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveryInfo {
+    /// 与`GrpcServerConfig::create_connection_params`相同，端口为实际绑定的端口
+    pub connection_params: HashMap<String, String>,
+    /// 核心模块公钥指纹
+    pub core_key_fingerprint: String,
+    pub pid: u32,
+}
+
+/// `$XDG_RUNTIME_DIR/nihility/core.json`，未设置`XDG_RUNTIME_DIR`时为`$HOME/.nihility/core.json`
+///
+/// 不使用所有用户均可写入的系统临时目录，两者均未设置时返回错误
+pub fn default_discovery_path() -> WrapResult<PathBuf> {
+    let discovery_dir = match (non_empty_env(RUNTIME_DIR_ENV), non_empty_env(HOME_ENV)) {
+        (Some(runtime_dir), _) => runtime_dir.join(DISCOVERY_DIR_NAME),
+        (None, Some(home)) => home.join(HOME_DISCOVERY_DIR_NAME),
+        (None, None) => {
+            return Err(NihilityCommonError::Config(
+                String::from("discovery_path"),
+                format!("{} And {} Not Set", RUNTIME_DIR_ENV, HOME_ENV),
+            ))
+        }
+    };
+    Ok(discovery_dir.join(DISCOVERY_FILE_NAME))
+}
+
+fn non_empty_env(key: &str) -> Option<PathBuf> {
+    std::env::var_os(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 仅当前用户可访问的目录，其他用户无法替换其中的发现文件
+fn create_private_dir(dir: &Path) -> WrapResult<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)?;
+    Ok(())
+}
+
+fn core_key_fingerprint<P: AsRef<Path>>(core_public_key_path: P) -> WrapResult<String> {
+    key_fingerprint(&RsaPublicKey::read_public_key_pem_file(
+        core_public_key_path,
+    )?)
+}
+
+impl DiscoveryInfo {
+    pub fn read<P: AsRef<Path>>(path: P) -> WrapResult<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(NihilityCommonError::FileNotExist(
+                path.display().to_string(),
+            ));
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// 先写入临时文件再重命名，读取方不会读到写入一半的内容
+    pub(crate) fn write(&self, path: &Path) -> WrapResult<()> {
+        if let Some(parent) = path.parent() {
+            create_private_dir(parent)?;
+        }
+        let temp_path = path.with_extension(format!("{}.tmp", self.pid));
+        fs::write(&temp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp_path, path)?;
+        info!("Write Discovery File {:?}", path);
+        Ok(())
+    }
+
+    /// 仅删除本进程写入的发现文件，避免删除新启动的核心模块写入的文件
+    pub(crate) fn remove(path: &Path) {
+        match DiscoveryInfo::read(path) {
+            Ok(info) if info.pid == std::process::id() => {
+                if let Err(e) = fs::remove_file(path) {
+                    warn!("Remove Discovery File {:?} Error: {}", path, e);
+                }
+            }
+            Ok(_) => debug!("Discovery File {:?} Written By Other Process", path),
+            Err(e) => debug!("Read Discovery File {:?} Error: {}", path, e),
+        }
+    }
+
+    /// 连接发现文件中的首个地址，其余地址见`GrpcClientConfig::server_addresses`
+    pub fn client_config(&self) -> WrapResult<GrpcClientConfig> {
+        GrpcClientConfig::try_from(self.connection_params.clone())
+    }
+
+    /// 与子模块持有的核心模块公钥核对指纹，发现文件可被其他进程写入，连接前应先核对
+    pub fn verify_core_key<P: AsRef<Path>>(&self, core_public_key_path: P) -> WrapResult<()> {
+        self.verify_fingerprint(&core_key_fingerprint(core_public_key_path)?)
+    }
+
+    fn verify_fingerprint(&self, core_key_fingerprint: &str) -> WrapResult<()> {
+        if self.core_key_fingerprint != core_key_fingerprint {
+            return Err(NihilityCommonError::CoreKeyNotMatch);
+        }
+        Ok(())
+    }
+}
+
+/// 读取核心模块的发现文件并在后台轮询，与子模块持有的核心模块公钥核对指纹后才通知内容变化
+///
+/// 需在tokio运行时中调用，文件暂时不存在、无法解析或指纹不符时保留上次读取的内容
+pub fn discover<P: AsRef<Path>, K: AsRef<Path>>(
+    path: P,
+    core_public_key_path: K,
+) -> WrapResult<Discovery> {
+    let path = path.as_ref().to_path_buf();
+    let fingerprint = core_key_fingerprint(core_public_key_path)?;
+    let info = DiscoveryInfo::read(&path)?;
+    info.verify_fingerprint(&fingerprint)?;
+    let (sender, receiver) = watch::channel(info);
+    let task = spawn(async move {
+        let mut interval = time::interval(DISCOVERY_POLL_INTERVAL);
+        let mut rejected = None;
+        loop {
+            interval.tick().await;
+            match DiscoveryInfo::read(&path) {
+                Ok(info) if info.verify_fingerprint(&fingerprint).is_err() => {
+                    if rejected.as_ref() != Some(&info) {
+                        warn!("Discovery File {:?} Core Key Not Match, Ignored", &path);
+                        rejected = Some(info);
+                    }
+                }
+                Ok(info) => {
+                    let changed = sender.send_if_modified(|current| {
+                        if *current == info {
+                            return false;
+                        }
+                        *current = info;
+                        true
+                    });
+                    if changed {
+                        info!("Discovery File {:?} Changed", &path);
+                    }
+                }
+                Err(e) => debug!("Read Discovery File {:?} Error: {}", &path, e),
+            }
+            if sender.is_closed() {
+                break;
+            }
+        }
+    });
+    Ok(Discovery { receiver, task })
+}
+
+/// `discover`返回的句柄，drop时停止轮询
+pub struct Discovery {
+    receiver: watch::Receiver<DiscoveryInfo>,
+    task: JoinHandle<()>,
+}
+
+impl Discovery {
+    pub fn current(&self) -> DiscoveryInfo {
+        self.receiver.borrow().clone()
+    }
+
+    /// 等待发现文件内容变化，返回已核对指纹的新内容
+    pub async fn changed(&mut self) -> WrapResult<DiscoveryInfo> {
+        self.receiver
+            .changed()
+            .await
+            .map_err(|_| NihilityCommonError::ChannelClosed)?;
+        Ok(self.receiver.borrow_and_update().clone())
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
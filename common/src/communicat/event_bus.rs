@@ -0,0 +1,167 @@
+use std::any::type_name;
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tracing::{debug, warn};
+
+use crate::communicat::router::Deliver;
+use crate::entity::instruct::{InstructEntity, InstructType};
+use crate::entity::manipulate::{ManipulateEntity, ManipulateType};
+use crate::entity::module_operate::{ModuleOperate, OperateType};
+use crate::error::WrapResult;
+use crate::utils::metrics::metrics;
+
+pub const DEFAULT_EVENT_BUS_CAPACITY: usize = 1024;
+
+/// 可在`EventBus`上发布的实体，`kind`用于订阅时过滤
+pub trait Event: Send + Sync + 'static {
+    type Kind: PartialEq + Send + Sync + 'static;
+
+    fn kind(&self) -> Self::Kind;
+}
+
+type EventFilter<E> = Box<dyn Fn(&E) -> bool + Send + Sync>;
+
+/// 进程内广播，每个订阅方都会收到发布的全部实体
+///
+/// 每个订阅方最多缓存`capacity`条未读实体，处理过慢时丢弃最旧的实体而不阻塞发布方
+pub struct EventBus<E: Event> {
+    sender: broadcast::Sender<Arc<E>>,
+    queue_name: String,
+}
+
+impl<E: Event> Default for EventBus<E> {
+    fn default() -> Self {
+        EventBus::new(DEFAULT_EVENT_BUS_CAPACITY)
+    }
+}
+
+impl<E: Event> EventBus<E> {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        let entity_name = type_name::<E>().rsplit("::").next().unwrap_or_default();
+        EventBus {
+            sender,
+            queue_name: format!("event_bus:{}", entity_name),
+        }
+    }
+
+    /// 没有订阅方时直接丢弃实体，不视为错误
+    pub fn publish(&self, entity: E) -> WrapResult<()> {
+        if self.sender.send(Arc::new(entity)).is_err() {
+            debug!(
+                "Event Bus {} Has No Subscriber, Drop Entity",
+                self.queue_name
+            );
+        }
+        metrics().set_queue_depth(&self.queue_name, self.sender.len());
+        Ok(())
+    }
+
+    pub fn subscribe(&self) -> Subscription<E> {
+        Subscription {
+            receiver: self.sender.subscribe(),
+            filter: None,
+            missed: 0,
+        }
+    }
+
+    pub fn subscribe_kind(&self, kind: E::Kind) -> Subscription<E> {
+        self.subscribe_filter(move |entity| entity.kind() == kind)
+    }
+
+    pub fn subscribe_filter<F>(&self, filter: F) -> Subscription<E>
+    where
+        F: Fn(&E) -> bool + Send + Sync + 'static,
+    {
+        Subscription {
+            receiver: self.sender.subscribe(),
+            filter: Some(Box::new(filter)),
+            missed: 0,
+        }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl<E: Event> Deliver<E> for EventBus<E> {
+    fn deliver(&self, entity: E) -> WrapResult<()> {
+        self.publish(entity)
+    }
+}
+
+pub struct Subscription<E: Event> {
+    receiver: broadcast::Receiver<Arc<E>>,
+    filter: Option<EventFilter<E>>,
+    missed: u64,
+}
+
+impl<E: Event> Subscription<E> {
+    /// 总线关闭后返回`None`，落后时跳过被丢弃的实体继续接收
+    pub async fn recv(&mut self) -> Option<Arc<E>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(entity) if self.accept(&entity) => return Some(entity),
+                Ok(_) => {}
+                Err(RecvError::Lagged(count)) => self.lagged(count),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 当前没有可读实体时返回`None`
+    pub fn try_recv(&mut self) -> Option<Arc<E>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(entity) if self.accept(&entity) => return Some(entity),
+                Ok(_) => {}
+                Err(TryRecvError::Lagged(count)) => self.lagged(count),
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// 因处理过慢被丢弃的实体数量
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    fn accept(&self, entity: &E) -> bool {
+        match &self.filter {
+            None => true,
+            Some(filter) => filter(entity),
+        }
+    }
+
+    fn lagged(&mut self, count: u64) {
+        warn!("Event Bus Subscription Lagged, Skip {} Entities", count);
+        self.missed += count;
+    }
+}
+
+impl Event for ModuleOperate {
+    type Kind = OperateType;
+
+    fn kind(&self) -> Self::Kind {
+        self.operate_type.clone()
+    }
+}
+
+impl Event for InstructEntity {
+    type Kind = InstructType;
+
+    fn kind(&self) -> Self::Kind {
+        self.info.instruct_type.clone()
+    }
+}
+
+impl Event for ManipulateEntity {
+    type Kind = ManipulateType;
+
+    fn kind(&self) -> Self::Kind {
+        self.info.manipulate_type.clone()
+    }
+}
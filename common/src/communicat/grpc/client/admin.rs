@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::RsaPrivateKey;
+use tonic::transport::Channel;
+
+use crate::admin::admin_client::AdminClient;
+use crate::admin::AdminReq;
+use crate::communicat::grpc::connect_channel;
+use crate::entity::admin::{AdminRequest, SubmoduleStatus};
+use crate::error::WrapResult;
+use crate::utils::auth::{core_key_sign, CORE_PRIVATE_KEY_FILE_NAME};
+
+/// 运维服务客户端，请求使用核心模块私钥签名
+#[derive(Clone)]
+pub struct GrpcAdminClient {
+    client: AdminClient<Channel>,
+    private_key: RsaPrivateKey,
+}
+
+impl GrpcAdminClient {
+    /// `core_key_dir`为核心模块密钥目录
+    pub async fn connect(server_address: &str, core_key_dir: &str) -> WrapResult<Self> {
+        let private_key_path = Path::new(core_key_dir).join(CORE_PRIVATE_KEY_FILE_NAME);
+        let private_key = RsaPrivateKey::read_pkcs8_pem_file(private_key_path)?;
+        let client = AdminClient::new(connect_channel(server_address).await?);
+        Ok(GrpcAdminClient {
+            client,
+            private_key,
+        })
+    }
+
+    fn request(&self, rpc: &str, auth_id: &str) -> WrapResult<AdminReq> {
+        let mut buf = [0u8; 512];
+        let mut admin_request = AdminRequest::new(rpc, auth_id);
+        core_key_sign(&self.private_key, &mut admin_request, &mut buf)?;
+        Ok(admin_request.into())
+    }
+
+    pub async fn list_submodules(&self) -> WrapResult<Vec<SubmoduleStatus>> {
+        let req = self.request("list_submodules", "")?;
+        self.client
+            .clone()
+            .list_submodules(req)
+            .await?
+            .into_inner()
+            .submodules
+            .into_iter()
+            .map(SubmoduleStatus::try_from)
+            .collect()
+    }
+
+    pub async fn get_submodule(&self, auth_id: &str) -> WrapResult<SubmoduleStatus> {
+        let req = self.request("get_submodule", auth_id)?;
+        SubmoduleStatus::try_from(self.client.clone().get_submodule(req).await?.into_inner())
+    }
+
+    /// 核心模块按子模块主动下线处理，子模块可重新注册
+    pub async fn force_offline(&self, auth_id: &str) -> WrapResult<SubmoduleStatus> {
+        let req = self.request("force_offline", auth_id)?;
+        SubmoduleStatus::try_from(self.client.clone().force_offline(req).await?.into_inner())
+    }
+
+    /// 强制下线并撤销auth_id，之后使用此auth_id签名的请求均被拒绝
+    pub async fn revoke(&self, auth_id: &str) -> WrapResult<SubmoduleStatus> {
+        let req = self.request("revoke", auth_id)?;
+        SubmoduleStatus::try_from(self.client.clone().revoke(req).await?.into_inner())
+    }
+
+    /// 返回Prometheus文本格式的统计
+    pub async fn dump_stats(&self) -> WrapResult<String> {
+        let req = self.request("dump_stats", "")?;
+        Ok(self
+            .client
+            .clone()
+            .dump_stats(req)
+            .await?
+            .into_inner()
+            .metrics)
+    }
+}
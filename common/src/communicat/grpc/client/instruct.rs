@@ -1,49 +1,73 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
 use tokio::spawn;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
-use tonic::Request;
-use tracing::error;
+use tonic::{Code, Response};
+use tracing::{debug, error};
 
+use crate::communicat::middleware::MiddlewareContext;
+use crate::communicat::trace::TraceContext;
 use crate::communicat::SendInstructOperate;
 use crate::entity::instruct::InstructEntity;
 use crate::entity::response::ResponseEntity;
 use crate::error::WrapResult;
 use crate::instruct::TextInstruct;
-use crate::utils::auth::{get_public_key, signature, verify, Signature};
+use crate::session::envelope::Message;
+use crate::utils::auth::{get_auth_id_bytes, signature, verify, Signature};
 
-use super::GrpcClient;
+use super::session::{RespStream, Transport};
+use super::{record_response, request, resolve_auth_id, throttled, traced_request, GrpcClient};
 
 const STREAM_BUFFER: usize = 12;
 
 #[async_trait]
 impl SendInstructOperate for GrpcClient {
     fn is_instruct_client_connected(&self) -> bool {
-        self.instruct_client.is_some()
+        self.instruct_client().is_some()
     }
 
     async fn send_text_instruct(&self, mut instruct: InstructEntity) -> WrapResult<ResponseEntity> {
         let mut buf = [0u8; 512];
-        let auth_id = String::from_utf8_lossy(instruct.get_sign()).to_string();
+        let auth_id = self.auth_id(instruct.get_sign());
+        let started = Instant::now();
+        let context = MiddlewareContext::new("send_text_instruct", None, &auth_id);
+        self.middleware.before(&context, &mut instruct)?;
         signature(
+            self.authenticator.as_ref(),
             &mut instruct,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.instruct_client
-                .clone()
-                .unwrap()
-                .send_text_instruct(Request::new(instruct.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let trace = TraceContext::resolve(instruct.trace_id.as_deref());
+        let mut text_instruct: TextInstruct = instruct.try_into()?;
+        if self.config.payload_encryption {
+            self.authenticator
+                .encrypt_payload(&auth_id, &mut text_instruct)?;
+        }
+        let result = match self.instruct_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .send_text_instruct(traced_request(text_instruct, &trace))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session
+                    .call_traced(Message::TextInstruct(text_instruct), &trace)
+                    .await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
@@ -53,57 +77,88 @@ impl SendInstructOperate for GrpcClient {
     ) -> WrapResult<Receiver<ResponseEntity>> {
         let (req_tx, req_rx) = mpsc::channel::<TextInstruct>(STREAM_BUFFER);
         let (out_tx, out_rx) = mpsc::channel::<ResponseEntity>(STREAM_BUFFER);
+        let payload_encryption = self.config.payload_encryption;
+        let authentication_mode = self.config.authentication_mode.clone();
+        let sign_authenticator = self.authenticator.clone();
+        let sign_auth_id = self.auth_id.clone();
+        let sign_middleware = self.middleware.clone();
         spawn(async move {
             let mut buf = [0u8; 512];
             while let Some(mut instruct) = instruct_stream.recv().await {
-                let auth_id = String::from_utf8_lossy(instruct.get_sign()).to_string();
-                match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        signature(&mut instruct, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        match <InstructEntity as TryInto<TextInstruct>>::try_into(instruct) {
-                            Ok(text_instruct) => match req_tx.send(text_instruct).await {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Grpc Client send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                    break;
-                                }
-                            },
+                let auth_id = resolve_auth_id(&sign_auth_id, instruct.get_sign());
+                let context = MiddlewareContext::new("send_multiple_text_instruct", None, &auth_id);
+                if let Err(e) = sign_middleware.before(&context, &mut instruct) {
+                    debug!(
+                        "Grpc Client send_multiple_text_instruct Rejected By Middleware: {:?}",
+                        e
+                    );
+                    continue;
+                }
+                if let Err(e) = signature(
+                    sign_authenticator.as_ref(),
+                    &mut instruct,
+                    &auth_id,
+                    &authentication_mode,
+                    &mut buf,
+                ) {
+                    error!(
+                        "Grpc Client send_multiple_text_instruct Signature Error: {:?}",
+                        e
+                    );
+                    break;
+                }
+                match <InstructEntity as TryInto<TextInstruct>>::try_into(instruct) {
+                    Ok(mut text_instruct) => {
+                        if payload_encryption {
+                            if let Err(e) =
+                                sign_authenticator.encrypt_payload(&auth_id, &mut text_instruct)
+                            {
+                                error!("Grpc Client send_multiple_text_instruct Encrypt Payload Error: {:?}", e);
+                                break;
+                            }
+                        }
+                        match req_tx.send(text_instruct).await {
+                            Ok(_) => {}
                             Err(e) => {
-                                error!(
-                                    "Grpc Client send_multiple_text_instruct Transform Error: {:?}",
-                                    e
-                                );
+                                error!("Grpc Client send_multiple_text_instruct Send To Stream Error: {:?}", e);
                                 break;
                             }
                         }
                     }
                     Err(e) => {
                         error!(
-                            "Grpc Instruct Server send_text_instruct Auth Id Error: {:?}",
-                            &e
+                            "Grpc Client send_multiple_text_instruct Transform Error: {:?}",
+                            e
                         );
                         break;
                     }
                 }
             }
         });
-        let mut resp_stream = self
-            .instruct_client
-            .clone()
-            .unwrap()
-            .send_multiple_text_instruct(ReceiverStream::new(req_rx))
-            .await?
-            .into_inner();
+        let mut resp_stream: RespStream = match self.instruct_client().unwrap() {
+            Transport::Direct(mut client) => Box::pin(
+                client
+                    .send_multiple_text_instruct(request(ReceiverStream::new(req_rx)))
+                    .await?
+                    .into_inner(),
+            ),
+            Transport::Session(session) => session.call_stream(req_rx, Message::TextInstruct),
+        };
+        let authenticator = self.authenticator.clone();
+        let middleware = self.middleware.clone();
+        let auth_id = self.auth_id(&get_auth_id_bytes());
+        let context = MiddlewareContext::new("send_multiple_text_instruct", None, &auth_id);
         spawn(async move {
             let mut buf = [0u8; 512];
             while let Some(result) = resp_stream.next().await {
                 match result {
                     Ok(resp) => {
                         let mut entity = ResponseEntity::from(resp);
-                        if !verify(&mut entity, &mut buf) {
+                        if verify(authenticator.as_ref(), &mut entity, &mut buf).is_none() {
                             entity.authentication_fail()
                         }
+                        middleware.after(&context, &entity);
+                        record_response(&context, &format!("{:?}", entity.code()));
                         match out_tx.send(entity).await {
                             Ok(_) => {}
                             Err(e) => {
@@ -118,7 +173,11 @@ impl SendInstructOperate for GrpcClient {
                             &status
                         );
                         let mut resp = ResponseEntity::default();
-                        resp.unknown_error();
+                        match status.code() {
+                            Code::ResourceExhausted => resp.too_many_requests(),
+                            _ => resp.unknown_error(),
+                        }
+                        record_response(&context, &format!("{:?}", resp.code()));
                         match out_tx.send(resp).await {
                             Ok(_) => {}
                             Err(e) => {
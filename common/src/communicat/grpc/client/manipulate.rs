@@ -1,51 +1,70 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
 use tokio::spawn;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
-use tonic::Request;
-use tracing::error;
+use tonic::{Code, Response};
+use tracing::{debug, error};
 
+use crate::communicat::middleware::MiddlewareContext;
+use crate::communicat::trace::TraceContext;
 use crate::communicat::SendManipulateOperate;
 use crate::entity::manipulate::ManipulateEntity;
 use crate::entity::response::ResponseEntity;
 use crate::error::WrapResult;
 use crate::manipulate::TextDisplayManipulate;
-use crate::utils::auth::{get_public_key, signature, verify, Signature};
+use crate::session::envelope::Message;
+use crate::utils::auth::{get_auth_id_bytes, signature, verify, Signature};
 
-use super::GrpcClient;
+use super::session::{RespStream, Transport};
+use super::{record_response, request, resolve_auth_id, throttled, traced_request, GrpcClient};
 
 const STREAM_BUFFER: usize = 12;
 
 #[async_trait]
 impl SendManipulateOperate for GrpcClient {
     fn is_manipulate_client_connected(&self) -> bool {
-        self.manipulate_client.is_some()
+        self.manipulate_client().is_some()
     }
     async fn send_simple_manipulate(
         &self,
         mut manipulate: ManipulateEntity,
     ) -> WrapResult<ResponseEntity> {
         let mut buf = [0u8; 512];
-        let auth_id = String::from_utf8_lossy(manipulate.get_sign()).to_string();
+        let auth_id = self.auth_id(manipulate.get_sign());
+        let started = Instant::now();
+        let context = MiddlewareContext::new("send_simple_manipulate", None, &auth_id);
+        self.middleware.before(&context, &mut manipulate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut manipulate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.manipulate_client
-                .clone()
-                .unwrap()
-                .send_simple_manipulate(Request::new(manipulate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let trace = TraceContext::resolve(manipulate.trace_id.as_deref());
+        let result = match self.manipulate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .send_simple_manipulate(traced_request(manipulate.try_into()?, &trace))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session
+                    .call_traced(Message::SimpleManipulate(manipulate.try_into()?), &trace)
+                    .await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
@@ -54,24 +73,45 @@ impl SendManipulateOperate for GrpcClient {
         mut manipulate: ManipulateEntity,
     ) -> WrapResult<ResponseEntity> {
         let mut buf = [0u8; 512];
-        let auth_id = String::from_utf8_lossy(manipulate.get_sign()).to_string();
+        let auth_id = self.auth_id(manipulate.get_sign());
+        let started = Instant::now();
+        let context = MiddlewareContext::new("send_text_display_manipulate", None, &auth_id);
+        self.middleware.before(&context, &mut manipulate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut manipulate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.manipulate_client
-                .clone()
-                .unwrap()
-                .send_text_display_manipulate(Request::new(manipulate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let trace = TraceContext::resolve(manipulate.trace_id.as_deref());
+        let mut text_display_manipulate: TextDisplayManipulate = manipulate.try_into()?;
+        if self.config.payload_encryption {
+            self.authenticator
+                .encrypt_payload(&auth_id, &mut text_display_manipulate)?;
+        }
+        let result = match self.manipulate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .send_text_display_manipulate(traced_request(text_display_manipulate, &trace))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session
+                    .call_traced(
+                        Message::TextDisplayManipulate(text_display_manipulate),
+                        &trace,
+                    )
+                    .await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
@@ -81,58 +121,92 @@ impl SendManipulateOperate for GrpcClient {
     ) -> WrapResult<Receiver<ResponseEntity>> {
         let (req_tx, req_rx) = mpsc::channel::<TextDisplayManipulate>(STREAM_BUFFER);
         let (out_tx, out_rx) = mpsc::channel::<ResponseEntity>(STREAM_BUFFER);
+        let payload_encryption = self.config.payload_encryption;
+        let authentication_mode = self.config.authentication_mode.clone();
+        let sign_authenticator = self.authenticator.clone();
+        let sign_auth_id = self.auth_id.clone();
+        let sign_middleware = self.middleware.clone();
         spawn(async move {
             while let Some(mut manipulate) = manipulate_stream.recv().await {
                 let mut buf = [0u8; 512];
-                let auth_id = String::from_utf8_lossy(manipulate.get_sign()).to_string();
-                match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        signature(&mut manipulate, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        match <ManipulateEntity as TryInto<TextDisplayManipulate>>::try_into(
-                            manipulate,
-                        ) {
-                            Ok(text_display_manipulate) => {
-                                match req_tx.send(text_display_manipulate).await {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        error!("Grpc Client send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                        break;
-                                    }
-                                }
+                let auth_id = resolve_auth_id(&sign_auth_id, manipulate.get_sign());
+                let context =
+                    MiddlewareContext::new("send_multiple_text_display_manipulate", None, &auth_id);
+                if let Err(e) = sign_middleware.before(&context, &mut manipulate) {
+                    debug!(
+                        "Grpc Client send_multiple_text_display_manipulate Rejected By Middleware: {:?}",
+                        e
+                    );
+                    continue;
+                }
+                if let Err(e) = signature(
+                    sign_authenticator.as_ref(),
+                    &mut manipulate,
+                    &auth_id,
+                    &authentication_mode,
+                    &mut buf,
+                ) {
+                    error!(
+                        "Grpc Client send_multiple_text_display_manipulate Signature Error: {:?}",
+                        e
+                    );
+                    break;
+                }
+                match <ManipulateEntity as TryInto<TextDisplayManipulate>>::try_into(manipulate) {
+                    Ok(mut text_display_manipulate) => {
+                        if payload_encryption {
+                            if let Err(e) = sign_authenticator
+                                .encrypt_payload(&auth_id, &mut text_display_manipulate)
+                            {
+                                error!("Grpc Client send_multiple_text_display_manipulate Encrypt Payload Error: {:?}", e);
+                                break;
                             }
+                        }
+                        match req_tx.send(text_display_manipulate).await {
+                            Ok(_) => {}
                             Err(e) => {
-                                error!("Grpc Client send_multiple_text_display_manipulate Transform Error: {:?}", e);
+                                error!("Grpc Client send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
                                 break;
                             }
                         }
                     }
                     Err(e) => {
                         error!(
-                            "Grpc Manipulate Server send_multiple_text_display_manipulate Auth Id Error: {:?}",
-                            &e
+                            "Grpc Client send_multiple_text_display_manipulate Transform Error: {:?}",
+                            e
                         );
                         break;
                     }
                 }
             }
         });
-        let mut resp_stream = self
-            .manipulate_client
-            .clone()
-            .unwrap()
-            .send_multiple_text_display_manipulate(ReceiverStream::new(req_rx))
-            .await?
-            .into_inner();
+        let mut resp_stream: RespStream = match self.manipulate_client().unwrap() {
+            Transport::Direct(mut client) => Box::pin(
+                client
+                    .send_multiple_text_display_manipulate(request(ReceiverStream::new(req_rx)))
+                    .await?
+                    .into_inner(),
+            ),
+            Transport::Session(session) => {
+                session.call_stream(req_rx, Message::TextDisplayManipulate)
+            }
+        };
+        let authenticator = self.authenticator.clone();
+        let middleware = self.middleware.clone();
+        let auth_id = self.auth_id(&get_auth_id_bytes());
+        let context =
+            MiddlewareContext::new("send_multiple_text_display_manipulate", None, &auth_id);
         spawn(async move {
             let mut buf = [0u8; 512];
             while let Some(result) = resp_stream.next().await {
                 match result {
                     Ok(resp) => {
                         let mut entity = ResponseEntity::from(resp);
-                        if !verify(&mut entity, &mut buf) {
+                        if verify(authenticator.as_ref(), &mut entity, &mut buf).is_none() {
                             entity.authentication_fail()
                         }
+                        middleware.after(&context, &entity);
+                        record_response(&context, &format!("{:?}", entity.code()));
                         match out_tx.send(entity).await {
                             Ok(_) => {}
                             Err(e) => {
@@ -147,7 +221,11 @@ impl SendManipulateOperate for GrpcClient {
                             &status
                         );
                         let mut resp = ResponseEntity::default();
-                        resp.unknown_error();
+                        match status.code() {
+                            Code::ResourceExhausted => resp.too_many_requests(),
+                            _ => resp.unknown_error(),
+                        }
+                        record_response(&context, &format!("{:?}", resp.code()));
                         match out_tx.send(resp).await {
                             Ok(_) => {}
                             Err(e) => {
@@ -167,24 +245,40 @@ impl SendManipulateOperate for GrpcClient {
         mut manipulate: ManipulateEntity,
     ) -> WrapResult<ResponseEntity> {
         let mut buf = [0u8; 512];
-        let auth_id = String::from_utf8_lossy(&manipulate.get_sign()).to_string();
+        let auth_id = self.auth_id(manipulate.get_sign());
+        let started = Instant::now();
+        let context = MiddlewareContext::new("send_direct_connection_manipulate", None, &auth_id);
+        self.middleware.before(&context, &mut manipulate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut manipulate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.manipulate_client
-                .clone()
-                .unwrap()
-                .send_direct_connection_manipulate(Request::new(manipulate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let trace = TraceContext::resolve(manipulate.trace_id.as_deref());
+        let result = match self.manipulate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .send_direct_connection_manipulate(traced_request(manipulate.try_into()?, &trace))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session
+                    .call_traced(
+                        Message::DirectConnectionManipulate(manipulate.try_into()?),
+                        &trace,
+                    )
+                    .await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 }
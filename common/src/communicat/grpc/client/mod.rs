@@ -1,74 +1,291 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
 use async_trait::async_trait;
+use tokio::runtime::Handle;
+use tokio::spawn;
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
+use tonic::metadata::MetadataValue;
 use tonic::transport::Channel;
+use tonic::{Code, Request, Status};
+use tracing::{info, warn};
 
+use crate::communicat::discovery::Discovery;
 use crate::communicat::grpc::config::GrpcClientConfig;
-use crate::communicat::NihilityClient;
+use crate::communicat::grpc::{connect_channel, TRACEPARENT_METADATA};
+use crate::communicat::middleware::{Middleware, MiddlewareChain, MiddlewareContext};
+use crate::communicat::trace::TraceContext;
+use crate::communicat::{NihilityClient, SubmoduleOperate};
+use crate::entity::response::{ResponseCode, ResponseEntity};
 use crate::error::{NihilityCommonError, WrapResult};
 use crate::instruct::instruct_client::InstructClient;
 use crate::manipulate::manipulate_client::ManipulateClient;
 use crate::submodule::submodule_client::SubmoduleClient;
-use crate::SubmoduleInfo;
+use crate::subscribe::subscribe_client::SubscribeClient;
+use crate::utils::auth::{default_authenticator, Authenticator};
+use crate::utils::metrics::{metrics, MetricsSide};
+use crate::{get_submodule_name, SubmoduleInfo};
+
+pub use admin::GrpcAdminClient;
+use session::{SessionConnection, Transport};
 
+mod admin;
 mod instruct;
 mod manipulate;
 mod module_operate;
+mod session;
+mod subscribe;
 
 #[derive(Clone)]
 pub struct GrpcClient {
     submodule_nfo: Option<SubmoduleInfo>,
     config: GrpcClientConfig,
-    cancellation_token: Option<CancellationToken>,
-    module_operate_client: Option<SubmoduleClient<Channel>>,
-    instruct_client: Option<InstructClient<Channel>>,
-    manipulate_client: Option<ManipulateClient<Channel>>,
+    authenticator: Arc<dyn Authenticator>,
+    /// 本客户端注册成功后核心模块分配的auth_id
+    auth_id: Arc<RwLock<Option<String>>>,
+    middleware: MiddlewareChain,
+    /// 各克隆共用，任一克隆均可停止心跳
+    cancellation_token: Arc<Mutex<Option<CancellationToken>>>,
+    /// 各克隆共用，注册后最后一个克隆释放时自动下线
+    offline_guard: Arc<OfflineGuard>,
+    /// 各克隆共用，`reconnect`后各克隆均使用新建立的连接
+    connections: Arc<RwLock<Connections>>,
+}
+
+#[derive(Default)]
+struct Connections {
+    server_address: String,
+    session: Option<SessionConnection>,
+    module_operate: Option<Transport<SubmoduleClient<Channel>>>,
+    instruct: Option<Transport<InstructClient<Channel>>>,
+    manipulate: Option<Transport<ManipulateClient<Channel>>>,
+    subscribe: Option<Transport<SubscribeClient<Channel>>>,
+}
+
+impl Connections {
+    /// 各服务均断开后关闭会话
+    fn release_session(&mut self) {
+        if self.module_operate.is_none()
+            && self.instruct.is_none()
+            && self.manipulate.is_none()
+            && self.subscribe.is_none()
+        {
+            self.session = None;
+        }
+    }
 }
 
 impl GrpcClient {
     pub fn init(grpc_client_config: GrpcClientConfig) -> Self {
+        let connections = Connections {
+            server_address: grpc_client_config.server_address.clone(),
+            ..Default::default()
+        };
         GrpcClient {
             submodule_nfo: None,
             config: grpc_client_config,
-            cancellation_token: None,
-            module_operate_client: None,
-            instruct_client: None,
-            manipulate_client: None,
+            authenticator: default_authenticator(),
+            auth_id: Arc::new(RwLock::new(None)),
+            middleware: MiddlewareChain::default(),
+            cancellation_token: Arc::default(),
+            offline_guard: Arc::default(),
+            connections: Arc::new(RwLock::new(connections)),
+        }
+    }
+
+    fn server_address(&self) -> String {
+        self.connections.read().unwrap().server_address.clone()
+    }
+
+    fn module_operate_client(&self) -> Option<Transport<SubmoduleClient<Channel>>> {
+        self.connections.read().unwrap().module_operate.clone()
+    }
+
+    fn instruct_client(&self) -> Option<Transport<InstructClient<Channel>>> {
+        self.connections.read().unwrap().instruct.clone()
+    }
+
+    fn manipulate_client(&self) -> Option<Transport<ManipulateClient<Channel>>> {
+        self.connections.read().unwrap().manipulate.clone()
+    }
+
+    fn subscribe_client(&self) -> Option<Transport<SubscribeClient<Channel>>> {
+        self.connections.read().unwrap().subscribe.clone()
+    }
+
+    /// 会话模式下各服务共用同一条会话，首次连接时建立
+    async fn session_connection(&self) -> WrapResult<SessionConnection> {
+        let server_address = {
+            let connections = self.connections.read().unwrap();
+            if let Some(session) = &connections.session {
+                return Ok(session.clone());
+            }
+            connections.server_address.clone()
+        };
+        let session = SessionConnection::connect(&server_address).await?;
+        self.connections.write().unwrap().session = Some(session.clone());
+        Ok(session)
+    }
+
+    /// 核心模块地址变化后按新地址重新建立已有的连接，各克隆均使用新连接
+    ///
+    /// 核心模块重启后其记录的注册信息已丢失，已注册时重新注册，心跳已启动时重新启动
+    pub async fn reconnect(&mut self, server_address: &str) -> WrapResult<()> {
+        let connected = {
+            let mut connections = self.connections.write().unwrap();
+            info!(
+                "Grpc Client Reconnect From {} To {}",
+                &connections.server_address, server_address
+            );
+            connections.server_address = server_address.to_string();
+            connections.session = None;
+            [
+                connections.module_operate.is_some(),
+                connections.instruct.is_some(),
+                connections.manipulate.is_some(),
+                connections.subscribe.is_some(),
+            ]
+        };
+        if connected[0] {
+            self.connection_submodule_operate_server().await?;
+        }
+        if connected[1] {
+            self.connection_instruct_server().await?;
         }
+        if connected[2] {
+            self.connection_manipulate_server().await?;
+        }
+        if connected[3] {
+            self.connection_subscribe_server().await?;
+        }
+        if connected[0] && self.auth_id.read().unwrap().is_some() {
+            let resp = self.send_register(self.get_submodule_info()?).await?;
+            if !matches!(resp.code(), ResponseCode::Success) {
+                warn!("Grpc Client Register Again Response {:?}", resp.code());
+            }
+        }
+        if self.cancellation_token.lock().unwrap().is_some() {
+            self.stop_heartbeat_thread().await?;
+            self.start_heartbeat_thread().await?;
+        }
+        Ok(())
+    }
+
+    /// 在后台跟随发现文件，核心模块地址变化后调用`reconnect`，abort返回的句柄后停止
+    pub fn follow_discovery(&self, mut discovery: Discovery) -> JoinHandle<()> {
+        let mut client = self.detached();
+        spawn(async move {
+            while let Ok(info) = discovery.changed().await {
+                let result = match info.client_config() {
+                    Ok(config) => client.reconnect(&config.server_address).await,
+                    Err(e) => Err(e),
+                };
+                // 自动下线仍由使用方的克隆负责
+                client.offline_guard.disarm();
+                if let Err(e) = result {
+                    warn!("Grpc Client Follow Discovery Error: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 不参与自动下线的克隆，供心跳线程与自动下线使用
+    fn detached(&self) -> Self {
+        GrpcClient {
+            offline_guard: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// 已注册时使用本客户端的auth_id，否则使用实体签名字段中的auth_id
+    fn auth_id(&self, sign: &[u8]) -> String {
+        resolve_auth_id(&self.auth_id, sign)
+    }
+
+    /// 执行中间件的`after`并记录统计
+    fn finish(&self, context: &MiddlewareContext, resp: &ResponseEntity, started: Instant) {
+        self.middleware.after(context, resp);
+        record_response(context, &format!("{:?}", resp.code()));
+        metrics().observe_duration(MetricsSide::Client, context.rpc, started.elapsed());
     }
 }
 
 #[async_trait]
 impl NihilityClient for GrpcClient {
     async fn connection_submodule_operate_server(&mut self) -> WrapResult<()> {
-        self.module_operate_client =
-            Some(SubmoduleClient::connect(self.config.server_address.to_string()).await?);
+        let client = if self.config.session_mode {
+            Transport::Session(self.session_connection().await?)
+        } else {
+            Transport::Direct(SubmoduleClient::new(
+                connect_channel(&self.server_address()).await?,
+            ))
+        };
+        self.connections.write().unwrap().module_operate = Some(client);
         Ok(())
     }
 
     async fn connection_instruct_server(&mut self) -> WrapResult<()> {
-        self.instruct_client =
-            Some(InstructClient::connect(self.config.server_address.to_string()).await?);
+        let client = if self.config.session_mode {
+            Transport::Session(self.session_connection().await?)
+        } else {
+            Transport::Direct(InstructClient::new(
+                connect_channel(&self.server_address()).await?,
+            ))
+        };
+        self.connections.write().unwrap().instruct = Some(client);
         Ok(())
     }
 
     async fn connection_manipulate_server(&mut self) -> WrapResult<()> {
-        self.manipulate_client =
-            Some(ManipulateClient::connect(self.config.server_address.to_string()).await?);
+        let client = if self.config.session_mode {
+            Transport::Session(self.session_connection().await?)
+        } else {
+            Transport::Direct(ManipulateClient::new(
+                connect_channel(&self.server_address()).await?,
+            ))
+        };
+        self.connections.write().unwrap().manipulate = Some(client);
+        Ok(())
+    }
+
+    async fn connection_subscribe_server(&mut self) -> WrapResult<()> {
+        let client = if self.config.session_mode {
+            Transport::Session(self.session_connection().await?)
+        } else {
+            Transport::Direct(SubscribeClient::new(
+                connect_channel(&self.server_address()).await?,
+            ))
+        };
+        self.connections.write().unwrap().subscribe = Some(client);
         Ok(())
     }
 
     fn disconnection_submodule_operate_server(&mut self) -> WrapResult<()> {
-        self.module_operate_client = None;
+        let mut connections = self.connections.write().unwrap();
+        connections.module_operate = None;
+        connections.release_session();
         Ok(())
     }
 
     fn disconnection_instruct_server(&mut self) -> WrapResult<()> {
-        self.instruct_client = None;
+        let mut connections = self.connections.write().unwrap();
+        connections.instruct = None;
+        connections.release_session();
         Ok(())
     }
 
     fn disconnection_manipulate_server(&mut self) -> WrapResult<()> {
-        self.manipulate_client = None;
+        let mut connections = self.connections.write().unwrap();
+        connections.manipulate = None;
+        connections.release_session();
+        Ok(())
+    }
+
+    fn disconnection_subscribe_server(&mut self) -> WrapResult<()> {
+        let mut connections = self.connections.write().unwrap();
+        connections.subscribe = None;
+        connections.release_session();
         Ok(())
     }
 
@@ -83,4 +300,93 @@ impl NihilityClient for GrpcClient {
             Some(info) => Ok(info),
         }
     }
+
+    fn set_authenticator(&mut self, authenticator: Arc<dyn Authenticator>) -> WrapResult<()> {
+        self.authenticator = authenticator;
+        Ok(())
+    }
+
+    fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) -> WrapResult<()> {
+        self.middleware.push(middleware);
+        Ok(())
+    }
+}
+
+/// 持有注册后的客户端，释放时在当前运行时中执行`shutdown`，主动下线后不再处理
+#[derive(Default)]
+struct OfflineGuard {
+    client: Mutex<Option<GrpcClient>>,
+}
+
+impl OfflineGuard {
+    fn arm(&self, client: GrpcClient) {
+        *self.client.lock().unwrap() = Some(client);
+    }
+
+    fn disarm(&self) {
+        self.client.lock().unwrap().take();
+    }
+}
+
+impl Drop for OfflineGuard {
+    fn drop(&mut self) {
+        let Some(mut client) = self.client.get_mut().unwrap().take() else {
+            return;
+        };
+        match Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = client.shutdown().await {
+                        warn!("Grpc Client Offline On Drop Error: {}", e);
+                    }
+                });
+            }
+            Err(_) => warn!("Grpc Client Dropped Outside Runtime, Offline Not Sent"),
+        }
+    }
+}
+
+/// 携带当前span的追踪上下文，没有时开始新的追踪
+fn request<T>(message: T) -> Request<T> {
+    traced_request(message, &TraceContext::resolve(None))
+}
+
+/// 在元数据中携带追踪上下文，服务端据此延续追踪
+fn traced_request<T>(message: T, trace: &TraceContext) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Ok(traceparent) = MetadataValue::try_from(trace.to_traceparent()) {
+        request
+            .metadata_mut()
+            .insert(TRACEPARENT_METADATA, traceparent);
+    }
+    request
+}
+
+fn resolve_auth_id(auth_id: &RwLock<Option<String>>, sign: &[u8]) -> String {
+    match auth_id.read().unwrap().as_ref() {
+        Some(auth_id) => auth_id.to_string(),
+        None => String::from_utf8_lossy(sign).to_string(),
+    }
+}
+
+fn record_response(context: &MiddlewareContext, code: &str) {
+    metrics().record_request(
+        MetricsSide::Client,
+        context.rpc,
+        &get_submodule_name(),
+        code,
+    );
+}
+
+/// 被服务端限速时返回`TooManyRequests`，其余错误原样返回
+fn throttled(context: &MiddlewareContext, status: Status) -> WrapResult<ResponseEntity> {
+    record_response(context, &format!("{:?}", status.code()));
+    match status.code() {
+        Code::ResourceExhausted => {
+            let mut resp = ResponseEntity::default();
+            resp.too_many_requests();
+            Ok(resp)
+        }
+        _ => Err(status.into()),
+    }
 }
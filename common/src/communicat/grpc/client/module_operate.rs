@@ -1,106 +1,144 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
-use rsa::pkcs8::{EncodePublicKey, LineEnding};
 use tokio::{select, spawn};
 use tokio_util::sync::CancellationToken;
-use tonic::Request;
+use tonic::Response;
 use tracing::{error, info};
 
+use crate::communicat::middleware::MiddlewareContext;
 use crate::communicat::{heartbeat_thread, SubmoduleOperate};
 use crate::entity::module_operate::ModuleOperate;
 use crate::entity::response::ResponseEntity;
 use crate::error::{NihilityCommonError, WrapResult};
-use crate::utils::auth::{get_public_key, signature, verify, Signature, SUBMODULE_PUBLIC_KEY};
-use crate::utils::auth::{submodule_authentication_core_init, submodule_resister_success};
+use crate::session::envelope::Message;
+use crate::utils::auth::{set_submodule_auth_id, signature, verify, AuthenticationMode, Signature};
 use crate::{get_submodule_name, OperateType, SubmoduleInfo};
 
-use super::GrpcClient;
+use super::session::Transport;
+use super::{request, throttled, GrpcClient};
 
 #[async_trait]
 impl SubmoduleOperate for GrpcClient {
     fn is_submodule_operate_client_connected(&self) -> bool {
-        self.module_operate_client.is_some()
+        self.module_operate_client().is_some()
     }
     async fn send_register(
         &mut self,
         mut submodule_info: SubmoduleInfo,
     ) -> WrapResult<ResponseEntity> {
-        let mut buf = [0u8; 512];
+        let mut buf = [0u8; 4096];
         let mut operate = ModuleOperate::default();
-        submodule_info.conn_params.conn_config.insert(
-            SUBMODULE_PUBLIC_KEY.to_string(),
-            submodule_authentication_core_init()?.to_public_key_pem(LineEnding::default())?,
-        );
+        self.authenticator
+            .prepare_register(&mut submodule_info, &self.config.authentication_mode)?;
         operate.info = Some(submodule_info);
         operate.operate_type = OperateType::Register;
+        let started = Instant::now();
+        let context = MiddlewareContext::new("register", None, &get_submodule_name());
+        self.middleware.before(&context, &mut operate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut operate,
             &get_submodule_name(),
-            get_public_key(&get_submodule_name()).await?,
+            &AuthenticationMode::Rsa,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.module_operate_client
-                .clone()
-                .unwrap()
-                .register(Request::new(operate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
-            resp.authentication_fail()
+        let result = match self.module_operate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .register(request(operate.try_into()?))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session.call(Message::Register(operate.try_into()?)).await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        match verify(self.authenticator.as_ref(), &mut resp, &mut buf) {
+            None => resp.authentication_fail(),
+            Some(identity) => {
+                self.authenticator
+                    .register_success(&identity.auth_id, &self.config.authentication_mode)?;
+                set_submodule_auth_id(&identity.auth_id);
+                *self.auth_id.write().unwrap() = Some(identity.auth_id);
+                self.offline_guard.arm(self.detached());
+            }
         }
-        submodule_resister_success(&mut resp).await?;
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
     async fn send_heartbeat(&self) -> WrapResult<ResponseEntity> {
         let mut buf = [0u8; 512];
         let mut operate = ModuleOperate::default();
-        let auth_id = String::from_utf8_lossy(operate.get_sign()).to_string();
+        let auth_id = self.auth_id(operate.get_sign());
         operate.operate_type = OperateType::Heartbeat;
+        let started = Instant::now();
+        let context = MiddlewareContext::new("heartbeat", None, &auth_id);
+        self.middleware.before(&context, &mut operate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut operate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.module_operate_client
-                .clone()
-                .unwrap()
-                .heartbeat(Request::new(operate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let result = match self.module_operate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .heartbeat(request(operate.try_into()?))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session.call(Message::Heartbeat(operate.try_into()?)).await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
     async fn send_offline(&mut self, submodule_info: SubmoduleInfo) -> WrapResult<ResponseEntity> {
+        self.offline_guard.disarm();
         let mut buf = [0u8; 512];
         let mut operate = ModuleOperate::default();
-        let auth_id = String::from_utf8_lossy(operate.get_sign()).to_string();
+        let auth_id = self.auth_id(operate.get_sign());
         operate.operate_type = OperateType::Offline;
         operate.info = Some(submodule_info);
+        let started = Instant::now();
+        let context = MiddlewareContext::new("offline", None, &auth_id);
+        self.middleware.before(&context, &mut operate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut operate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.module_operate_client
-                .clone()
-                .unwrap()
-                .offline(Request::new(operate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let result = match self.module_operate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .offline(request(operate.try_into()?))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => {
+                session.call(Message::Offline(operate.try_into()?)).await
+            }
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
 
@@ -109,30 +147,38 @@ impl SubmoduleOperate for GrpcClient {
         let mut operate = ModuleOperate::default();
         operate.operate_type = OperateType::Update;
         operate.info = Some(submodule_info);
-        let auth_id = String::from_utf8_lossy(operate.get_sign()).to_string();
+        let auth_id = self.auth_id(operate.get_sign());
+        let started = Instant::now();
+        let context = MiddlewareContext::new("update", None, &auth_id);
+        self.middleware.before(&context, &mut operate)?;
         signature(
+            self.authenticator.as_ref(),
             &mut operate,
             &auth_id,
-            get_public_key(&auth_id).await?,
+            &self.config.authentication_mode,
             &mut buf,
         )?;
-        let mut resp = ResponseEntity::from(
-            self.module_operate_client
-                .clone()
-                .unwrap()
-                .update(Request::new(operate.try_into()?))
-                .await?
-                .into_inner(),
-        );
-        if !verify(&mut resp, &mut buf) {
+        let result = match self.module_operate_client().unwrap() {
+            Transport::Direct(mut client) => client
+                .update(request(operate.try_into()?))
+                .await
+                .map(Response::into_inner),
+            Transport::Session(session) => session.call(Message::Update(operate.try_into()?)).await,
+        };
+        let mut resp = match result {
+            Ok(resp) => ResponseEntity::from(resp),
+            Err(status) => return throttled(&context, status),
+        };
+        if verify(self.authenticator.as_ref(), &mut resp, &mut buf).is_none() {
             resp.authentication_fail()
         }
+        self.finish(&context, &resp, started);
         Ok(resp)
     }
     async fn start_heartbeat_thread(&mut self) -> WrapResult<()> {
         let cancellation_token = CancellationToken::new();
         let thread_cancellation_token = cancellation_token.clone();
-        let client = self.clone();
+        let client = self.detached();
         spawn(async move {
             select! {
                 heartbeat_thread_result = heartbeat_thread(client) => {
@@ -144,18 +190,25 @@ impl SubmoduleOperate for GrpcClient {
                 _ = thread_cancellation_token.cancelled() => {},
             }
         });
-        self.cancellation_token = Some(cancellation_token);
+        // 重复注册时停止之前的心跳线程
+        if let Some(previous) = self
+            .cancellation_token
+            .lock()
+            .unwrap()
+            .replace(cancellation_token)
+        {
+            previous.cancel();
+        }
         Ok(())
     }
 
     async fn stop_heartbeat_thread(&mut self) -> WrapResult<()> {
-        match &self.cancellation_token {
+        match self.cancellation_token.lock().unwrap().take() {
             None => Err(NihilityCommonError::ThreadNotStarted(String::from(
                 "Heartbeat",
             ))),
             Some(cancellation_token) => {
                 cancellation_token.cancel();
-                self.cancellation_token = None;
                 info!("Heartbeat Thread Stopped");
                 Ok(())
             }
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use tokio::spawn;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::codegen::tokio_stream::Stream;
+use tonic::{Code, Status};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::communicat::grpc::connect_channel;
+use crate::communicat::trace::TraceContext;
+use crate::error::WrapResult;
+use crate::response_code::Resp;
+use crate::session::envelope::Message;
+use crate::session::session_client::SessionClient;
+use crate::session::{Envelope, SessionStatus};
+use crate::subscribe::SubscribeResp;
+
+use super::request;
+
+const SESSION_BUFFER: usize = 128;
+const SESSION_CLOSED_MESSAGE: &str = "Session Closed";
+
+pub(super) type RespStream = Pin<Box<dyn Stream<Item = Result<Resp, Status>> + Send>>;
+pub(super) type PushStream = Pin<Box<dyn Stream<Item = Result<SubscribeResp, Status>> + Send>>;
+
+/// 连接方式，会话模式下各类消息共用同一条双向流
+#[derive(Clone)]
+pub(super) enum Transport<C> {
+    Direct(C),
+    Session(SessionConnection),
+}
+
+enum PendingReply {
+    Call(oneshot::Sender<Result<Resp, Status>>),
+    Subscribe(mpsc::Sender<Result<SubscribeResp, Status>>),
+}
+
+type PendingMap = Arc<Mutex<HashMap<String, PendingReply>>>;
+
+/// `Session`双向流的客户端，按correlation_id将服务端的回复交给对应的请求
+#[derive(Clone)]
+pub(super) struct SessionConnection {
+    sender: mpsc::Sender<Envelope>,
+    pending: PendingMap,
+}
+
+impl SessionConnection {
+    pub(super) async fn connect(server_address: &str) -> WrapResult<Self> {
+        let mut client = SessionClient::new(connect_channel(server_address).await?);
+        let (sender, receiver) = mpsc::channel(SESSION_BUFFER);
+        let mut inbound = client
+            .session(request(ReceiverStream::new(receiver)))
+            .await?
+            .into_inner();
+        let pending = PendingMap::default();
+        let reader_pending = pending.clone();
+        spawn(async move {
+            while let Some(result) = inbound.next().await {
+                match result {
+                    Ok(envelope) => dispatch(&reader_pending, envelope).await,
+                    Err(e) => {
+                        error!("Session Client Receive Error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+            debug!("Session Client Stream Closed");
+            reader_pending.lock().unwrap().clear();
+        });
+        Ok(SessionConnection { sender, pending })
+    }
+
+    pub(super) async fn call(&self, message: Message) -> Result<Resp, Status> {
+        self.call_traced(message, &TraceContext::resolve(None))
+            .await
+    }
+
+    pub(super) async fn call_traced(
+        &self,
+        message: Message,
+        trace: &TraceContext,
+    ) -> Result<Resp, Status> {
+        let (tx, rx) = oneshot::channel();
+        self.send(message, PendingReply::Call(tx), trace).await?;
+        match rx.await {
+            Ok(result) => result,
+            Err(_) => Err(Status::unavailable(SESSION_CLOSED_MESSAGE)),
+        }
+    }
+
+    /// 依次发送请求，回复按请求顺序输出，各请求沿用调用时的追踪上下文
+    pub(super) fn call_stream<T: Send + 'static>(
+        &self,
+        mut requests: mpsc::Receiver<T>,
+        wrap: fn(T) -> Message,
+    ) -> RespStream {
+        let (tx, rx) = mpsc::channel(SESSION_BUFFER);
+        let session = self.clone();
+        let trace = TraceContext::resolve(None);
+        spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let resp = session.call_traced(wrap(request), &trace).await;
+                if tx.send(resp).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Box::pin(ReceiverStream::new(rx))
+    }
+
+    pub(super) async fn subscribe(&self, message: Message) -> Result<PushStream, Status> {
+        let (tx, rx) = mpsc::channel(SESSION_BUFFER);
+        self.send(
+            message,
+            PendingReply::Subscribe(tx),
+            &TraceContext::resolve(None),
+        )
+        .await?;
+        Ok(Box::pin(ReceiverStream::new(rx)))
+    }
+
+    async fn send(
+        &self,
+        message: Message,
+        reply: PendingReply,
+        trace: &TraceContext,
+    ) -> Result<(), Status> {
+        let correlation_id = Uuid::new_v4().to_string();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(correlation_id.to_string(), reply);
+        let envelope = Envelope {
+            correlation_id: correlation_id.to_string(),
+            message: Some(message),
+            traceparent: trace.to_traceparent(),
+        };
+        if self.sender.send(envelope).await.is_err() {
+            self.pending.lock().unwrap().remove(&correlation_id);
+            return Err(Status::unavailable(SESSION_CLOSED_MESSAGE));
+        }
+        Ok(())
+    }
+}
+
+async fn dispatch(pending: &PendingMap, envelope: Envelope) {
+    let correlation_id = envelope.correlation_id;
+    let result = match envelope.message {
+        Some(Message::Resp(resp)) => Ok(resp),
+        Some(Message::Status(status)) => Err(Status::from(status)),
+        Some(Message::Push(push)) => {
+            let subscriber = match pending.lock().unwrap().get(&correlation_id) {
+                Some(PendingReply::Subscribe(subscriber)) => subscriber.clone(),
+                _ => return,
+            };
+            if subscriber.send(Ok(push)).await.is_err() {
+                pending.lock().unwrap().remove(&correlation_id);
+            }
+            return;
+        }
+        other => {
+            debug!("Session Client Receive Unexpected Message: {:?}", other);
+            return;
+        }
+    };
+    match pending.lock().unwrap().remove(&correlation_id) {
+        Some(PendingReply::Call(caller)) => {
+            let _ = caller.send(result);
+        }
+        Some(PendingReply::Subscribe(subscriber)) => {
+            if let Err(status) = result {
+                let _ = subscriber.try_send(Err(status));
+            }
+        }
+        None => debug!(
+            "Session Client Reply {} Has No Pending Request",
+            correlation_id
+        ),
+    }
+}
+
+impl From<SessionStatus> for Status {
+    fn from(value: SessionStatus) -> Self {
+        Status::new(Code::from(value.code), value.message)
+    }
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use tokio::spawn;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::Receiver;
+use tokio_stream::StreamExt;
+use tracing::error;
+
+use crate::communicat::SubscribeOperate;
+use crate::entity::module_operate::{ModuleOperate, OperateType};
+use crate::entity::subscribe::{encrypted_payload, PushEntity};
+use crate::error::WrapResult;
+use crate::session::envelope::Message;
+use crate::utils::auth::{signature, Signature};
+
+use super::session::{PushStream, Transport};
+use super::{request, GrpcClient};
+
+const STREAM_BUFFER: usize = 12;
+
+#[async_trait]
+impl SubscribeOperate for GrpcClient {
+    fn is_subscribe_client_connected(&self) -> bool {
+        self.subscribe_client().is_some()
+    }
+
+    async fn send_subscribe(&self) -> WrapResult<Receiver<PushEntity>> {
+        let mut buf = [0u8; 512];
+        let mut operate = ModuleOperate::default();
+        let auth_id = self.auth_id(operate.get_sign());
+        operate.operate_type = OperateType::Subscribe;
+        signature(
+            self.authenticator.as_ref(),
+            &mut operate,
+            &auth_id,
+            &self.config.authentication_mode,
+            &mut buf,
+        )?;
+        let mut push_stream: PushStream = match self.subscribe_client().unwrap() {
+            Transport::Direct(mut client) => Box::pin(
+                client
+                    .subscribe(request(operate.try_into()?))
+                    .await?
+                    .into_inner(),
+            ),
+            Transport::Session(session) => {
+                session
+                    .subscribe(Message::Subscribe(operate.try_into()?))
+                    .await?
+            }
+        };
+        let (tx, rx) = mpsc::channel::<PushEntity>(STREAM_BUFFER);
+        let authenticator = self.authenticator.clone();
+        spawn(async move {
+            let mut buf = [0u8; 2048];
+            while let Some(result) = push_stream.next().await {
+                let mut resp = match result {
+                    Ok(resp) => resp,
+                    Err(e) => {
+                        error!("Grpc Client send_subscribe Receive Error: {:?}", e);
+                        break;
+                    }
+                };
+                // 未加密的负载直接跳过
+                if let Some(payload) = encrypted_payload(&mut resp) {
+                    if let Err(e) = authenticator.decrypt_payload(payload) {
+                        error!(
+                            "Grpc Client send_subscribe Decrypt Payload Error: {:?}, Skip",
+                            e
+                        );
+                        continue;
+                    }
+                }
+                let mut entity = match PushEntity::try_from(resp) {
+                    Ok(entity) => entity,
+                    Err(e) => {
+                        error!("Grpc Client send_subscribe Receive Error: {:?}", e);
+                        break;
+                    }
+                };
+                if !entity.verify(authenticator.as_ref(), &mut buf) {
+                    error!("Grpc Client send_subscribe Verify Push Entity Fail, Skip");
+                    continue;
+                }
+                if let Err(e) = tx.send(entity).await {
+                    error!(
+                        "Grpc Client send_subscribe Send To Submodule Error: {:?}",
+                        e
+                    );
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
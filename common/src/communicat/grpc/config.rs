@@ -1,70 +1,223 @@
-use std::collections::HashMap;
-use std::net::IpAddr;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 
-use local_ip_address::{local_ip, local_ipv6};
+use local_ip_address::{list_afinet_netifas, local_ip, local_ipv6};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error};
 
 use crate::error::NihilityCommonError;
+use crate::utils::auth::AuthenticationMode;
 
-const BIND_PORT: u32 = 5050;
-const BIND_IP: &str = "127.0.0.1";
+const BIND_PORT: u16 = 5050;
+const DRAIN_TIMEOUT_SECS: u64 = 10;
 const DEFAULT_TERMINAL_ADDR: &str = "http://127.0.0.1:5050";
+pub(crate) const UNIX_SCHEME: &str = "unix:";
 
 const SERVER_ADDR_FIELD: &str = "server_addr";
+/// 全部可用地址，以逗号分隔，`server_addr`为其中第一个
+const SERVER_ADDRS_FIELD: &str = "server_addrs";
+
+/// 服务端监听地址，配置中写作`127.0.0.1`、`::`或`unix:/run/nihility.sock`
+///
+/// IPv6地址仅接受IPv6连接，同时监听`0.0.0.0`与`::`即为双栈
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum BindAddr {
+    Ip(IpAddr),
+    Unix(PathBuf),
+}
 
 /// Grpc相关配置
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
 pub struct GrpcServerConfig {
-    pub bind_ip: IpAddr,
-    pub bind_port: u32,
+    /// IP地址均监听`bind_port`
+    pub bind_addrs: Vec<BindAddr>,
+    pub bind_port: u16,
+    /// 停止后等待进行中请求完成的最长时间，超时后直接断开
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// 是否使用子模块注册时的公钥加密订阅流推送的指令与操作中的文本负载
+    #[serde(default)]
+    pub payload_encryption: bool,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
 pub struct GrpcClientConfig {
     pub server_address: String,
+    /// 是否加密指令与操作中的文本负载
+    #[serde(default)]
+    pub payload_encryption: bool,
+    #[serde(default)]
+    pub authentication_mode: AuthenticationMode,
+    /// 是否通过单条`Session`双向流传输全部消息
+    #[serde(default)]
+    pub session_mode: bool,
 }
 
 impl Default for GrpcServerConfig {
     fn default() -> Self {
-        let ip = match local_ipv6() {
-            Ok(ipv6) => ipv6,
-            Err(e) => {
-                debug!("Get Local Ipv6 Addr Error {:?}, Try Get Ipv4 Addr", e);
-                match local_ip() {
-                    Ok(ipv4) => ipv4,
-                    Err(e) => {
-                        error!("Get Ipv4 Addr Error: {:?}", e);
-                        IpAddr::from_str(BIND_IP).unwrap()
-                    }
-                }
-            }
-        };
+        let mut bind_addrs = Vec::new();
+        match local_ip() {
+            Ok(ipv4) => bind_addrs.push(BindAddr::Ip(ipv4)),
+            Err(e) => debug!("Get Local Ipv4 Addr Error: {:?}", e),
+        }
+        match local_ipv6() {
+            Ok(ipv6) => bind_addrs.push(BindAddr::Ip(ipv6)),
+            Err(e) => debug!("Get Local Ipv6 Addr Error: {:?}", e),
+        }
+        if bind_addrs.is_empty() {
+            error!("Get Local Ip Addr Error, Bind Loopback Addr");
+            bind_addrs.push(BindAddr::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        }
         GrpcServerConfig {
-            bind_ip: ip,
+            bind_addrs,
             bind_port: BIND_PORT,
+            drain_timeout_secs: DRAIN_TIMEOUT_SECS,
+            payload_encryption: false,
         }
     }
 }
 
+fn default_drain_timeout_secs() -> u64 {
+    DRAIN_TIMEOUT_SECS
+}
+
 impl Default for GrpcClientConfig {
     fn default() -> Self {
         GrpcClientConfig {
             server_address: DEFAULT_TERMINAL_ADDR.to_string(),
+            payload_encryption: false,
+            authentication_mode: AuthenticationMode::default(),
+            session_mode: false,
         }
     }
 }
 
 impl GrpcServerConfig {
+    /// 未指定地址展开为本机各网卡地址，回环地址排在最后
     pub fn create_connection_params(&self) -> HashMap<String, String> {
-        let mut result = HashMap::<String, String>::new();
-        let server_addr = match self.bind_ip {
-            IpAddr::V4(ip) => format!("http://{}:{}", ip, self.bind_port),
-            IpAddr::V6(ip) => format!("http://[{}]:{}", ip, self.bind_port),
-        };
-        result.insert(SERVER_ADDR_FIELD.to_string(), server_addr);
-        result
+        connection_params(
+            self.bind_addrs
+                .iter()
+                .map(|bind_addr| (bind_addr, self.bind_port)),
+        )
+    }
+}
+
+/// `bind_addrs`为监听地址与端口，启动后可传入实际绑定的端口
+pub(crate) fn connection_params<'a>(
+    bind_addrs: impl IntoIterator<Item = (&'a BindAddr, u16)>,
+) -> HashMap<String, String> {
+    let mut result = HashMap::<String, String>::new();
+    let server_addrs = endpoints(bind_addrs);
+    if let Some(server_addr) = server_addrs.first() {
+        result.insert(SERVER_ADDR_FIELD.to_string(), server_addr.to_string());
+    }
+    result.insert(SERVER_ADDRS_FIELD.to_string(), server_addrs.join(","));
+    result
+}
+
+fn endpoints<'a>(bind_addrs: impl IntoIterator<Item = (&'a BindAddr, u16)>) -> Vec<String> {
+    let mut endpoints = Vec::new();
+    for (bind_addr, port) in bind_addrs {
+        match bind_addr {
+            BindAddr::Ip(ip) if ip.is_unspecified() => {
+                for ip in interface_ips(ip.is_ipv4()) {
+                    endpoints.push(http_endpoint(SocketAddr::new(ip, port)));
+                }
+            }
+            BindAddr::Ip(ip) => endpoints.push(http_endpoint(SocketAddr::new(*ip, port))),
+            BindAddr::Unix(_) => endpoints.push(bind_addr.to_string()),
+        }
+    }
+    let mut seen = HashSet::new();
+    endpoints.retain(|endpoint| seen.insert(endpoint.clone()));
+    endpoints
+}
+
+fn http_endpoint(addr: SocketAddr) -> String {
+    format!("http://{}", addr)
+}
+
+/// IPv6链路本地地址需要指定网卡，不作为可连接地址
+fn interface_ips(ipv4: bool) -> Vec<IpAddr> {
+    let mut ips: Vec<IpAddr> = match list_afinet_netifas() {
+        Ok(interfaces) => interfaces.into_iter().map(|(_, ip)| ip).collect(),
+        Err(e) => {
+            error!("List Network Interfaces Error: {:?}", e);
+            Vec::new()
+        }
+    };
+    ips.retain(|ip| match ip {
+        IpAddr::V4(_) => ipv4,
+        IpAddr::V6(ip) => !ipv4 && (ip.segments()[0] & 0xffc0) != 0xfe80,
+    });
+    if ips.is_empty() {
+        ips.push(match ipv4 {
+            true => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            false => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    ips.sort_by_key(|ip| ip.is_loopback());
+    ips
+}
+
+impl fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddr::Ip(ip) => write!(f, "{}", ip),
+            BindAddr::Unix(path) => write!(f, "{}{}", UNIX_SCHEME, path.display()),
+        }
+    }
+}
+
+impl FromStr for BindAddr {
+    type Err = NihilityCommonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(UNIX_SCHEME) {
+            Some(path) if !path.is_empty() => Ok(BindAddr::Unix(PathBuf::from(path))),
+            Some(_) => Err(NihilityCommonError::Config(
+                s.to_string(),
+                String::from("Unix Socket Path Must Not Be Empty"),
+            )),
+            None => Ok(BindAddr::Ip(IpAddr::from_str(s)?)),
+        }
+    }
+}
+
+impl TryFrom<String> for BindAddr {
+    type Error = NihilityCommonError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        BindAddr::from_str(&value)
+    }
+}
+
+impl From<BindAddr> for String {
+    fn from(value: BindAddr) -> Self {
+        value.to_string()
+    }
+}
+
+impl GrpcClientConfig {
+    /// 核心模块发布的全部可用地址，可从中选择后设置`server_address`
+    pub fn server_addresses(connection_params: &HashMap<String, String>) -> Vec<String> {
+        match (
+            connection_params.get(SERVER_ADDRS_FIELD),
+            connection_params.get(SERVER_ADDR_FIELD),
+        ) {
+            (Some(server_addrs), _) if !server_addrs.is_empty() => {
+                server_addrs.split(',').map(str::to_string).collect()
+            }
+            (_, Some(server_addr)) => vec![server_addr.to_string()],
+            _ => Vec::new(),
+        }
     }
 }
 
@@ -72,9 +225,12 @@ impl TryFrom<HashMap<String, String>> for GrpcClientConfig {
     type Error = NihilityCommonError;
 
     fn try_from(value: HashMap<String, String>) -> Result<Self, Self::Error> {
-        if let Some(server_address) = value.get(SERVER_ADDR_FIELD) {
+        if let Some(server_address) = GrpcClientConfig::server_addresses(&value).first() {
             return Ok(GrpcClientConfig {
                 server_address: server_address.to_string(),
+                payload_encryption: false,
+                authentication_mode: AuthenticationMode::default(),
+                session_mode: false,
             });
         }
         Err(NihilityCommonError::ConfigFieldMissing)
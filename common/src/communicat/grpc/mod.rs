@@ -1,3 +1,31 @@
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use tonic::transport::{Channel, Endpoint};
+
+use crate::communicat::grpc::config::UNIX_SCHEME;
+use crate::error::WrapResult;
+
 pub mod client;
-pub mod server;
 pub mod config;
+pub mod server;
+
+/// W3C Trace Context格式的追踪上下文
+pub(crate) const TRACEPARENT_METADATA: &str = "traceparent";
+
+/// `unix:`开头的地址通过Unix domain socket连接，其余按http地址连接
+pub(crate) async fn connect_channel(server_address: &str) -> WrapResult<Channel> {
+    #[cfg(unix)]
+    if let Some(path) = server_address.strip_prefix(UNIX_SCHEME) {
+        let path = PathBuf::from(path);
+        let channel = Endpoint::from_static("http://localhost")
+            .connect_with_connector(tower::service_fn(move |_| {
+                tokio::net::UnixStream::connect(path.clone())
+            }))
+            .await?;
+        return Ok(channel);
+    }
+    Ok(Endpoint::from_shared(server_address.to_string())?
+        .connect()
+        .await?)
+}
@@ -0,0 +1,216 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use rsa::RsaPublicKey;
+use tonic::{Code, Request, Response, Status};
+use tracing::{error, info};
+
+use crate::admin::admin_server::Admin;
+use crate::admin::{self, AdminReq, Stats, SubmoduleList};
+use crate::communicat::grpc::server::RequestContext;
+use crate::communicat::matcher::InstructMatcher;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::Deliver;
+use crate::communicat::subscriber::SubscriberHub;
+use crate::entity::admin::{AdminRequest, SubmoduleStatus};
+use crate::entity::module_operate::ModuleOperate;
+use crate::utils::audit::AuditEventKind;
+use crate::utils::auth::{core_key_verify, Authenticator, AUTHENTICATION_ERROR_MESSAGE};
+use crate::utils::metrics::metrics;
+
+/// 签名时间与服务端时间相差超过此时长的请求不予接受
+const ADMIN_REQUEST_MAX_AGE: Duration = Duration::from_secs(60);
+const SUCCESS_CODE: &str = "Success";
+const NOT_FOUND_CODE: &str = "NotFound";
+
+#[derive(Clone)]
+pub struct AdminImpl {
+    core_public_key: RsaPublicKey,
+    authenticator: Arc<dyn Authenticator>,
+    registry: Arc<SubmoduleRegistry>,
+    operate_module_sender: Option<Arc<dyn Deliver<ModuleOperate>>>,
+    instruct_matcher: Option<Arc<InstructMatcher>>,
+    subscriber_hub: Option<Arc<SubscriberHub>>,
+    /// 已接受请求的随机数及其过期时间
+    nonces: Arc<Mutex<HashMap<u64, SystemTime>>>,
+}
+
+impl AdminImpl {
+    pub fn init(
+        core_public_key: RsaPublicKey,
+        authenticator: Arc<dyn Authenticator>,
+        registry: Arc<SubmoduleRegistry>,
+        operate_module_sender: Option<Arc<dyn Deliver<ModuleOperate>>>,
+        instruct_matcher: Option<Arc<InstructMatcher>>,
+        subscriber_hub: Option<Arc<SubscriberHub>>,
+    ) -> Self {
+        AdminImpl {
+            core_public_key,
+            authenticator,
+            registry,
+            operate_module_sender,
+            instruct_matcher,
+            subscriber_hub,
+            nonces: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 校验核心模块私钥签名、签名时间与随机数
+    #[allow(clippy::result_large_err)]
+    fn verify(
+        &self,
+        request: Request<AdminReq>,
+        rpc: &'static str,
+    ) -> Result<(AdminRequest, RequestContext), Status> {
+        let mut buf = [0u8; 512];
+        let context = RequestContext::new(rpc, &request);
+        let mut admin_request = AdminRequest::received(rpc, request.into_inner());
+        let detail = if admin_request.elapsed() > ADMIN_REQUEST_MAX_AGE {
+            "Admin Request Expired"
+        } else if !core_key_verify(&self.core_public_key, &mut admin_request, &mut buf) {
+            "Admin Request Not Signed By Core Key"
+        } else if !self.accept_nonce(&admin_request) {
+            "Admin Request Replayed"
+        } else {
+            return Ok((admin_request, context));
+        };
+        context
+            .audit(AuditEventKind::AuthenticationFail)
+            .detail(detail.to_string())
+            .emit();
+        context.record_verification_failure();
+        Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+    }
+
+    /// 有效期内同一随机数只接受一次
+    fn accept_nonce(&self, admin_request: &AdminRequest) -> bool {
+        let now = SystemTime::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, expires_at| *expires_at > now);
+        match nonces.entry(admin_request.nonce) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(admin_request.signed_at() + ADMIN_REQUEST_MAX_AGE);
+                true
+            }
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn remove(
+        &self,
+        admin_request: &AdminRequest,
+        context: &RequestContext,
+    ) -> Result<SubmoduleStatus, Status> {
+        match self.registry.remove(&admin_request.auth_id) {
+            Some(status) => Ok(status),
+            None => Err(not_found(admin_request, context)),
+        }
+    }
+
+    /// 与子模块主动下线相同，通知核心模块并移除匹配器与订阅
+    fn force_offline(&self, status: &SubmoduleStatus, context: &RequestContext) {
+        info!(
+            "Admin Server Force Submodule {} Offline: {}",
+            &status.name, &status.auth_id
+        );
+        context
+            .audit(AuditEventKind::Offline)
+            .submodule_name(&status.name)
+            .auth_id(&status.auth_id)
+            .detail(String::from("Forced By Admin"))
+            .emit();
+        let operate = ModuleOperate::offline(&status.name, &status.auth_id);
+        if let Some(instruct_matcher) = &self.instruct_matcher {
+            instruct_matcher.apply(&operate);
+        }
+        if let Some(subscriber_hub) = &self.subscriber_hub {
+            subscriber_hub.unbind(&status.auth_id);
+        }
+        if let Some(operate_module_sender) = &self.operate_module_sender {
+            if let Err(e) = operate_module_sender.deliver(operate) {
+                error!("Admin Server {} Send To Core Error: {:?}", context.rpc, e);
+            }
+        }
+    }
+}
+
+fn not_found(admin_request: &AdminRequest, context: &RequestContext) -> Status {
+    context.record(NOT_FOUND_CODE);
+    Status::not_found(format!(
+        "Submodule {} Not Registered",
+        &admin_request.auth_id
+    ))
+}
+
+#[tonic::async_trait]
+impl Admin for AdminImpl {
+    async fn list_submodules(
+        &self,
+        request: Request<AdminReq>,
+    ) -> Result<Response<SubmoduleList>, Status> {
+        let (_, context) = self.verify(request, "list_submodules")?;
+        let submodules = self
+            .registry
+            .list()
+            .into_iter()
+            .map(admin::SubmoduleStatus::from)
+            .collect();
+        context.record(SUCCESS_CODE);
+        Ok(Response::new(SubmoduleList { submodules }))
+    }
+
+    async fn get_submodule(
+        &self,
+        request: Request<AdminReq>,
+    ) -> Result<Response<admin::SubmoduleStatus>, Status> {
+        let (admin_request, context) = self.verify(request, "get_submodule")?;
+        match self.registry.get(&admin_request.auth_id) {
+            Some(status) => {
+                context.record(SUCCESS_CODE);
+                Ok(Response::new(status.into()))
+            }
+            None => Err(not_found(&admin_request, &context)),
+        }
+    }
+
+    async fn force_offline(
+        &self,
+        request: Request<AdminReq>,
+    ) -> Result<Response<admin::SubmoduleStatus>, Status> {
+        let (admin_request, context) = self.verify(request, "force_offline")?;
+        let status = self.remove(&admin_request, &context)?;
+        self.force_offline(&status, &context);
+        context.record(SUCCESS_CODE);
+        Ok(Response::new(status.into()))
+    }
+
+    async fn revoke(
+        &self,
+        request: Request<AdminReq>,
+    ) -> Result<Response<admin::SubmoduleStatus>, Status> {
+        let (admin_request, context) = self.verify(request, "revoke")?;
+        let status = self.remove(&admin_request, &context)?;
+        self.force_offline(&status, &context);
+        if let Err(e) = self.authenticator.revoke(&status.auth_id) {
+            error!("Admin Server revoke {} Error: {:?}", &status.auth_id, e);
+        }
+        context
+            .audit(AuditEventKind::Revoke)
+            .submodule_name(&status.name)
+            .auth_id(&status.auth_id)
+            .emit();
+        context.record(SUCCESS_CODE);
+        Ok(Response::new(status.into()))
+    }
+
+    async fn dump_stats(&self, request: Request<AdminReq>) -> Result<Response<Stats>, Status> {
+        let (_, context) = self.verify(request, "dump_stats")?;
+        context.record(SUCCESS_CODE);
+        Ok(Response::new(Stats {
+            metrics: metrics().render(),
+        }))
+    }
+}
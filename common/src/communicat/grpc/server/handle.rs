@@ -0,0 +1,71 @@
+use std::net::SocketAddr;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tonic_health::server::HealthReporter;
+
+use crate::error::{NihilityCommonError, WrapResult};
+
+/// `GrpcServer::start`返回的句柄
+pub struct ServerHandle {
+    local_addrs: Vec<SocketAddr>,
+    ready: Option<oneshot::Receiver<()>>,
+    health_reporter: HealthReporter,
+    cancellation_token: CancellationToken,
+    task: JoinHandle<Result<(), tonic::transport::Error>>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(
+        local_addrs: Vec<SocketAddr>,
+        ready: oneshot::Receiver<()>,
+        health_reporter: HealthReporter,
+        cancellation_token: CancellationToken,
+        task: JoinHandle<Result<(), tonic::transport::Error>>,
+    ) -> Self {
+        ServerHandle {
+            local_addrs,
+            ready: Some(ready),
+            health_reporter,
+            cancellation_token,
+            task,
+        }
+    }
+
+    /// 第一个IP监听地址，端口为0时为系统分配的端口，仅监听Unix socket时为`None`
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addrs.first().copied()
+    }
+
+    /// 按配置顺序的全部IP监听地址
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    /// 各服务健康状态设置为`Serving`后返回`local_addr`
+    pub async fn ready(&mut self) -> WrapResult<Option<SocketAddr>> {
+        if let Some(ready) = self.ready.take() {
+            ready
+                .await
+                .map_err(|_| NihilityCommonError::ChannelClosed)?;
+        }
+        Ok(self.local_addr())
+    }
+
+    /// 启动时已启用的服务均为`Serving`，可通过此处更新各服务的健康状态
+    pub fn health_reporter(&self) -> HealthReporter {
+        self.health_reporter.clone()
+    }
+
+    /// 取消`CancellationToken`并等待停止完成
+    pub async fn shutdown(self) -> WrapResult<()> {
+        self.cancellation_token.cancel();
+        self.join().await
+    }
+
+    /// 等待服务端停止，停止后进行中的请求最多等待`drain_timeout_secs`，服务端异常退出时返回错误
+    pub async fn join(self) -> WrapResult<()> {
+        Ok(self.task.await??)
+    }
+}
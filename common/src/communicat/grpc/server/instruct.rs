@@ -1,29 +1,47 @@
+use std::sync::Arc;
+
 use tokio::spawn;
 use tokio::sync::mpsc;
-use tokio::sync::mpsc::UnboundedSender;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::codegen::tokio_stream::StreamExt;
-use tonic::{Code, Request, Response, Status, Streaming};
+use tonic::{Request, Response, Status, Streaming};
 use tracing::error;
 
-use crate::communicat::grpc::server::StreamResp;
+use crate::communicat::grpc::server::{
+    decrypt_failed_status, handle_request, limit_peer, RequestContext, StreamResp,
+};
+use crate::communicat::middleware::MiddlewareChain;
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::Deliver;
 use crate::entity::instruct::InstructEntity;
-use crate::entity::response::ResponseEntity;
 use crate::instruct::instruct_server::Instruct;
 use crate::instruct::TextInstruct;
 use crate::response_code::Resp;
-use crate::utils::auth::{
-    get_public_key, signature, verify, Signature, AUTHENTICATION_ERROR_MESSAGE,
-};
+use crate::utils::auth::Authenticator;
 
 #[derive(Clone)]
 pub struct InstructImpl {
-    instruct_sender: UnboundedSender<InstructEntity>,
+    authenticator: Arc<dyn Authenticator>,
+    middleware: Arc<MiddlewareChain>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    registry: Arc<SubmoduleRegistry>,
+    instruct_sender: Arc<dyn Deliver<InstructEntity>>,
 }
 
 impl InstructImpl {
-    pub fn init(sender: UnboundedSender<InstructEntity>) -> Self {
+    pub fn init(
+        authenticator: Arc<dyn Authenticator>,
+        middleware: Arc<MiddlewareChain>,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        registry: Arc<SubmoduleRegistry>,
+        sender: Arc<dyn Deliver<InstructEntity>>,
+    ) -> Self {
         InstructImpl {
+            authenticator,
+            middleware,
+            rate_limiter,
+            registry,
             instruct_sender: sender,
         }
     }
@@ -35,37 +53,28 @@ impl Instruct for InstructImpl {
         &self,
         request: Request<TextInstruct>,
     ) -> Result<Response<Resp>, Status> {
+        let context = RequestContext::new("send_text_instruct", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
         let mut buf = [0u8; 512];
-        let mut entity = InstructEntity::from(request.into_inner());
-        if verify(&mut entity, &mut buf) {
-            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-            match self.instruct_sender.send(entity) {
-                Ok(_) => match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        let mut resp = ResponseEntity::default();
-                        signature(&mut resp, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        Ok(Response::new(Resp::from(resp)))
-                    }
-                    Err(e) => {
-                        error!(
-                            "Grpc Instruct Server send_text_instruct Auth Id Error: {:?}",
-                            &e
-                        );
-                        Err(Status::from_error(Box::new(e)))
-                    }
-                },
-                Err(e) => {
-                    error!(
-                        "Grpc Instruct Server send_text_instruct Auth Id Error: {:?}",
-                        &e
-                    );
-                    Err(Status::from_error(Box::new(e)))
-                }
-            }
-        } else {
-            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+        let mut text_instruct = request.into_inner();
+        if let Err(e) = self.authenticator.decrypt_payload(&mut text_instruct) {
+            error!(
+                "Grpc Instruct Server send_text_instruct Decrypt Payload Error: {:?}",
+                &e
+            );
+            return Err(decrypt_failed_status());
         }
+        handle_request(
+            self.authenticator.as_ref(),
+            &self.middleware,
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            self.instruct_sender.as_ref(),
+            InstructEntity::from(text_instruct),
+            &context,
+            &mut buf,
+        )
+        .map(Response::new)
     }
 
     type SendMultipleTextInstructStream = StreamResp;
@@ -74,114 +83,55 @@ impl Instruct for InstructImpl {
         &self,
         request: Request<Streaming<TextInstruct>>,
     ) -> Result<Response<Self::SendMultipleTextInstructStream>, Status> {
+        let mut context = RequestContext::new("send_multiple_text_instruct", &request);
         let mut req_stream = request.into_inner();
         let (tx, rx) = mpsc::channel(128);
+        let authenticator = self.authenticator.clone();
+        let middleware = self.middleware.clone();
+        let registry = self.registry.clone();
+        let rate_limiter = self.rate_limiter.clone();
         let instruct_sender = self.instruct_sender.clone();
         spawn(async move {
             let mut buf = [0u8; 512];
             while let Some(result) = req_stream.next().await {
-                match result {
-                    Ok(instruct) => {
-                        let mut entity = InstructEntity::from(instruct);
-                        if verify(&mut entity, &mut buf) {
-                            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-                            match instruct_sender.send(entity) {
-                                Ok(_) => {
-                                    let mut resp = ResponseEntity::default();
-                                    match get_public_key(&auth_id).await {
-                                        Ok(public_key) => {
-                                            signature(&mut resp, &auth_id, public_key, &mut buf)
-                                                .expect("Encode Entity Error");
-                                            match tx.send(Ok(Resp::from(resp))).await {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "Manipulate Server send_multiple_text_display_manipulate Auth Id Error: {:?}",
-                                                &e
-                                            );
-                                            match tx
-                                                .send(Err(Status::from_error(Box::new(e))))
-                                                .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Core Error: {:?}", e);
-                                    match get_public_key(&auth_id).await {
-                                        Ok(public_key) => {
-                                            let mut resp = ResponseEntity::default();
-                                            resp.unknown_error();
-                                            signature(&mut resp, &auth_id, public_key, &mut buf)
-                                                .expect("Encode Entity Error");
-                                            match tx.send(Ok(Resp::from(resp))).await {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "Manipulate Server send_multiple_text_display_manipulate Auth Id Error: {:?}",
-                                                &e
-                                            );
-                                            match tx
-                                                .send(Err(Status::from_error(Box::new(e))))
-                                                .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            error!(
-                                "Instruct Server send_multiple_text_instruct Authentication Fail"
-                            );
-                            match tx
-                                .send(Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE)))
-                                .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                    break;
-                                }
+                context.restart();
+                let resp = match result {
+                    Ok(mut instruct) => match limit_peer(rate_limiter.as_deref(), &context) {
+                        Err(status) => Err(status),
+                        Ok(()) => match authenticator.decrypt_payload(&mut instruct) {
+                            Err(e) => {
+                                error!(
+                                    "Instruct Server send_multiple_text_instruct Decrypt Payload Error: {:?}",
+                                    &e
+                                );
+                                Err(decrypt_failed_status())
                             }
-                        }
-                    }
+                            Ok(()) => handle_request(
+                                authenticator.as_ref(),
+                                &middleware,
+                                rate_limiter.as_deref(),
+                                &registry,
+                                instruct_sender.as_ref(),
+                                InstructEntity::from(instruct),
+                                &context,
+                                &mut buf,
+                            ),
+                        },
+                    },
                     Err(e) => {
                         error!(
                             "Instruct Server send_multiple_text_instruct Receive Error: {:?}",
                             &e
                         );
-                        match tx.send(Err(e)).await {
-                            Ok(_) => {}
-                            Err(e) => {
-                                error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                break;
-                            }
-                        }
+                        Err(e)
                     }
+                };
+                if let Err(e) = tx.send(resp).await {
+                    error!(
+                        "Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}",
+                        e
+                    );
+                    break;
                 }
             }
         });
@@ -0,0 +1,76 @@
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tracing::info;
+#[cfg(unix)]
+use tracing::warn;
+
+use crate::communicat::grpc::config::BindAddr;
+use crate::error::WrapResult;
+
+const LISTEN_BACKLOG: i32 = 1024;
+
+pub(super) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// 在当前tokio运行时中绑定，IP地址均使用`port`
+pub(super) fn bind(bind_addr: &BindAddr, port: u16) -> WrapResult<Listener> {
+    match bind_addr {
+        BindAddr::Ip(ip) => {
+            let listener = TcpListener::from_std(bind_tcp(SocketAddr::new(*ip, port))?)?;
+            info!("Grpc Server Bind At {}", listener.local_addr()?);
+            Ok(Listener::Tcp(listener))
+        }
+        #[cfg(unix)]
+        BindAddr::Unix(path) => {
+            let listener = bind_unix(path)?;
+            info!("Grpc Server Bind At {}", bind_addr);
+            Ok(Listener::Unix(listener, path.to_path_buf()))
+        }
+        #[cfg(not(unix))]
+        BindAddr::Unix(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unix Socket Not Supported: {}", bind_addr),
+        )
+        .into()),
+    }
+}
+
+/// IPv6地址设置`IPV6_V6ONLY`，同一端口可再绑定IPv4地址
+fn bind_tcp(addr: SocketAddr) -> io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+/// 上次异常退出残留的socket文件无法连接，删除后重新绑定
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    match UnixListener::bind(path) {
+        Err(e)
+            if e.kind() == io::ErrorKind::AddrInUse
+                && std::os::unix::net::UnixStream::connect(path).is_err() =>
+        {
+            warn!("Remove Stale Unix Socket {:?}", path);
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        result => result,
+    }
+}
@@ -1,24 +1,32 @@
+use std::sync::Arc;
+
 use tokio::spawn;
 use tokio::sync::mpsc;
-use tokio::sync::mpsc::UnboundedSender;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
-use tonic::{Code, Request, Response, Status, Streaming};
+use tonic::{Request, Response, Status, Streaming};
 use tracing::error;
 
-use crate::communicat::grpc::server::StreamResp;
+use crate::communicat::grpc::server::{
+    decrypt_failed_status, handle_request, limit_peer, RequestContext, StreamResp,
+};
+use crate::communicat::middleware::MiddlewareChain;
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::Deliver;
 use crate::entity::manipulate::ManipulateEntity;
-use crate::entity::response::ResponseEntity;
 use crate::manipulate::manipulate_server::Manipulate;
 use crate::manipulate::{DirectConnectionManipulate, SimpleManipulate, TextDisplayManipulate};
 use crate::response_code::Resp;
-use crate::utils::auth::{
-    get_public_key, signature, verify, Signature, AUTHENTICATION_ERROR_MESSAGE,
-};
+use crate::utils::auth::Authenticator;
 
 #[derive(Clone)]
 pub struct ManipulateImpl {
-    manipulate_sender: UnboundedSender<ManipulateEntity>,
+    authenticator: Arc<dyn Authenticator>,
+    middleware: Arc<MiddlewareChain>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    registry: Arc<SubmoduleRegistry>,
+    manipulate_sender: Arc<dyn Deliver<ManipulateEntity>>,
 }
 
 #[tonic::async_trait]
@@ -27,74 +35,51 @@ impl Manipulate for ManipulateImpl {
         &self,
         request: Request<SimpleManipulate>,
     ) -> Result<Response<Resp>, Status> {
+        let context = RequestContext::new("send_simple_manipulate", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
         let mut buf = [0u8; 512];
-        let mut entity = ManipulateEntity::from(request.into_inner());
-        if verify(&mut entity, &mut buf) {
-            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-            match self.manipulate_sender.send(entity) {
-                Ok(_) => match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        let mut resp = ResponseEntity::default();
-                        signature(&mut resp, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        Ok(Response::new(Resp::from(resp)))
-                    }
-                    Err(e) => {
-                        error!(
-                            "Grpc Manipulate Server send_simple_manipulate Error: {:?}",
-                            &e
-                        );
-                        Err(Status::from_error(Box::new(e)))
-                    }
-                },
-                Err(e) => {
-                    error!(
-                        "Grpc Manipulate Server send_simple_manipulate Auth Id Error: {:?}",
-                        &e
-                    );
-                    Err(Status::from_error(Box::new(e)))
-                }
-            }
-        } else {
-            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
-        }
+        handle_request(
+            self.authenticator.as_ref(),
+            &self.middleware,
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            self.manipulate_sender.as_ref(),
+            ManipulateEntity::from(request.into_inner()),
+            &context,
+            &mut buf,
+        )
+        .map(Response::new)
     }
 
     async fn send_text_display_manipulate(
         &self,
         request: Request<TextDisplayManipulate>,
     ) -> Result<Response<Resp>, Status> {
+        let context = RequestContext::new("send_text_display_manipulate", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
         let mut buf = [0u8; 512];
-        let mut entity = ManipulateEntity::from(request.into_inner());
-        if verify(&mut entity, &mut buf) {
-            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-            match self.manipulate_sender.send(entity) {
-                Ok(_) => match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        let mut resp = ResponseEntity::default();
-                        signature(&mut resp, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        Ok(Response::new(Resp::from(resp)))
-                    }
-                    Err(e) => {
-                        error!(
-                            "Grpc Manipulate Server send_text_display_manipulate Error: {:?}",
-                            &e
-                        );
-                        Err(Status::from_error(Box::new(e)))
-                    }
-                },
-                Err(e) => {
-                    error!(
-                        "Grpc Manipulate Server send_text_display_manipulate Auth Id Error: {:?}",
-                        &e
-                    );
-                    Err(Status::from_error(Box::new(e)))
-                }
-            }
-        } else {
-            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+        let mut text_display_manipulate = request.into_inner();
+        if let Err(e) = self
+            .authenticator
+            .decrypt_payload(&mut text_display_manipulate)
+        {
+            error!(
+                "Grpc Manipulate Server send_text_display_manipulate Decrypt Payload Error: {:?}",
+                &e
+            );
+            return Err(decrypt_failed_status());
         }
+        handle_request(
+            self.authenticator.as_ref(),
+            &self.middleware,
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            self.manipulate_sender.as_ref(),
+            ManipulateEntity::from(text_display_manipulate),
+            &context,
+            &mut buf,
+        )
+        .map(Response::new)
     }
 
     type SendMultipleTextDisplayManipulateStream = StreamResp;
@@ -103,114 +88,52 @@ impl Manipulate for ManipulateImpl {
         &self,
         request: Request<Streaming<TextDisplayManipulate>>,
     ) -> Result<Response<Self::SendMultipleTextDisplayManipulateStream>, Status> {
+        let mut context = RequestContext::new("send_multiple_text_display_manipulate", &request);
         let mut req_stream = request.into_inner();
         let (tx, rx) = mpsc::channel(128);
+        let authenticator = self.authenticator.clone();
+        let middleware = self.middleware.clone();
+        let registry = self.registry.clone();
+        let rate_limiter = self.rate_limiter.clone();
         let manipulate_sender = self.manipulate_sender.clone();
         spawn(async move {
             let mut buf = [0u8; 512];
             while let Some(result) = req_stream.next().await {
-                match result {
-                    Ok(manipulate) => {
-                        let mut entity = ManipulateEntity::from(manipulate);
-                        if verify(&mut entity, &mut buf) {
-                            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-                            match manipulate_sender.send(entity) {
-                                Ok(_) => {
-                                    let mut resp = ResponseEntity::default();
-                                    match get_public_key(&auth_id).await {
-                                        Ok(public_key) => {
-                                            signature(&mut resp, &auth_id, public_key, &mut buf)
-                                                .expect("Encode Entity Error");
-                                            match tx.send(Ok(Resp::from(resp))).await {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "Instruct Server send_multiple_text_instruct Auth Id Error: {:?}",
-                                                &e
-                                            );
-                                            match tx
-                                                .send(Err(Status::from_error(Box::new(e))))
-                                                .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Instruct Server send_multiple_text_instruct Send To Core Error: {:?}", e);
-                                    match get_public_key(&auth_id).await {
-                                        Ok(public_key) => {
-                                            let mut resp = ResponseEntity::default();
-                                            resp.unknown_error();
-                                            signature(&mut resp, &auth_id, public_key, &mut buf)
-                                                .expect("Encode Entity Error");
-                                            match tx.send(Ok(Resp::from(resp))).await {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "Instruct Server send_multiple_text_instruct Auth Id Error: {:?}",
-                                                &e
-                                            );
-                                            match tx
-                                                .send(Err(Status::from_error(Box::new(e))))
-                                                .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            error!(
-                                "Instruct Server send_multiple_text_instruct Authentication Fail"
-                            );
-                            match tx
-                                .send(Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE)))
-                                .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Instruct Server send_multiple_text_instruct Send To Stream Error: {:?}", e);
-                                    break;
-                                }
+                context.restart();
+                let resp = match result {
+                    Ok(mut manipulate) => match limit_peer(rate_limiter.as_deref(), &context) {
+                        Err(status) => Err(status),
+                        Ok(()) => match authenticator.decrypt_payload(&mut manipulate) {
+                            Err(e) => {
+                                error!(
+                                    "Manipulate Server send_multiple_text_display_manipulate Decrypt Payload Error: {:?}",
+                                    &e
+                                );
+                                Err(decrypt_failed_status())
                             }
-                        }
-                    }
+                            Ok(()) => handle_request(
+                                authenticator.as_ref(),
+                                &middleware,
+                                rate_limiter.as_deref(),
+                                &registry,
+                                manipulate_sender.as_ref(),
+                                ManipulateEntity::from(manipulate),
+                                &context,
+                                &mut buf,
+                            ),
+                        },
+                    },
                     Err(e) => {
                         error!(
                             "Manipulate Server send_multiple_text_display_manipulate Receive Error: {:?}",
                             &e
                         );
-                        match tx.send(Err(e)).await {
-                            Ok(_) => {}
-                            Err(e) => {
-                                error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
-                                break;
-                            }
-                        }
+                        Err(e)
                     }
+                };
+                if let Err(e) = tx.send(resp).await {
+                    error!("Manipulate Server send_multiple_text_display_manipulate Send To Stream Error: {:?}", e);
+                    break;
                 }
             }
         });
@@ -223,38 +146,22 @@ impl Manipulate for ManipulateImpl {
         &self,
         request: Request<DirectConnectionManipulate>,
     ) -> Result<Response<Resp>, Status> {
+        let context = RequestContext::new("send_direct_connection_manipulate", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
         match ManipulateEntity::try_from(request.into_inner()) {
-            Ok(mut entity) => {
+            Ok(entity) => {
                 let mut buf = [0u8; 512];
-                if verify(&mut entity, &mut buf) {
-                    let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-                    match self.manipulate_sender.send(entity) {
-                        Ok(_) => match get_public_key(&auth_id).await {
-                            Ok(public_key) => {
-                                let mut resp = ResponseEntity::default();
-                                signature(&mut resp, &auth_id, public_key, &mut buf)
-                                    .expect("Encode Entity Error");
-                                Ok(Response::new(Resp::from(resp)))
-                            }
-                            Err(e) => {
-                                error!(
-                    "Grpc Manipulate Server send_direct_connection_manipulate Error: {:?}",
-                    &e
-                );
-                                Err(Status::from_error(Box::new(e)))
-                            }
-                        },
-                        Err(e) => {
-                            error!(
-                        "Grpc Manipulate Server send_direct_connection_manipulate Auth Id Error: {:?}",
-                        &e
-                    );
-                            Err(Status::from_error(Box::new(e)))
-                        }
-                    }
-                } else {
-                    Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
-                }
+                handle_request(
+                    self.authenticator.as_ref(),
+                    &self.middleware,
+                    self.rate_limiter.as_deref(),
+                    &self.registry,
+                    self.manipulate_sender.as_ref(),
+                    entity,
+                    &context,
+                    &mut buf,
+                )
+                .map(Response::new)
             }
             Err(e) => {
                 error!(
@@ -268,8 +175,18 @@ impl Manipulate for ManipulateImpl {
 }
 
 impl ManipulateImpl {
-    pub fn init(sender: UnboundedSender<ManipulateEntity>) -> Self {
+    pub fn init(
+        authenticator: Arc<dyn Authenticator>,
+        middleware: Arc<MiddlewareChain>,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        registry: Arc<SubmoduleRegistry>,
+        sender: Arc<dyn Deliver<ManipulateEntity>>,
+    ) -> Self {
         ManipulateImpl {
+            authenticator,
+            middleware,
+            rate_limiter,
+            registry,
             manipulate_sender: sender,
         }
     }
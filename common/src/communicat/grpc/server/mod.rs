@@ -1,41 +1,102 @@
-use std::net::IpAddr;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use tokio::spawn;
-use tokio::sync::mpsc::UnboundedSender;
+use futures_util::future::try_join_all;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use tokio::{pin, select, spawn};
+use tokio_stream::wrappers::TcpListenerStream;
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
 use tokio_util::sync::CancellationToken;
 use tonic::codegen::tokio_stream::Stream;
+use tonic::server::NamedService;
 use tonic::transport::Server;
-use tonic::Status;
-use tracing::{error, info};
+use tonic::{Code, Request, Status};
+use tonic_health::server::health_reporter;
+use tonic_health::ServingStatus;
+use tracing::{error, info, warn};
 
-use crate::communicat::grpc::config::GrpcServerConfig;
+use crate::admin::admin_server::AdminServer;
+use crate::communicat::discovery::DiscoveryInfo;
+use crate::communicat::grpc::config::{connection_params, GrpcServerConfig};
+use crate::communicat::grpc::server::admin::AdminImpl;
 use crate::communicat::grpc::server::instruct::InstructImpl;
 use crate::communicat::grpc::server::manipulate::ManipulateImpl;
 use crate::communicat::grpc::server::module_operate::SubmoduleImpl;
+use crate::communicat::grpc::server::session::SessionImpl;
+use crate::communicat::grpc::server::subscribe::SubscribeImpl;
+use crate::communicat::grpc::TRACEPARENT_METADATA;
+use crate::communicat::matcher::InstructMatcher;
+use crate::communicat::middleware::{Intercept, Middleware, MiddlewareChain, MiddlewareContext};
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::Deliver;
+use crate::communicat::subscriber::SubscriberHub;
+use crate::communicat::trace::{TraceContext, Traced};
 use crate::communicat::NihilityServer;
 use crate::entity::instruct::InstructEntity;
 use crate::entity::manipulate::ManipulateEntity;
 use crate::entity::module_operate::ModuleOperate;
+use crate::entity::response::ResponseEntity;
 use crate::error::WrapResult;
 use crate::instruct::instruct_server::InstructServer;
 use crate::manipulate::manipulate_server::ManipulateServer;
 use crate::response_code::Resp;
+use crate::session::session_server::SessionServer;
 use crate::submodule::submodule_server::SubmoduleServer;
+use crate::subscribe::subscribe_server::SubscribeServer;
+use crate::utils::audit::{AuditEvent, AuditEventKind};
+use crate::utils::auth::{
+    default_authenticator, key_fingerprint, signature, verify, AuthenticationMode, Authenticator,
+    Identity, Signature, AUTHENTICATION_ERROR_MESSAGE, CORE_PUBLIC_KEY_FILE_NAME,
+};
+use crate::utils::metrics::{metrics, MetricsSide};
 
+pub use handle::ServerHandle;
+use listener::Listener;
+
+mod admin;
+mod handle;
 mod instruct;
+mod listener;
 mod manipulate;
 mod module_operate;
+mod session;
+mod subscribe;
+
+const RATE_LIMITED_MESSAGE: &str = "Rate Limit Exceeded";
+const DECRYPT_FAILED_MESSAGE: &str = "Payload Decrypt Failed";
+const TOO_MANY_REQUESTS_CODE: &str = "TooManyRequests";
+const UNKNOWN_SUBMODULE: &str = "unknown";
+const AUTHENTICATION_FAIL_CODE: &str = "AuthenticationFail";
 
 type StreamResp = Pin<Box<dyn Stream<Item = Result<Resp, Status>> + Send>>;
 
+type ServeFuture = Pin<Box<dyn Future<Output = Result<(), tonic::transport::Error>> + Send>>;
+
 pub struct GrpcServer {
     server_config: GrpcServerConfig,
     cancellation_token: CancellationToken,
-    submodule_operate_server: Option<SubmoduleServer<SubmoduleImpl>>,
-    instruct_server: Option<InstructServer<InstructImpl>>,
-    manipulate_server: Option<ManipulateServer<ManipulateImpl>>,
+    authenticator: Arc<dyn Authenticator>,
+    submodule_operate_deliver: Option<Arc<dyn Deliver<ModuleOperate>>>,
+    instruct_deliver: Option<Arc<dyn Deliver<InstructEntity>>>,
+    manipulate_deliver: Option<Arc<dyn Deliver<ManipulateEntity>>>,
+    instruct_matcher: Option<Arc<InstructMatcher>>,
+    subscriber_hub: Option<Arc<SubscriberHub>>,
+    middleware: MiddlewareChain,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    registry: Arc<SubmoduleRegistry>,
+    admin_public_key: Option<RsaPublicKey>,
+    /// 发现文件路径与核心模块公钥指纹
+    discovery: Option<(PathBuf, String)>,
 }
 
 impl GrpcServer {
@@ -46,64 +107,562 @@ impl GrpcServer {
         GrpcServer {
             server_config: grpc_server_config,
             cancellation_token,
-            submodule_operate_server: None,
-            instruct_server: None,
-            manipulate_server: None,
+            authenticator: default_authenticator(),
+            submodule_operate_deliver: None,
+            instruct_deliver: None,
+            manipulate_deliver: None,
+            instruct_matcher: None,
+            subscriber_hub: None,
+            middleware: MiddlewareChain::default(),
+            rate_limiter: None,
+            registry: Arc::new(SubmoduleRegistry::default()),
+            admin_public_key: None,
+            discovery: None,
         }
     }
 }
 
 #[async_trait]
 impl NihilityServer for GrpcServer {
-    fn set_submodule_operate_sender(
+    fn set_authenticator(&mut self, authenticator: Arc<dyn Authenticator>) -> WrapResult<()> {
+        self.authenticator = authenticator;
+        Ok(())
+    }
+
+    fn set_submodule_operate_deliver(
         &mut self,
-        submodule_sender: UnboundedSender<ModuleOperate>,
+        submodule_deliver: Arc<dyn Deliver<ModuleOperate>>,
     ) -> WrapResult<()> {
-        self.submodule_operate_server =
-            Some(SubmoduleServer::new(SubmoduleImpl::init(submodule_sender)));
+        self.submodule_operate_deliver = Some(submodule_deliver);
         Ok(())
     }
 
-    fn set_instruct_sender(
+    fn set_instruct_deliver(
         &mut self,
-        instruct_sender: UnboundedSender<InstructEntity>,
+        instruct_deliver: Arc<dyn Deliver<InstructEntity>>,
     ) -> WrapResult<()> {
-        self.instruct_server = Some(InstructServer::new(InstructImpl::init(instruct_sender)));
+        self.instruct_deliver = Some(instruct_deliver);
         Ok(())
     }
 
-    fn set_manipulate_sender(
+    fn set_manipulate_deliver(
         &mut self,
-        manipulate_sender: UnboundedSender<ManipulateEntity>,
+        manipulate_deliver: Arc<dyn Deliver<ManipulateEntity>>,
     ) -> WrapResult<()> {
-        self.manipulate_server = Some(ManipulateServer::new(ManipulateImpl::init(
-            manipulate_sender,
-        )));
+        self.manipulate_deliver = Some(manipulate_deliver);
+        Ok(())
+    }
+
+    fn set_instruct_matcher(&mut self, instruct_matcher: Arc<InstructMatcher>) -> WrapResult<()> {
+        self.instruct_matcher = Some(instruct_matcher);
+        Ok(())
+    }
+
+    fn set_subscriber_hub(&mut self, subscriber_hub: Arc<SubscriberHub>) -> WrapResult<()> {
+        self.subscriber_hub = Some(subscriber_hub);
+        Ok(())
+    }
+
+    fn set_rate_limiter(&mut self, rate_limiter: Arc<RateLimiter>) -> WrapResult<()> {
+        self.rate_limiter = Some(rate_limiter);
+        Ok(())
+    }
+
+    fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) -> WrapResult<()> {
+        self.middleware.push(middleware);
+        Ok(())
+    }
+
+    fn set_submodule_registry(&mut self, registry: Arc<SubmoduleRegistry>) -> WrapResult<()> {
+        self.registry = registry;
+        Ok(())
+    }
+
+    fn enable_admin(&mut self, core_key_dir: &str) -> WrapResult<()> {
+        let public_key_path = Path::new(core_key_dir).join(CORE_PUBLIC_KEY_FILE_NAME);
+        self.admin_public_key = Some(RsaPublicKey::read_public_key_pem_file(public_key_path)?);
+        Ok(())
+    }
+
+    fn enable_discovery(&mut self, core_key_dir: &str, discovery_path: &Path) -> WrapResult<()> {
+        let public_key_path = Path::new(core_key_dir).join(CORE_PUBLIC_KEY_FILE_NAME);
+        let core_public_key = RsaPublicKey::read_public_key_pem_file(public_key_path)?;
+        self.discovery = Some((
+            discovery_path.to_path_buf(),
+            key_fingerprint(&core_public_key)?,
+        ));
         Ok(())
     }
 
-    fn start(&mut self) -> WrapResult<()> {
-        let bind_addr = match self.server_config.bind_ip {
-            IpAddr::V4(ip) => format!("{}:{}", ip, self.server_config.bind_port),
-            IpAddr::V6(ip) => format!("[{}]:{}", ip, self.server_config.bind_port),
+    fn start(&mut self) -> WrapResult<ServerHandle> {
+        let listeners = self
+            .server_config
+            .bind_addrs
+            .iter()
+            .map(|bind_addr| listener::bind(bind_addr, self.server_config.bind_port))
+            .collect::<WrapResult<Vec<Listener>>>()?;
+        let mut local_addrs = Vec::new();
+        let mut bound_addrs = Vec::new();
+        for (bind_addr, listener) in self.server_config.bind_addrs.iter().zip(&listeners) {
+            match listener {
+                Listener::Tcp(listener) => {
+                    let local_addr = listener.local_addr()?;
+                    local_addrs.push(local_addr);
+                    bound_addrs.push((bind_addr, local_addr.port()));
+                }
+                #[cfg(unix)]
+                Listener::Unix(..) => bound_addrs.push((bind_addr, 0)),
+            }
+        }
+        let discovery_path = match &self.discovery {
+            Some((discovery_path, core_key_fingerprint)) => {
+                DiscoveryInfo {
+                    connection_params: connection_params(bound_addrs),
+                    core_key_fingerprint: core_key_fingerprint.to_string(),
+                    pid: std::process::id(),
+                }
+                .write(discovery_path)?;
+                Some(discovery_path.clone())
+            }
+            None => None,
         };
-        info!("Grpc Server Bind At {}", &bind_addr);
-        let server_cancellation_token = self.cancellation_token.clone();
-        let server = Server::builder()
-            .add_optional_service(self.submodule_operate_server.clone())
-            .add_optional_service(self.instruct_server.clone())
-            .add_optional_service(self.manipulate_server.clone())
-            .serve_with_shutdown(bind_addr.parse()?, async move {
-                server_cancellation_token.cancelled().await
+        let middleware = Arc::new(self.middleware.clone());
+        let submodule_impl = self.submodule_operate_deliver.clone().map(|deliver| {
+            SubmoduleImpl::init(
+                self.authenticator.clone(),
+                middleware.clone(),
+                self.rate_limiter.clone(),
+                self.registry.clone(),
+                deliver,
+                self.instruct_matcher.clone(),
+                self.subscriber_hub.clone(),
+            )
+        });
+        let instruct_impl = self.instruct_deliver.clone().map(|deliver| {
+            InstructImpl::init(
+                self.authenticator.clone(),
+                middleware.clone(),
+                self.rate_limiter.clone(),
+                self.registry.clone(),
+                deliver,
+            )
+        });
+        let manipulate_impl = self.manipulate_deliver.clone().map(|deliver| {
+            ManipulateImpl::init(
+                self.authenticator.clone(),
+                middleware.clone(),
+                self.rate_limiter.clone(),
+                self.registry.clone(),
+                deliver,
+            )
+        });
+        let subscribe_impl = self.subscriber_hub.clone().map(|hub| {
+            SubscribeImpl::init(
+                self.authenticator.clone(),
+                self.rate_limiter.clone(),
+                self.registry.clone(),
+                hub,
+                self.server_config.payload_encryption,
+            )
+        });
+        let admin_impl = self.admin_public_key.clone().map(|core_public_key| {
+            AdminImpl::init(
+                core_public_key,
+                self.authenticator.clone(),
+                self.registry.clone(),
+                self.submodule_operate_deliver.clone(),
+                self.instruct_matcher.clone(),
+                self.subscriber_hub.clone(),
+            )
+        });
+        let services: Vec<&'static str> = [
+            submodule_impl
+                .as_ref()
+                .map(|_| SubmoduleServer::<SubmoduleImpl>::NAME),
+            instruct_impl
+                .as_ref()
+                .map(|_| InstructServer::<InstructImpl>::NAME),
+            manipulate_impl
+                .as_ref()
+                .map(|_| ManipulateServer::<ManipulateImpl>::NAME),
+            subscribe_impl
+                .as_ref()
+                .map(|_| SubscribeServer::<SubscribeImpl>::NAME),
+            admin_impl.as_ref().map(|_| AdminServer::<AdminImpl>::NAME),
+            Some(SessionServer::<SessionImpl>::NAME),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let (health_reporter, health_service) = health_reporter();
+        let session_server = SessionServer::new(SessionImpl::init(
+            submodule_impl.clone(),
+            instruct_impl.clone(),
+            manipulate_impl.clone(),
+            subscribe_impl.clone(),
+        ));
+        let router = || {
+            Server::builder()
+                .add_service(health_service.clone())
+                .add_optional_service(submodule_impl.clone().map(SubmoduleServer::new))
+                .add_optional_service(instruct_impl.clone().map(InstructServer::new))
+                .add_optional_service(manipulate_impl.clone().map(ManipulateServer::new))
+                .add_optional_service(subscribe_impl.clone().map(SubscribeServer::new))
+                .add_optional_service(admin_impl.clone().map(AdminServer::new))
+                .add_service(session_server.clone())
+        };
+        let mut servers: Vec<ServeFuture> = Vec::new();
+        #[cfg(unix)]
+        let mut unix_paths = Vec::new();
+        for listener in listeners {
+            let shutdown = self.cancellation_token.clone().cancelled_owned();
+            servers.push(match listener {
+                Listener::Tcp(listener) => Box::pin(
+                    router()
+                        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown),
+                ),
+                #[cfg(unix)]
+                Listener::Unix(listener, path) => {
+                    unix_paths.push(path);
+                    Box::pin(
+                        router().serve_with_incoming_shutdown(
+                            UnixListenerStream::new(listener),
+                            shutdown,
+                        ),
+                    )
+                }
             });
+        }
         let cancellation_token = self.cancellation_token.clone();
-        spawn(async move {
-            if let Err(e) = server.await {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let mut reporter = health_reporter.clone();
+        let drain_timeout = Duration::from_secs(self.server_config.drain_timeout_secs);
+        let task = spawn(async move {
+            for service in &services {
+                reporter
+                    .set_service_status(service, ServingStatus::Serving)
+                    .await;
+            }
+            let _ = ready_tx.send(());
+            let server = try_join_all(servers);
+            pin!(server);
+            let result = select! {
+                result = &mut server => result.map(|_| ()),
+                _ = cancellation_token.cancelled() => {
+                    for service in &services {
+                        reporter
+                            .set_service_status(service, ServingStatus::NotServing)
+                            .await;
+                    }
+                    info!("Grpc Server Draining In {:?}", drain_timeout);
+                    match timeout(drain_timeout, &mut server).await {
+                        Ok(result) => result.map(|_| ()),
+                        Err(_) => {
+                            warn!("Grpc Server Drain Timeout, Drop Remaining Requests");
+                            Ok(())
+                        }
+                    }
+                }
+            };
+            if let Err(e) = &result {
                 error!("Grpc Server Error: {}", e);
                 cancellation_token.cancel();
             }
-            info!("Grpc Server Stop")
+            #[cfg(unix)]
+            for path in unix_paths {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Remove Unix Socket {:?} Error: {}", path, e);
+                }
+            }
+            if let Some(discovery_path) = discovery_path {
+                DiscoveryInfo::remove(&discovery_path);
+            }
+            info!("Grpc Server Stop");
+            result
         });
-        Ok(())
+        Ok(ServerHandle::new(
+            local_addrs,
+            ready_rx,
+            health_reporter,
+            self.cancellation_token.clone(),
+            task,
+        ))
+    }
+}
+
+/// 请求的RPC名称与来源地址，用于日志与审计
+pub(crate) struct RequestContext {
+    rpc: &'static str,
+    peer_addr: Option<SocketAddr>,
+    /// 签名校验通过后该auth_id注册的子模块名称，流式请求中每个实体重新确定
+    verified_name: OnceLock<String>,
+    /// 请求元数据中的追踪上下文，缺省时开始新的追踪，流式请求中各实体相同
+    trace: TraceContext,
+    started: Instant,
+}
+
+impl RequestContext {
+    fn new<T>(rpc: &'static str, request: &Request<T>) -> Self {
+        let peer_addr = request.remote_addr();
+        let trace = request
+            .metadata()
+            .get(TRACEPARENT_METADATA)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceContext::from_traceparent)
+            .unwrap_or_else(TraceContext::new_root);
+        RequestContext {
+            rpc,
+            peer_addr,
+            verified_name: OnceLock::new(),
+            trace,
+            started: Instant::now(),
+        }
+    }
+
+    /// 流式请求中每个实体单独计时
+    fn restart(&mut self) {
+        self.started = Instant::now();
+        self.verified_name = OnceLock::new();
+    }
+
+    fn verified(&self, submodule_name: &str) {
+        let _ = self.verified_name.set(submodule_name.to_string());
+    }
+
+    /// 限速按校验通过的子模块名称计数，无法确定时使用来源IP
+    fn rate_limit_key(&self) -> String {
+        match self.verified_name.get() {
+            Some(name) => name.to_string(),
+            None => self
+                .peer_addr
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|| String::from(UNKNOWN_SUBMODULE)),
+        }
+    }
+
+    /// 统计时使用校验通过的子模块名称，校验前为`unknown`，不采信客户端的声明
+    fn submodule_label(&self) -> &str {
+        self.verified_name
+            .get()
+            .map(String::as_str)
+            .unwrap_or(UNKNOWN_SUBMODULE)
+    }
+
+    fn record(&self, code: &str) {
+        metrics().record_request(MetricsSide::Server, self.rpc, self.submodule_label(), code);
+        metrics().observe_duration(MetricsSide::Server, self.rpc, self.started.elapsed());
+    }
+
+    fn record_verification_failure(&self) {
+        metrics().record_verification_failure(self.rpc, self.submodule_label());
+        self.record(AUTHENTICATION_FAIL_CODE);
+    }
+
+    fn audit(&self, kind: AuditEventKind) -> AuditEvent {
+        AuditEvent::new(kind, self.rpc, self.peer_addr)
+    }
+
+    fn middleware_context(&self, auth_id: &str) -> MiddlewareContext {
+        MiddlewareContext::new(self.rpc, self.peer_addr, auth_id)
+    }
+}
+
+/// 在签名校验与载荷解密前按来源IP检查，无来源IP时（如unix socket）跳过
+#[allow(clippy::result_large_err)]
+fn limit_peer(rate_limiter: Option<&RateLimiter>, context: &RequestContext) -> Result<(), Status> {
+    let (Some(rate_limiter), Some(peer_addr)) = (rate_limiter, context.peer_addr) else {
+        return Ok(());
+    };
+    if rate_limiter.check_peer(peer_addr.ip(), context.rpc) {
+        return Ok(());
+    }
+    warn!(
+        "Grpc Server {} Rate Limited Before Verification: {}",
+        context.rpc,
+        peer_addr.ip()
+    );
+    context.record(TOO_MANY_REQUESTS_CODE);
+    Err(rate_limited_status())
+}
+
+/// 在校验签名后按子模块名称检查，用于按子模块统计与限制，超出限制时记录日志
+fn is_rate_limited(rate_limiter: Option<&RateLimiter>, context: &RequestContext) -> bool {
+    let Some(rate_limiter) = rate_limiter else {
+        return false;
+    };
+    let key = context.rate_limit_key();
+    if rate_limiter.check(&key, context.rpc) {
+        return false;
+    }
+    warn!("Grpc Server {} Rate Limited: {}", context.rpc, &key);
+    context.record(TOO_MANY_REQUESTS_CODE);
+    true
+}
+
+/// 按auth_id注册的子模块名称限速，未注册时按来源IP限速
+#[allow(clippy::result_large_err)]
+fn limit_verified(
+    rate_limiter: Option<&RateLimiter>,
+    registry: &SubmoduleRegistry,
+    identity: &Identity,
+    context: &RequestContext,
+) -> Result<(), Status> {
+    if let Some(status) = registry.get(&identity.auth_id) {
+        context.verified(&status.name);
+    }
+    if is_rate_limited(rate_limiter, context) {
+        return Err(rate_limited_status());
+    }
+    Ok(())
+}
+
+fn rate_limited_status() -> Status {
+    Status::resource_exhausted(RATE_LIMITED_MESSAGE)
+}
+
+/// 加密载荷无法解密时拒绝请求，不再继续校验与投递
+fn decrypt_failed_status() -> Status {
+    Status::invalid_argument(DECRYPT_FAILED_MESSAGE)
+}
+
+/// 校验请求实体并经中间件处理后发送至核心模块，返回按请求方身份签名的响应
+///
+/// 在请求追踪上下文的span中处理，发送的实体携带请求的trace_id；调用前需先经过`limit_peer`
+#[allow(clippy::result_large_err, clippy::too_many_arguments)]
+fn handle_request<E: Signature + Intercept + Traced>(
+    authenticator: &dyn Authenticator,
+    middleware: &MiddlewareChain,
+    rate_limiter: Option<&RateLimiter>,
+    registry: &SubmoduleRegistry,
+    deliver: &dyn Deliver<E>,
+    mut entity: E,
+    context: &RequestContext,
+    buf: &mut [u8],
+) -> Result<Resp, Status> {
+    let _entered = context.trace.span(context.rpc).entered();
+    let identity = verify_request(authenticator, &mut entity, context, buf)?;
+    limit_verified(rate_limiter, registry, &identity, context)?;
+    entity.set_trace_id(&context.trace.trace_id);
+    if let Some(resp) = intercept_request(middleware, &mut entity, &identity, context) {
+        return respond(authenticator, middleware, resp, &identity, context, buf);
+    }
+    forward_request(
+        authenticator,
+        middleware,
+        registry,
+        deliver,
+        entity,
+        &identity,
+        context,
+        buf,
+    )
+}
+
+/// 中间件拒绝请求时返回无法处理的响应
+fn intercept_request<E: Intercept>(
+    middleware: &MiddlewareChain,
+    entity: &mut E,
+    identity: &Identity,
+    context: &RequestContext,
+) -> Option<ResponseEntity> {
+    match middleware.before(&context.middleware_context(&identity.auth_id), entity) {
+        Ok(_) => None,
+        Err(e) => {
+            info!(
+                "Grpc Server {} Rejected By Middleware: {:?}",
+                context.rpc, e
+            );
+            let mut resp = ResponseEntity::default();
+            resp.unable_to_process();
+            Some(resp)
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn verify_request<E: Signature>(
+    authenticator: &dyn Authenticator,
+    entity: &mut E,
+    context: &RequestContext,
+    buf: &mut [u8],
+) -> Result<Identity, Status> {
+    match verify(authenticator, entity, buf) {
+        Some(identity) if authenticator.is_revoked(&identity.auth_id) => {
+            context
+                .audit(AuditEventKind::RevokedAuthId)
+                .auth_id(&identity.auth_id)
+                .emit();
+            context.record_verification_failure();
+            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+        }
+        Some(identity) => Ok(identity),
+        None => {
+            context.audit(AuditEventKind::AuthenticationFail).emit();
+            context.record_verification_failure();
+            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+        }
+    }
+}
+
+/// 发送成功后计入子模块的消息统计
+#[allow(clippy::result_large_err, clippy::too_many_arguments)]
+fn forward_request<E>(
+    authenticator: &dyn Authenticator,
+    middleware: &MiddlewareChain,
+    registry: &SubmoduleRegistry,
+    deliver: &dyn Deliver<E>,
+    entity: E,
+    identity: &Identity,
+    context: &RequestContext,
+    buf: &mut [u8],
+) -> Result<Resp, Status> {
+    let mut resp = ResponseEntity::default();
+    match deliver.deliver(entity) {
+        Ok(_) => registry.record_message(&identity.auth_id, context.rpc),
+        Err(e) => {
+            error!("Grpc Server {} Send To Core Error: {:?}", context.rpc, e);
+            resp.unknown_error();
+        }
+    }
+    respond(authenticator, middleware, resp, identity, context, buf)
+}
+
+/// 执行中间件的`after`后签名响应
+#[allow(clippy::result_large_err)]
+fn respond(
+    authenticator: &dyn Authenticator,
+    middleware: &MiddlewareChain,
+    resp: ResponseEntity,
+    identity: &Identity,
+    context: &RequestContext,
+    buf: &mut [u8],
+) -> Result<Resp, Status> {
+    middleware.after(&context.middleware_context(&identity.auth_id), &resp);
+    context.record(&format!("{:?}", resp.code()));
+    sign_resp(
+        authenticator,
+        resp,
+        &identity.auth_id,
+        &identity.mode,
+        context,
+        buf,
+    )
+}
+
+#[allow(clippy::result_large_err)]
+fn sign_resp(
+    authenticator: &dyn Authenticator,
+    mut resp: ResponseEntity,
+    auth_id: &str,
+    mode: &AuthenticationMode,
+    context: &RequestContext,
+    buf: &mut [u8],
+) -> Result<Resp, Status> {
+    match signature(authenticator, &mut resp, auth_id, mode, buf) {
+        Ok(_) => Ok(Resp::from(resp)),
+        Err(e) => {
+            error!("Grpc Server {} Sign Response Error: {:?}", context.rpc, &e);
+            Err(Status::from_error(Box::new(e)))
+        }
     }
 }
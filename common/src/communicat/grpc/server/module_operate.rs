@@ -1,186 +1,260 @@
-use tokio::sync::mpsc::UnboundedSender;
+use std::sync::Arc;
+
 use tonic::{Code, Request, Response, Status};
 use tracing::error;
 
+use crate::communicat::grpc::server::{
+    forward_request, handle_request, intercept_request, limit_peer, limit_verified, respond,
+    verify_request, RequestContext,
+};
+use crate::communicat::matcher::InstructMatcher;
+use crate::communicat::middleware::MiddlewareChain;
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::Deliver;
+use crate::communicat::subscriber::SubscriberHub;
 use crate::entity::module_operate::{ModuleOperate, OperateType};
 use crate::entity::response::ResponseEntity;
+use crate::error::NihilityCommonError;
 use crate::response_code::Resp;
 use crate::submodule::submodule_server::Submodule;
 use crate::submodule::{SubmoduleHeartbeat, SubmoduleReq};
+use crate::utils::audit::AuditEventKind;
 use crate::utils::auth::{
-    get_public_key, set_module_operate_register_info, signature, verify, Signature,
-    AUTHENTICATION_ERROR_MESSAGE,
+    verify, Authenticator, Identity, Signature, AUTHENTICATION_ERROR_MESSAGE,
 };
 
 #[derive(Clone)]
 pub struct SubmoduleImpl {
-    operate_module_sender: UnboundedSender<ModuleOperate>,
+    authenticator: Arc<dyn Authenticator>,
+    middleware: Arc<MiddlewareChain>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    registry: Arc<SubmoduleRegistry>,
+    operate_module_sender: Arc<dyn Deliver<ModuleOperate>>,
+    instruct_matcher: Option<Arc<InstructMatcher>>,
+    subscriber_hub: Option<Arc<SubscriberHub>>,
 }
 
 impl SubmoduleImpl {
-    pub fn init(operate_module_sender: UnboundedSender<ModuleOperate>) -> Self {
+    pub fn init(
+        authenticator: Arc<dyn Authenticator>,
+        middleware: Arc<MiddlewareChain>,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        registry: Arc<SubmoduleRegistry>,
+        operate_module_sender: Arc<dyn Deliver<ModuleOperate>>,
+        instruct_matcher: Option<Arc<InstructMatcher>>,
+        subscriber_hub: Option<Arc<SubscriberHub>>,
+    ) -> Self {
         SubmoduleImpl {
+            authenticator,
+            middleware,
+            rate_limiter,
+            registry,
             operate_module_sender,
+            instruct_matcher,
+            subscriber_hub,
+        }
+    }
+
+    fn update_instruct_matcher(&self, operate: &ModuleOperate) {
+        if let Some(instruct_matcher) = &self.instruct_matcher {
+            instruct_matcher.apply(operate);
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn handle_operate(
+        &self,
+        request: Request<SubmoduleReq>,
+        operate_type: OperateType,
+        rpc: &'static str,
+    ) -> Result<Response<Resp>, Status> {
+        let mut buf = [0u8; 512];
+        let context = RequestContext::new(rpc, &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
+        let mut operate = match ModuleOperate::try_from(request.into_inner()) {
+            Ok(operate) => operate,
+            Err(e) => {
+                error!(
+                    "Submodule Server {} Create Operate From req Error: {:?}",
+                    rpc, &e
+                );
+                return Err(Status::from_error(Box::new(e)));
+            }
+        };
+        operate.operate_type = operate_type;
+        let authenticator = self.authenticator.as_ref();
+        let identity = verify_request(authenticator, &mut operate, &context, &mut buf)?;
+        // 下线与更新只能作用于该auth_id注册时的子模块
+        if self
+            .registry
+            .get(&identity.auth_id)
+            .is_none_or(|status| status.name != operate.name)
+        {
+            context
+                .audit(AuditEventKind::AuthenticationFail)
+                .submodule_name(&operate.name)
+                .auth_id(&identity.auth_id)
+                .detail(String::from("Submodule Name Not Match Auth Id"))
+                .emit();
+            context.record_verification_failure();
+            return Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE));
+        }
+        limit_verified(
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            &identity,
+            &context,
+        )?;
+        if let Some(resp) = intercept_request(&self.middleware, &mut operate, &identity, &context) {
+            return respond(
+                authenticator,
+                &self.middleware,
+                resp,
+                &identity,
+                &context,
+                &mut buf,
+            )
+            .map(Response::new);
+        }
+        match operate.operate_type {
+            OperateType::Offline => {
+                self.registry.remove(&identity.auth_id);
+                context
+                    .audit(AuditEventKind::Offline)
+                    .submodule_name(&operate.name)
+                    .auth_id(&identity.auth_id)
+                    .emit();
+                if let Some(subscriber_hub) = &self.subscriber_hub {
+                    subscriber_hub.unbind(&identity.auth_id);
+                }
+            }
+            OperateType::Update => self.registry.update(&identity.auth_id, &operate),
+            _ => {}
         }
+        self.update_instruct_matcher(&operate);
+        forward_request(
+            authenticator,
+            &self.middleware,
+            &self.registry,
+            self.operate_module_sender.as_ref(),
+            operate,
+            &identity,
+            &context,
+            &mut buf,
+        )
+        .map(Response::new)
     }
 }
 
 #[tonic::async_trait]
 impl Submodule for SubmoduleImpl {
     async fn register(&self, request: Request<SubmoduleReq>) -> Result<Response<Resp>, Status> {
-        let mut buf = [0u8; 1024];
-        match ModuleOperate::try_from(request.into_inner()) {
-            Ok(mut operate) => {
-                operate.operate_type = OperateType::Register;
-                if verify(&mut operate, &mut buf) {
-                    match set_module_operate_register_info(&mut operate).await {
-                        Ok(auth_id) => match self.operate_module_sender.send(operate) {
-                            Ok(_) => match get_public_key(&auth_id).await {
-                                Ok(public_key) => {
-                                    let mut resp = ResponseEntity::default();
-                                    signature(&mut resp, &auth_id, public_key, &mut buf)
-                                        .expect("Encode Entity Error");
-                                    Ok(Response::new(Resp::from(resp)))
-                                }
-                                Err(e) => {
-                                    error!("Submodule Server register Auth Id Error: {:?}", &e);
-                                    Err(Status::from_error(Box::new(e)))
-                                }
-                            },
-                            Err(e) => {
-                                error!("Submodule Server register Send To Core Error: {:?}", &e);
-                                Err(Status::from_error(Box::new(e)))
-                            }
-                        },
-                        Err(e) => {
-                            error!("Submodule Server register req Error: {:?}", &e);
-                            Err(Status::from_error(Box::new(e)))
-                        }
-                    }
-                } else {
-                    error!("Submodule Server register Request Verify Error!");
-                    Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
-                }
-            }
+        let mut buf = [0u8; 4096];
+        let context = RequestContext::new("register", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
+        let mut operate = match ModuleOperate::try_from(request.into_inner()) {
+            Ok(operate) => operate,
             Err(e) => {
                 error!(
                     "Submodule Server register Create Operate From req Error: {:?}",
                     &e
                 );
-                Err(Status::from_error(Box::new(e)))
+                return Err(Status::from_error(Box::new(e)));
             }
+        };
+        operate.operate_type = OperateType::Register;
+        let Some(identity) = verify(self.authenticator.as_ref(), &mut operate, &mut buf) else {
+            context
+                .audit(AuditEventKind::AuthenticationFail)
+                .submodule_name(&operate.name)
+                .emit();
+            context.record_verification_failure();
+            return Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE));
+        };
+        if let Some(resp) = intercept_request(&self.middleware, &mut operate, &identity, &context) {
+            return respond(
+                self.authenticator.as_ref(),
+                &self.middleware,
+                resp,
+                &identity,
+                &context,
+                &mut buf,
+            )
+            .map(Response::new);
         }
-    }
-
-    async fn offline(&self, request: Request<SubmoduleReq>) -> Result<Response<Resp>, Status> {
-        let mut buf = [0u8; 512];
-        match ModuleOperate::try_from(request.into_inner()) {
-            Ok(mut operate) => {
-                operate.operate_type = OperateType::Offline;
-                if verify(&mut operate, &mut buf) {
-                    let auth_id = String::from_utf8_lossy(operate.get_sign()).to_string();
-                    match self.operate_module_sender.send(operate) {
-                        Ok(_) => match get_public_key(&auth_id).await {
-                            Ok(public_key) => {
-                                let mut resp = ResponseEntity::default();
-                                signature(&mut resp, &auth_id, public_key, &mut buf)
-                                    .expect("Encode Entity Error");
-                                Ok(Response::new(Resp::from(resp)))
-                            }
-                            Err(e) => {
-                                error!("Submodule Server offline Auth Id Error: {:?}", &e);
-                                Err(Status::from_error(Box::new(e)))
-                            }
-                        },
-                        Err(e) => {
-                            error!("Submodule Server offline Send To Core Error: {:?}", &e);
-                            Err(Status::from_error(Box::new(e)))
-                        }
-                    }
-                } else {
-                    error!("Submodule Server register Request Verify Error!");
-                    Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
+        match self.authenticator.register(&mut operate) {
+            Ok(auth_id) => {
+                context
+                    .audit(AuditEventKind::Register)
+                    .submodule_name(&operate.name)
+                    .auth_id(&auth_id)
+                    .emit();
+                self.registry.register(&auth_id, &operate);
+                context.verified(&operate.name);
+                self.update_instruct_matcher(&operate);
+                if let Some(subscriber_hub) = &self.subscriber_hub {
+                    subscriber_hub.bind(&auth_id, &operate.name);
+                }
+                operate.set_sign(auth_id.as_bytes().into());
+                let mut resp = ResponseEntity::default();
+                if let Err(e) = self.operate_module_sender.deliver(operate) {
+                    error!("Submodule Server register Send To Core Error: {:?}", e);
+                    resp.unknown_error();
                 }
+                let identity = Identity {
+                    auth_id,
+                    ..identity
+                };
+                respond(
+                    self.authenticator.as_ref(),
+                    &self.middleware,
+                    resp,
+                    &identity,
+                    &context,
+                    &mut buf,
+                )
+                .map(Response::new)
             }
             Err(e) => {
-                error!(
-                    "Submodule Server offline Create Operate From req Error: {:?}",
-                    &e
-                );
+                if let NihilityCommonError::KeyFingerprint(_) = e {
+                    context
+                        .audit(AuditEventKind::KeyChange)
+                        .submodule_name(&operate.name)
+                        .detail(e.to_string())
+                        .emit();
+                }
+                error!("Submodule Server register req Error: {:?}", &e);
                 Err(Status::from_error(Box::new(e)))
             }
         }
     }
 
+    async fn offline(&self, request: Request<SubmoduleReq>) -> Result<Response<Resp>, Status> {
+        self.handle_operate(request, OperateType::Offline, "offline")
+    }
+
     async fn heartbeat(
         &self,
         request: Request<SubmoduleHeartbeat>,
     ) -> Result<Response<Resp>, Status> {
         let mut buf = [0u8; 512];
-        let mut entity = ModuleOperate::from(request.into_inner());
-        if verify(&mut entity, &mut buf) {
-            let auth_id = String::from_utf8_lossy(entity.get_sign()).to_string();
-            match self.operate_module_sender.send(entity) {
-                Ok(_) => match get_public_key(&auth_id).await {
-                    Ok(public_key) => {
-                        let mut resp = ResponseEntity::default();
-                        signature(&mut resp, &auth_id, public_key, &mut buf)
-                            .expect("Encode Entity Error");
-                        Ok(Response::new(Resp::from(resp)))
-                    }
-                    Err(e) => {
-                        error!("Submodule Server heartbeat Auth Id Error: {:?}", &e);
-                        Err(Status::from_error(Box::new(e)))
-                    }
-                },
-                Err(e) => {
-                    error!("Submodule Server heartbeat Send To Core Error: {:?}", &e);
-                    Err(Status::from_error(Box::new(e)))
-                }
-            }
-        } else {
-            error!("Submodule Server register Request Verify Error!");
-            Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
-        }
+        let context = RequestContext::new("heartbeat", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
+        handle_request(
+            self.authenticator.as_ref(),
+            &self.middleware,
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            self.operate_module_sender.as_ref(),
+            ModuleOperate::from(request.into_inner()),
+            &context,
+            &mut buf,
+        )
+        .map(Response::new)
     }
 
     async fn update(&self, request: Request<SubmoduleReq>) -> Result<Response<Resp>, Status> {
-        let mut buf = [0u8; 512];
-        match ModuleOperate::try_from(request.into_inner()) {
-            Ok(mut operate) => {
-                operate.operate_type = OperateType::Update;
-                if verify(&mut operate, &mut buf) {
-                    let auth_id = String::from_utf8_lossy(operate.get_sign()).to_string();
-                    match self.operate_module_sender.send(operate) {
-                        Ok(_) => match get_public_key(&auth_id).await {
-                            Ok(public_key) => {
-                                let mut resp = ResponseEntity::default();
-                                signature(&mut resp, &auth_id, public_key, &mut buf)
-                                    .expect("Encode Entity Error");
-                                Ok(Response::new(Resp::from(resp)))
-                            }
-                            Err(e) => {
-                                error!("Submodule Server update Auth Id Error: {:?}", &e);
-                                Err(Status::from_error(Box::new(e)))
-                            }
-                        },
-                        Err(e) => {
-                            error!("Submodule Server update Send To Core Error: {:?}", &e);
-                            Err(Status::from_error(Box::new(e)))
-                        }
-                    }
-                } else {
-                    error!("Submodule Server register Request Verify Error!");
-                    Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE))
-                }
-            }
-            Err(e) => {
-                error!(
-                    "Submodule Server update Create Operate From req Error: {:?}",
-                    &e
-                );
-                Err(Status::from_error(Box::new(e)))
-            }
-        }
+        self.handle_operate(request, OperateType::Update, "update")
     }
 }
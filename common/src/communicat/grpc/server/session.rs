@@ -0,0 +1,294 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::spawn;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::codegen::tokio_stream::Stream;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::transport::server::TcpConnectInfo;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, error};
+
+use crate::communicat::grpc::server::instruct::InstructImpl;
+use crate::communicat::grpc::server::manipulate::ManipulateImpl;
+use crate::communicat::grpc::server::module_operate::SubmoduleImpl;
+use crate::communicat::grpc::server::subscribe::SubscribeImpl;
+use crate::communicat::grpc::TRACEPARENT_METADATA;
+use crate::instruct::instruct_server::Instruct;
+use crate::manipulate::manipulate_server::Manipulate;
+use crate::response_code::Resp;
+use crate::session::envelope::Message;
+use crate::session::session_server::Session;
+use crate::session::{Envelope, SessionStatus};
+use crate::submodule::submodule_server::Submodule;
+use crate::subscribe::subscribe_server::Subscribe;
+use crate::subscribe::SubscribeResp;
+
+const SESSION_BUFFER: usize = 128;
+/// 单个会话同时处理的消息上限
+const SESSION_CONCURRENCY: usize = 16;
+
+type SessionStream = Pin<Box<dyn Stream<Item = Result<Envelope, Status>> + Send>>;
+
+/// 将会话中的每条消息分发至对应服务的实现，认证、审计与转发逻辑与单独调用时一致
+#[derive(Clone)]
+pub struct SessionImpl {
+    submodule: Option<SubmoduleImpl>,
+    instruct: Option<InstructImpl>,
+    manipulate: Option<ManipulateImpl>,
+    subscribe: Option<SubscribeImpl>,
+}
+
+impl SessionImpl {
+    pub fn init(
+        submodule: Option<SubmoduleImpl>,
+        instruct: Option<InstructImpl>,
+        manipulate: Option<ManipulateImpl>,
+        subscribe: Option<SubscribeImpl>,
+    ) -> Self {
+        SessionImpl {
+            submodule,
+            instruct,
+            manipulate,
+            subscribe,
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        envelope: Envelope,
+        origin: RequestOrigin,
+        out: mpsc::Sender<Result<Envelope, Status>>,
+        permit: OwnedSemaphorePermit,
+    ) {
+        let correlation_id = envelope.correlation_id;
+        let origin = origin.traced(&envelope.traceparent);
+        let result = match envelope.message {
+            Some(Message::Register(req)) => match &self.submodule {
+                Some(submodule) => submodule.register(request(req, &origin)).await,
+                None => Err(unimplemented("Submodule")),
+            },
+            Some(Message::Offline(req)) => match &self.submodule {
+                Some(submodule) => submodule.offline(request(req, &origin)).await,
+                None => Err(unimplemented("Submodule")),
+            },
+            Some(Message::Heartbeat(req)) => match &self.submodule {
+                Some(submodule) => submodule.heartbeat(request(req, &origin)).await,
+                None => Err(unimplemented("Submodule")),
+            },
+            Some(Message::Update(req)) => match &self.submodule {
+                Some(submodule) => submodule.update(request(req, &origin)).await,
+                None => Err(unimplemented("Submodule")),
+            },
+            Some(Message::TextInstruct(req)) => match &self.instruct {
+                Some(instruct) => instruct.send_text_instruct(request(req, &origin)).await,
+                None => Err(unimplemented("Instruct")),
+            },
+            Some(Message::SimpleManipulate(req)) => match &self.manipulate {
+                Some(manipulate) => {
+                    manipulate
+                        .send_simple_manipulate(request(req, &origin))
+                        .await
+                }
+                None => Err(unimplemented("Manipulate")),
+            },
+            Some(Message::TextDisplayManipulate(req)) => match &self.manipulate {
+                Some(manipulate) => {
+                    manipulate
+                        .send_text_display_manipulate(request(req, &origin))
+                        .await
+                }
+                None => Err(unimplemented("Manipulate")),
+            },
+            Some(Message::DirectConnectionManipulate(req)) => match &self.manipulate {
+                Some(manipulate) => {
+                    manipulate
+                        .send_direct_connection_manipulate(request(req, &origin))
+                        .await
+                }
+                None => Err(unimplemented("Manipulate")),
+            },
+            Some(Message::Subscribe(req)) => {
+                let result = match &self.subscribe {
+                    Some(subscribe) => subscribe.subscribe(request(req, &origin)).await,
+                    None => Err(unimplemented("Subscribe")),
+                };
+                // 推送转发持续到订阅结束，不再占用会话的并发额度
+                drop(permit);
+                return match result {
+                    Ok(push_stream) => {
+                        forward_push(correlation_id, push_stream.into_inner(), out).await
+                    }
+                    Err(status) => reply(&out, correlation_id, Err(status)).await,
+                };
+            }
+            other => {
+                debug!("Session Server Receive Unexpected Message: {:?}", other);
+                Err(Status::invalid_argument("Unexpected Session Message"))
+            }
+        };
+        reply(&out, correlation_id, result.map(Response::into_inner)).await
+    }
+}
+
+#[tonic::async_trait]
+impl Session for SessionImpl {
+    type SessionStream = SessionStream;
+
+    async fn session(
+        &self,
+        request: Request<Streaming<Envelope>>,
+    ) -> Result<Response<Self::SessionStream>, Status> {
+        let origin = RequestOrigin {
+            connect_info: request.extensions().get::<TcpConnectInfo>().cloned(),
+            metadata: request.metadata().clone(),
+        };
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::channel(SESSION_BUFFER);
+        let limit = Arc::new(Semaphore::new(SESSION_CONCURRENCY));
+        let (ordered_tx, mut ordered_rx) = mpsc::channel::<Envelope>(SESSION_BUFFER);
+        let session = self.clone();
+        {
+            let origin = origin.clone();
+            let limit = limit.clone();
+            let out = tx.clone();
+            spawn(async move {
+                while let Some(envelope) = ordered_rx.recv().await {
+                    let Ok(permit) = limit.clone().acquire_owned().await else {
+                        break;
+                    };
+                    session
+                        .dispatch(envelope, origin.clone(), out.clone(), permit)
+                        .await;
+                }
+            });
+        }
+        let session = self.clone();
+        spawn(async move {
+            while let Some(result) = inbound.next().await {
+                match result {
+                    Ok(envelope) if is_module_operate(&envelope) => {
+                        if ordered_tx.send(envelope).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(envelope) => {
+                        let Ok(permit) = limit.clone().acquire_owned().await else {
+                            break;
+                        };
+                        let session = session.clone();
+                        let origin = origin.clone();
+                        let out = tx.clone();
+                        spawn(async move { session.dispatch(envelope, origin, out, permit).await });
+                    }
+                    Err(e) => {
+                        error!("Session Server Receive Error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::SessionStream
+        ))
+    }
+}
+
+impl From<Status> for SessionStatus {
+    fn from(value: Status) -> Self {
+        SessionStatus {
+            code: value.code() as i32,
+            message: value.message().to_string(),
+        }
+    }
+}
+
+/// 会话建立时的连接信息与元数据
+#[derive(Clone)]
+struct RequestOrigin {
+    connect_info: Option<TcpConnectInfo>,
+    metadata: MetadataMap,
+}
+
+impl RequestOrigin {
+    /// 以消息携带的追踪上下文替换会话建立时的元数据
+    fn traced(mut self, traceparent: &str) -> Self {
+        if traceparent.is_empty() {
+            return self;
+        }
+        if let Ok(traceparent) = MetadataValue::try_from(traceparent) {
+            self.metadata.insert(TRACEPARENT_METADATA, traceparent);
+        }
+        self
+    }
+}
+
+/// 保留会话的连接信息与元数据，审计与限速时与单独调用一致
+fn request<T>(message: T, origin: &RequestOrigin) -> Request<T> {
+    let mut request = Request::new(message);
+    *request.metadata_mut() = origin.metadata.clone();
+    if let Some(connect_info) = origin.connect_info.clone() {
+        request.extensions_mut().insert(connect_info);
+    }
+    request
+}
+
+/// 子模块注册、更新、心跳与下线按到达顺序依次处理，避免更新与下线相互竞争
+fn is_module_operate(envelope: &Envelope) -> bool {
+    matches!(
+        envelope.message,
+        Some(
+            Message::Register(_) | Message::Update(_) | Message::Heartbeat(_) | Message::Offline(_)
+        )
+    )
+}
+
+fn unimplemented(service: &str) -> Status {
+    Status::unimplemented(format!("{} Service Not Enabled", service))
+}
+
+async fn reply(
+    out: &mpsc::Sender<Result<Envelope, Status>>,
+    correlation_id: String,
+    result: Result<Resp, Status>,
+) {
+    let message = match result {
+        Ok(resp) => Message::Resp(resp),
+        Err(status) => Message::Status(status.into()),
+    };
+    let envelope = Envelope {
+        correlation_id,
+        message: Some(message),
+        traceparent: String::new(),
+    };
+    if let Err(e) = out.send(Ok(envelope)).await {
+        error!("Session Server Send To Stream Error: {:?}", e);
+    }
+}
+
+/// 推送的实体沿用订阅请求的correlation_id
+async fn forward_push<S>(
+    correlation_id: String,
+    mut push_stream: S,
+    out: mpsc::Sender<Result<Envelope, Status>>,
+) where
+    S: Stream<Item = Result<SubscribeResp, Status>> + Unpin,
+{
+    while let Some(result) = push_stream.next().await {
+        let message = match result {
+            Ok(push) => Message::Push(push),
+            Err(status) => Message::Status(status.into()),
+        };
+        let envelope = Envelope {
+            correlation_id: correlation_id.to_string(),
+            message: Some(message),
+            traceparent: String::new(),
+        };
+        if out.send(Ok(envelope)).await.is_err() {
+            debug!("Session Closed, Stop Forward Push");
+            break;
+        }
+    }
+}
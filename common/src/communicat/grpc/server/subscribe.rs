@@ -0,0 +1,128 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::codegen::tokio_stream::Stream;
+use tonic::{Code, Request, Response, Status};
+use tracing::error;
+
+use crate::communicat::grpc::server::{limit_peer, limit_verified, verify_request, RequestContext};
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::subscriber::SubscriberHub;
+use crate::entity::module_operate::ModuleOperate;
+use crate::entity::subscribe::{encrypted_payload, PushEntity};
+use crate::subscribe::subscribe_server::Subscribe;
+use crate::subscribe::{SubscribeReq, SubscribeResp};
+use crate::utils::audit::AuditEventKind;
+use crate::utils::auth::{Authenticator, Identity, AUTHENTICATION_ERROR_MESSAGE};
+
+type SubscribeStream = Pin<Box<dyn Stream<Item = Result<SubscribeResp, Status>> + Send>>;
+
+#[derive(Clone)]
+pub struct SubscribeImpl {
+    authenticator: Arc<dyn Authenticator>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    registry: Arc<SubmoduleRegistry>,
+    subscriber_hub: Arc<SubscriberHub>,
+    payload_encryption: bool,
+}
+
+impl SubscribeImpl {
+    pub fn init(
+        authenticator: Arc<dyn Authenticator>,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        registry: Arc<SubmoduleRegistry>,
+        subscriber_hub: Arc<SubscriberHub>,
+        payload_encryption: bool,
+    ) -> Self {
+        SubscribeImpl {
+            authenticator,
+            rate_limiter,
+            registry,
+            subscriber_hub,
+            payload_encryption,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Subscribe for SubscribeImpl {
+    type SubscribeStream = SubscribeStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeReq>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut buf = [0u8; 512];
+        let context = RequestContext::new("subscribe", &request);
+        limit_peer(self.rate_limiter.as_deref(), &context)?;
+        let mut operate = ModuleOperate::from(request.into_inner());
+        let identity = verify_request(
+            self.authenticator.as_ref(),
+            &mut operate,
+            &context,
+            &mut buf,
+        )?;
+        limit_verified(
+            self.rate_limiter.as_deref(),
+            &self.registry,
+            &identity,
+            &context,
+        )?;
+        let Some((submodule_name, receiver)) = self.subscriber_hub.subscribe(&identity.auth_id)
+        else {
+            context
+                .audit(AuditEventKind::AuthenticationFail)
+                .submodule_name(&operate.name)
+                .auth_id(&identity.auth_id)
+                .detail(String::from("Submodule Not Registered"))
+                .emit();
+            return Err(Status::new(Code::Ok, AUTHENTICATION_ERROR_MESSAGE));
+        };
+        let authenticator = self.authenticator.clone();
+        let payload_encryption = self.payload_encryption;
+        #[allow(clippy::result_large_err)]
+        let stream = ReceiverStream::new(receiver).map(move |entity| {
+            push_resp(
+                authenticator.as_ref(),
+                entity,
+                &identity,
+                &submodule_name,
+                payload_encryption,
+            )
+        });
+        Ok(Response::new(Box::pin(stream) as Self::SubscribeStream))
+    }
+}
+
+/// 推送的实体按订阅方身份签名，需要时再以订阅方公钥加密文本负载
+#[allow(clippy::result_large_err)]
+fn push_resp(
+    authenticator: &dyn Authenticator,
+    mut entity: PushEntity,
+    identity: &Identity,
+    submodule_name: &str,
+    payload_encryption: bool,
+) -> Result<SubscribeResp, Status> {
+    let mut buf = [0u8; 2048];
+    entity
+        .signature(authenticator, &identity.auth_id, &identity.mode, &mut buf)
+        .and_then(|_| entity.try_into())
+        .and_then(|mut resp: SubscribeResp| {
+            if payload_encryption {
+                if let Some(payload) = encrypted_payload(&mut resp) {
+                    authenticator.encrypt_payload(&identity.auth_id, payload)?;
+                }
+            }
+            Ok(resp)
+        })
+        .map_err(|e| {
+            error!(
+                "Subscribe Server Push To Submodule {} Error: {:?}",
+                submodule_name, &e
+            );
+            Status::from_error(Box::new(e))
+        })
+}
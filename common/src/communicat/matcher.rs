@@ -0,0 +1,210 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use regex::Regex;
+use tracing::{debug, error};
+
+use crate::entity::instruct::{InstructData, InstructEntity};
+use crate::entity::module_operate::{ModuleOperate, OperateType};
+
+/// 正则表达式形式的指令模式前缀
+pub const REGEX_PATTERN_PREFIX: &str = "re:";
+
+/// 匹配方式，排序靠前的优先级更高
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructMatch {
+    pub submodule_name: String,
+    pub pattern: String,
+    pub kind: MatchKind,
+}
+
+/// 由`default_instruct`解析出的模式
+///
+/// `re:`开头为正则，仅以`*`结尾为前缀，含`*`或`?`为通配，其余为精确匹配
+#[derive(Debug)]
+enum InstructPattern {
+    Exact(String),
+    Prefix(String),
+    Glob(String),
+    Regex(String, Regex),
+}
+
+impl InstructPattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        if let Some(expr) = pattern.strip_prefix(REGEX_PATTERN_PREFIX) {
+            return match Regex::new(expr) {
+                Ok(regex) => Some(InstructPattern::Regex(pattern.to_string(), regex)),
+                Err(e) => {
+                    error!("Instruct Pattern {} Parse Error: {:?}", pattern, e);
+                    None
+                }
+            };
+        }
+        let wildcard = |c: char| c == '*' || c == '?';
+        match pattern.strip_suffix('*') {
+            Some(prefix) if !prefix.contains(wildcard) => {
+                Some(InstructPattern::Prefix(prefix.to_string()))
+            }
+            _ if pattern.contains(wildcard) => Some(InstructPattern::Glob(pattern.to_string())),
+            _ => Some(InstructPattern::Exact(pattern.to_string())),
+        }
+    }
+
+    fn kind(&self) -> MatchKind {
+        match self {
+            InstructPattern::Exact(_) => MatchKind::Exact,
+            InstructPattern::Prefix(_) => MatchKind::Prefix,
+            InstructPattern::Glob(_) => MatchKind::Glob,
+            InstructPattern::Regex(..) => MatchKind::Regex,
+        }
+    }
+
+    fn pattern(&self) -> String {
+        match self {
+            InstructPattern::Exact(pattern)
+            | InstructPattern::Glob(pattern)
+            | InstructPattern::Regex(pattern, _) => pattern.to_string(),
+            InstructPattern::Prefix(prefix) => format!("{}*", prefix),
+        }
+    }
+
+    /// 同一匹配方式下字面字符越多越具体
+    fn specificity(&self) -> usize {
+        match self {
+            InstructPattern::Exact(pattern) | InstructPattern::Prefix(pattern) => {
+                pattern.chars().count()
+            }
+            InstructPattern::Glob(pattern) => {
+                pattern.chars().filter(|c| *c != '*' && *c != '?').count()
+            }
+            InstructPattern::Regex(..) => 0,
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            InstructPattern::Exact(pattern) => text == pattern,
+            InstructPattern::Prefix(prefix) => text.starts_with(prefix.as_str()),
+            InstructPattern::Glob(pattern) => glob_match(
+                &pattern.chars().collect::<Vec<_>>(),
+                &text.chars().collect::<Vec<_>>(),
+            ),
+            InstructPattern::Regex(_, regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// `*`匹配任意长度字符，`?`匹配单个字符
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 按已注册子模块的`default_instruct`为指令匹配候选子模块
+///
+/// 设置至`NihilityServer`后随子模块注册、更新、下线自动维护
+#[derive(Default)]
+pub struct InstructMatcher {
+    patterns: RwLock<HashMap<String, Vec<InstructPattern>>>,
+}
+
+impl InstructMatcher {
+    pub fn new() -> Self {
+        InstructMatcher::default()
+    }
+
+    /// 替换子模块的全部指令模式
+    pub fn insert(&self, submodule_name: &str, default_instruct: &[String]) {
+        let patterns = default_instruct
+            .iter()
+            .filter_map(|pattern| InstructPattern::parse(pattern))
+            .collect();
+        self.patterns
+            .write()
+            .unwrap()
+            .insert(submodule_name.to_string(), patterns);
+    }
+
+    pub fn remove(&self, submodule_name: &str) {
+        self.patterns.write().unwrap().remove(submodule_name);
+    }
+
+    /// 注册与更新时替换模式，下线时移除，其余操作忽略
+    pub fn apply(&self, operate: &ModuleOperate) {
+        match (&operate.operate_type, &operate.info) {
+            (OperateType::Register | OperateType::Update, Some(info)) => {
+                debug!("Instruct Matcher Update Submodule {}", &operate.name);
+                self.insert(&operate.name, &info.default_instruct);
+            }
+            (OperateType::Offline, _) => {
+                debug!("Instruct Matcher Remove Submodule {}", &operate.name);
+                self.remove(&operate.name);
+            }
+            _ => {}
+        }
+    }
+
+    /// 返回按匹配方式、具体程度排序的候选子模块，每个子模块仅保留其最佳匹配
+    pub fn match_instruct(&self, instruct: &InstructEntity) -> Vec<InstructMatch> {
+        let text = match &instruct.instruct {
+            InstructData::Text(text) => text.trim(),
+        };
+        let mut candidates = Vec::new();
+        for (submodule_name, patterns) in self.patterns.read().unwrap().iter() {
+            let best = patterns
+                .iter()
+                .filter(|pattern| pattern.is_match(text))
+                .min_by_key(|pattern| (pattern.kind(), Reverse(pattern.specificity())));
+            if let Some(pattern) = best {
+                candidates.push((
+                    pattern.specificity(),
+                    InstructMatch {
+                        submodule_name: submodule_name.to_string(),
+                        pattern: pattern.pattern(),
+                        kind: pattern.kind(),
+                    },
+                ));
+            }
+        }
+        candidates.sort_by(|(a_specificity, a), (b_specificity, b)| {
+            a.kind
+                .cmp(&b.kind)
+                .then(b_specificity.cmp(a_specificity))
+                .then(a.submodule_name.cmp(&b.submodule_name))
+        });
+        candidates
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+}
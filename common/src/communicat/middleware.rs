@@ -0,0 +1,98 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::entity::instruct::InstructEntity;
+use crate::entity::manipulate::ManipulateEntity;
+use crate::entity::module_operate::ModuleOperate;
+use crate::entity::response::ResponseEntity;
+use crate::error::WrapResult;
+
+/// 中间件钩子可获取的请求信息
+///
+/// 服务端`peer_addr`为请求方地址，客户端为`None`；`auth_id`为请求方身份
+#[derive(Debug, Clone)]
+pub struct MiddlewareContext {
+    pub rpc: &'static str,
+    pub peer_addr: Option<SocketAddr>,
+    pub auth_id: String,
+}
+
+impl MiddlewareContext {
+    pub(crate) fn new(rpc: &'static str, peer_addr: Option<SocketAddr>, auth_id: &str) -> Self {
+        MiddlewareContext {
+            rpc,
+            peer_addr,
+            auth_id: auth_id.to_string(),
+        }
+    }
+}
+
+pub enum MiddlewareEntity<'a> {
+    Instruct(&'a mut InstructEntity),
+    Manipulate(&'a mut ManipulateEntity),
+    ModuleOperate(&'a mut ModuleOperate),
+}
+
+/// 请求处理前后的钩子，可用于日志、统计、过滤或改写实体
+///
+/// 服务端在校验签名后调用`before`，返回错误时拒绝请求并响应`UnableToProcess`；
+/// 客户端在签名前调用`before`，返回错误时不发送请求
+pub trait Middleware: Send + Sync {
+    fn before(&self, _context: &MiddlewareContext, _entity: MiddlewareEntity) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn after(&self, _context: &MiddlewareContext, _resp: &ResponseEntity) {}
+}
+
+/// 按添加顺序执行`before`，按相反顺序执行`after`
+#[derive(Default, Clone)]
+pub(crate) struct MiddlewareChain {
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl MiddlewareChain {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middlewares.push(middleware);
+    }
+
+    pub(crate) fn before<E: Intercept>(
+        &self,
+        context: &MiddlewareContext,
+        entity: &mut E,
+    ) -> WrapResult<()> {
+        for middleware in &self.middlewares {
+            middleware.before(context, entity.middleware_entity())?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn after(&self, context: &MiddlewareContext, resp: &ResponseEntity) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after(context, resp);
+        }
+    }
+}
+
+/// 可交给中间件处理的实体
+pub(crate) trait Intercept {
+    fn middleware_entity(&mut self) -> MiddlewareEntity<'_>;
+}
+
+impl Intercept for InstructEntity {
+    fn middleware_entity(&mut self) -> MiddlewareEntity<'_> {
+        MiddlewareEntity::Instruct(self)
+    }
+}
+
+impl Intercept for ManipulateEntity {
+    fn middleware_entity(&mut self) -> MiddlewareEntity<'_> {
+        MiddlewareEntity::Manipulate(self)
+    }
+}
+
+impl Intercept for ModuleOperate {
+    fn middleware_entity(&mut self) -> MiddlewareEntity<'_> {
+        MiddlewareEntity::ModuleOperate(self)
+    }
+}
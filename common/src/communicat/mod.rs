@@ -1,30 +1,64 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use tokio::sync::mpsc::{Receiver, UnboundedSender};
 use tonic::async_trait;
 use tracing::debug;
 
+use crate::communicat::grpc::server::ServerHandle;
+use crate::communicat::matcher::InstructMatcher;
+use crate::communicat::middleware::Middleware;
+use crate::communicat::rate_limit::RateLimiter;
+use crate::communicat::registry::SubmoduleRegistry;
+use crate::communicat::router::{Deliver, Router};
+use crate::communicat::subscriber::SubscriberHub;
 use crate::entity::instruct::InstructEntity;
 use crate::entity::manipulate::ManipulateEntity;
 use crate::entity::module_operate::ModuleOperate;
 use crate::entity::response::ResponseEntity;
+use crate::entity::subscribe::PushEntity;
 use crate::error::{NihilityCommonError, WrapResult};
+use crate::utils::auth::Authenticator;
 use crate::SubmoduleInfo;
 
+pub mod discovery;
+pub mod event_bus;
 pub mod grpc;
+pub mod matcher;
+pub mod middleware;
+pub mod rate_limit;
+pub mod registry;
+pub mod router;
+pub mod subscriber;
+pub mod trace;
 
 static HEARTBEAT_TIME: u64 = 30;
 
+static HEARTBEAT_INTERVAL: OnceLock<u64> = OnceLock::new();
+
+/// 子模块心跳间隔秒数，未设置时为30秒
+pub fn set_heartbeat_interval(secs: u64) {
+    HEARTBEAT_INTERVAL.get_or_init(|| secs);
+}
+
 #[async_trait]
-pub trait NihilityClient: SendManipulateOperate + SendInstructOperate + SubmoduleOperate {
+pub trait NihilityClient:
+    SendManipulateOperate + SendInstructOperate + SubmoduleOperate + SubscribeOperate
+{
     async fn connection_submodule_operate_server(&mut self) -> WrapResult<()>;
     async fn connection_instruct_server(&mut self) -> WrapResult<()>;
     async fn connection_manipulate_server(&mut self) -> WrapResult<()>;
+    async fn connection_subscribe_server(&mut self) -> WrapResult<()>;
     fn disconnection_submodule_operate_server(&mut self) -> WrapResult<()>;
     fn disconnection_instruct_server(&mut self) -> WrapResult<()>;
     fn disconnection_manipulate_server(&mut self) -> WrapResult<()>;
+    fn disconnection_subscribe_server(&mut self) -> WrapResult<()>;
     fn set_submodule_info(&mut self, submodule_info: SubmoduleInfo) -> WrapResult<()>;
     fn get_submodule_info(&self) -> WrapResult<SubmoduleInfo>;
+    fn set_authenticator(&mut self, authenticator: Arc<dyn Authenticator>) -> WrapResult<()>;
+    /// 按添加顺序在签名前执行，需在注册前添加
+    fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) -> WrapResult<()>;
     async fn register(&mut self) -> WrapResult<ResponseEntity> {
         if self.is_submodule_operate_client_connected() {
             self.start_heartbeat_thread().await?;
@@ -109,26 +143,116 @@ pub trait NihilityClient: SendManipulateOperate + SendInstructOperate + Submodul
         }
         Err(NihilityCommonError::NotConnected("Manipulate".to_string()))
     }
+    /// 停止心跳，已注册时发送下线，之后断开全部连接，下线失败时同样断开并返回该错误
+    async fn shutdown(&mut self) -> WrapResult<()> {
+        let mut result = Ok(());
+        if self.is_submodule_operate_client_connected()
+            && self.stop_heartbeat_thread().await.is_ok()
+        {
+            result = match self.get_submodule_info() {
+                Ok(submodule_info) => self.send_offline(submodule_info).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+        }
+        let disconnected = [
+            self.disconnection_submodule_operate_server(),
+            self.disconnection_instruct_server(),
+            self.disconnection_manipulate_server(),
+            self.disconnection_subscribe_server(),
+        ];
+        result.and(disconnected.into_iter().collect())
+    }
+    /// 注册后调用，接收核心模块推送至本子模块的实体，下线后结束
+    async fn subscribe(&self) -> WrapResult<Receiver<PushEntity>> {
+        if self.is_subscribe_client_connected() {
+            return self.send_subscribe().await;
+        }
+        Err(NihilityCommonError::NotConnected("Subscribe".to_string()))
+    }
 }
 
 #[async_trait]
 pub trait NihilityServer {
+    fn set_authenticator(&mut self, authenticator: Arc<dyn Authenticator>) -> WrapResult<()>;
+
+    /// 可设置为`EventBus`以供多个组件同时订阅
+    fn set_submodule_operate_deliver(
+        &mut self,
+        submodule_deliver: Arc<dyn Deliver<ModuleOperate>>,
+    ) -> WrapResult<()>;
+
+    /// 可设置为`Router`或`EventBus`
+    fn set_instruct_deliver(
+        &mut self,
+        instruct_deliver: Arc<dyn Deliver<InstructEntity>>,
+    ) -> WrapResult<()>;
+
+    /// 可设置为`Router`或`EventBus`
+    fn set_manipulate_deliver(
+        &mut self,
+        manipulate_deliver: Arc<dyn Deliver<ManipulateEntity>>,
+    ) -> WrapResult<()>;
+
     fn set_submodule_operate_sender(
         &mut self,
         submodule_sender: UnboundedSender<ModuleOperate>,
-    ) -> WrapResult<()>;
+    ) -> WrapResult<()> {
+        self.set_submodule_operate_deliver(Arc::new(submodule_sender))
+    }
 
+    /// 等同于仅设置兜底通道的`Router`
     fn set_instruct_sender(
         &mut self,
         instruct_sender: UnboundedSender<InstructEntity>,
-    ) -> WrapResult<()>;
+    ) -> WrapResult<()> {
+        self.set_instruct_router(Arc::new(Router::new(instruct_sender)))
+    }
 
+    /// 等同于仅设置兜底通道的`Router`
     fn set_manipulate_sender(
         &mut self,
         manipulate_sender: UnboundedSender<ManipulateEntity>,
-    ) -> WrapResult<()>;
+    ) -> WrapResult<()> {
+        self.set_manipulate_router(Arc::new(Router::new(manipulate_sender)))
+    }
+
+    fn set_instruct_router(
+        &mut self,
+        instruct_router: Arc<Router<InstructEntity>>,
+    ) -> WrapResult<()> {
+        self.set_instruct_deliver(instruct_router)
+    }
+
+    fn set_manipulate_router(
+        &mut self,
+        manipulate_router: Arc<Router<ManipulateEntity>>,
+    ) -> WrapResult<()> {
+        self.set_manipulate_deliver(manipulate_router)
+    }
+
+    /// 子模块注册、更新、下线时同步维护匹配器
+    fn set_instruct_matcher(&mut self, instruct_matcher: Arc<InstructMatcher>) -> WrapResult<()>;
 
-    fn start(&mut self) -> WrapResult<()>;
+    /// 设置后启用订阅服务，子模块注册后可通过订阅流接收核心模块推送的实体
+    fn set_subscriber_hub(&mut self, subscriber_hub: Arc<SubscriberHub>) -> WrapResult<()>;
+
+    /// 设置后在校验签名后按子模块与RPC限速，超出时返回`ResourceExhausted`
+    fn set_rate_limiter(&mut self, rate_limiter: Arc<RateLimiter>) -> WrapResult<()>;
+
+    /// 按添加顺序在校验签名后执行，需在`start`前添加
+    fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) -> WrapResult<()>;
+
+    /// 未设置时使用服务端内部的记录，设置后核心模块可直接查询已注册的子模块
+    fn set_submodule_registry(&mut self, registry: Arc<SubmoduleRegistry>) -> WrapResult<()>;
+
+    /// 读取核心模块密钥目录中的公钥并启用运维服务，请求需使用核心模块私钥签名
+    fn enable_admin(&mut self, core_key_dir: &str) -> WrapResult<()>;
+
+    /// 启动后将实际监听地址与核心模块公钥指纹写入`discovery_path`，停止后删除
+    fn enable_discovery(&mut self, core_key_dir: &str, discovery_path: &Path) -> WrapResult<()>;
+
+    /// 在当前tokio运行时中绑定监听地址并启动，绑定失败时返回错误
+    fn start(&mut self) -> WrapResult<ServerHandle>;
 }
 
 #[async_trait]
@@ -173,8 +297,16 @@ pub trait SendManipulateOperate {
     ) -> WrapResult<ResponseEntity>;
 }
 
+#[async_trait]
+pub trait SubscribeOperate {
+    fn is_subscribe_client_connected(&self) -> bool;
+    async fn send_subscribe(&self) -> WrapResult<Receiver<PushEntity>>;
+}
+
 async fn heartbeat_thread<C: NihilityClient + Send + Sync>(client: C) -> WrapResult<()> {
-    let mut interval = tokio::time::interval(Duration::from_secs(HEARTBEAT_TIME));
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        HEARTBEAT_INTERVAL.get().copied().unwrap_or(HEARTBEAT_TIME),
+    ));
     loop {
         interval.tick().await;
         debug!("NihilityClient Send Heartbeat");
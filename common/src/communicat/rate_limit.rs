@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// 令牌桶限速规则，`submodule_name`与`rpc`为空时匹配全部
+///
+/// 每个子模块分别计数，同一规则下该子模块匹配的全部RPC共用一个令牌桶；
+/// 服务端按签名校验通过的子模块名称计数，无法确定时按来源IP计数；
+/// 未指定`submodule_name`的规则另在签名校验前按来源IP计数
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RateLimitRule {
+    #[serde(default)]
+    pub submodule_name: Option<String>,
+    #[serde(default)]
+    pub rpc: Option<String>,
+    /// 令牌桶容量，即允许的突发请求数
+    pub capacity: u32,
+    /// 每秒补充的令牌数
+    pub refill_per_second: f64,
+}
+
+impl RateLimitRule {
+    fn is_match(&self, owner: &BucketOwner, rpc: &str) -> bool {
+        let owner_match = match (&self.submodule_name, owner) {
+            (None, _) => true,
+            (Some(name), BucketOwner::Submodule(submodule_name)) => name == submodule_name,
+            (Some(_), BucketOwner::Peer(_)) => false,
+        };
+        owner_match && self.rpc.as_ref().is_none_or(|name| name == rpc)
+    }
+}
+
+/// 令牌桶的计数对象，签名校验前后分别计数
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum BucketOwner {
+    Submodule(String),
+    Peer(IpAddr),
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rule: &RateLimitRule) -> Self {
+        TokenBucket {
+            tokens: rule.capacity as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, rule: &RateLimitRule, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rule.refill_per_second).min(rule.capacity as f64);
+        self.updated = now;
+    }
+
+    /// 已补满的令牌桶与新建的相同，可以移除
+    fn is_full(&self, rule: &RateLimitRule, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * rule.refill_per_second >= rule.capacity as f64
+    }
+}
+
+/// 令牌桶数量超过该值时移除已补满的令牌桶
+const BUCKET_SWEEP_THRESHOLD: usize = 1024;
+
+/// 服务端在校验签名前按来源IP、校验签名后按子模块与RPC限速，请求需通过全部匹配的规则
+pub struct RateLimiter {
+    rules: RwLock<Vec<RateLimitRule>>,
+    buckets: Mutex<HashMap<(usize, BucketOwner), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rules: Vec<RateLimitRule>) -> Self {
+        RateLimiter {
+            rules: RwLock::new(rules),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 替换全部规则，已有的令牌桶重新计数
+    pub fn set_rules(&self, rules: Vec<RateLimitRule>) {
+        let mut current = self.rules.write().unwrap();
+        self.buckets.lock().unwrap().clear();
+        *current = rules;
+    }
+
+    pub fn rules(&self) -> Vec<RateLimitRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// 全部匹配的规则均有剩余令牌时各消耗一个并返回`true`，否则不消耗
+    pub fn check(&self, submodule_name: &str, rpc: &str) -> bool {
+        self.take(BucketOwner::Submodule(submodule_name.to_string()), rpc)
+    }
+
+    /// 签名校验前按来源IP检查，避免校验签名与解密载荷的开销被滥用，
+    /// 仅应用未指定`submodule_name`的规则，同一主机的各子模块共用令牌桶
+    pub fn check_peer(&self, peer_ip: IpAddr, rpc: &str) -> bool {
+        self.take(BucketOwner::Peer(peer_ip), rpc)
+    }
+
+    fn take(&self, owner: BucketOwner, rpc: &str) -> bool {
+        let rules = self.rules.read().unwrap();
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        if buckets.len() > BUCKET_SWEEP_THRESHOLD {
+            buckets.retain(|(index, _), bucket| {
+                rules
+                    .get(*index)
+                    .is_some_and(|rule| !bucket.is_full(rule, now))
+            });
+        }
+        let matched: Vec<(usize, &RateLimitRule)> = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.is_match(&owner, rpc))
+            .collect();
+        for (index, rule) in &matched {
+            buckets
+                .entry((*index, owner.clone()))
+                .or_insert_with(|| TokenBucket::new(rule))
+                .refill(rule, now);
+        }
+        let allowed = matched
+            .iter()
+            .all(|(index, _)| buckets[&(*index, owner.clone())].tokens >= 1.0);
+        if allowed {
+            for (index, _) in &matched {
+                if let Some(bucket) = buckets.get_mut(&(*index, owner.clone())) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+        allowed
+    }
+
+    /// 当前保留的令牌桶数量
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::entity::admin::SubmoduleStatus;
+use crate::entity::module_operate::ModuleOperate;
+
+/// 子模块心跳的RPC名称，转发时同时更新最近心跳时间
+const HEARTBEAT_RPC: &str = "heartbeat";
+
+/// 核心模块按auth_id记录的已注册子模块，下线后移除
+#[derive(Default)]
+pub struct SubmoduleRegistry {
+    submodules: RwLock<HashMap<String, SubmoduleStatus>>,
+}
+
+impl SubmoduleRegistry {
+    pub(crate) fn register(&self, auth_id: &str, operate: &ModuleOperate) {
+        let Some(info) = &operate.info else {
+            return;
+        };
+        let status = SubmoduleStatus {
+            name: operate.name.to_string(),
+            auth_id: auth_id.to_string(),
+            info: info.clone(),
+            registered_at: SystemTime::now(),
+            last_heartbeat: None,
+            message_count: HashMap::new(),
+        };
+        self.submodules
+            .write()
+            .unwrap()
+            .insert(auth_id.to_string(), status);
+    }
+
+    pub(crate) fn update(&self, auth_id: &str, operate: &ModuleOperate) {
+        if let (Some(status), Some(info)) = (
+            self.submodules.write().unwrap().get_mut(auth_id),
+            &operate.info,
+        ) {
+            status.info = info.clone();
+        }
+    }
+
+    pub(crate) fn record_message(&self, auth_id: &str, rpc: &str) {
+        if let Some(status) = self.submodules.write().unwrap().get_mut(auth_id) {
+            if rpc == HEARTBEAT_RPC {
+                status.last_heartbeat = Some(SystemTime::now());
+            }
+            *status.message_count.entry(rpc.to_string()).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn remove(&self, auth_id: &str) -> Option<SubmoduleStatus> {
+        self.submodules.write().unwrap().remove(auth_id)
+    }
+
+    /// 按子模块名称排序
+    pub fn list(&self) -> Vec<SubmoduleStatus> {
+        let mut submodules: Vec<SubmoduleStatus> =
+            self.submodules.read().unwrap().values().cloned().collect();
+        submodules.sort_by(|a, b| (&a.name, a.registered_at).cmp(&(&b.name, b.registered_at)));
+        submodules
+    }
+
+    pub fn get(&self, auth_id: &str) -> Option<SubmoduleStatus> {
+        self.submodules.read().unwrap().get(auth_id).cloned()
+    }
+}
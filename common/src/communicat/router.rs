@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::RwLock;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::debug;
+
+use crate::entity::instruct::{InstructEntity, InstructType};
+use crate::entity::manipulate::{ManipulateEntity, ManipulateType};
+use crate::error::{NihilityCommonError, WrapResult};
+
+/// 可被`Router`分发的实体，`target`为目标子模块名称
+pub trait Route {
+    type Kind: Clone + Eq + Hash + Send + Sync;
+
+    fn target(&self) -> &str;
+    fn kind(&self) -> Self::Kind;
+}
+
+/// 服务端将校验通过的实体交给核心模块的方式
+pub trait Deliver<E>: Send + Sync {
+    fn deliver(&self, entity: E) -> WrapResult<()>;
+}
+
+impl<E: Send> Deliver<E> for UnboundedSender<E> {
+    fn deliver(&self, entity: E) -> WrapResult<()> {
+        self.send(entity)
+            .map_err(|_| NihilityCommonError::ChannelClosed)
+    }
+}
+
+/// 按目标子模块、再按实体类型分发实体，均未匹配时发送至兜底通道
+///
+/// 订阅方的接收端被丢弃后自动取消对应订阅
+pub struct Router<E: Route> {
+    targets: RwLock<HashMap<String, UnboundedSender<E>>>,
+    kinds: RwLock<HashMap<E::Kind, UnboundedSender<E>>>,
+    fallback: UnboundedSender<E>,
+}
+
+impl<E: Route> Router<E> {
+    pub fn new(fallback: UnboundedSender<E>) -> Self {
+        Router {
+            targets: RwLock::new(HashMap::new()),
+            kinds: RwLock::new(HashMap::new()),
+            fallback,
+        }
+    }
+
+    /// 同一目标重复订阅时替换之前的订阅
+    pub fn subscribe_target(&self, target: &str) -> UnboundedReceiver<E> {
+        let (tx, rx) = unbounded_channel();
+        self.targets.write().unwrap().insert(target.to_string(), tx);
+        rx
+    }
+
+    pub fn subscribe_kind(&self, kind: E::Kind) -> UnboundedReceiver<E> {
+        let (tx, rx) = unbounded_channel();
+        self.kinds.write().unwrap().insert(kind, tx);
+        rx
+    }
+
+    pub fn unsubscribe_target(&self, target: &str) {
+        self.targets.write().unwrap().remove(target);
+    }
+
+    pub fn unsubscribe_kind(&self, kind: &E::Kind) {
+        self.kinds.write().unwrap().remove(kind);
+    }
+
+    pub fn route(&self, entity: E) -> WrapResult<()> {
+        let target = entity.target().to_string();
+        let Some(entity) = try_send(&self.targets, &target, entity) else {
+            return Ok(());
+        };
+        let kind = entity.kind();
+        let Some(entity) = try_send(&self.kinds, &kind, entity) else {
+            return Ok(());
+        };
+        self.fallback
+            .send(entity)
+            .map_err(|_| NihilityCommonError::ChannelClosed)
+    }
+}
+
+/// 未订阅或发送失败时返回实体，发送失败说明接收端已丢弃，同时移除订阅
+fn try_send<K: Eq + Hash, E>(
+    subscribers: &RwLock<HashMap<K, UnboundedSender<E>>>,
+    key: &K,
+    entity: E,
+) -> Option<E> {
+    let result = match subscribers.read().unwrap().get(key) {
+        None => return Some(entity),
+        Some(sender) => sender.send(entity),
+    };
+    match result {
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Router Receiver Dropped, Unsubscribe");
+            subscribers.write().unwrap().remove(key);
+            Some(e.0)
+        }
+    }
+}
+
+impl<E: Route + Send> Deliver<E> for Router<E> {
+    fn deliver(&self, entity: E) -> WrapResult<()> {
+        self.route(entity)
+    }
+}
+
+impl Route for InstructEntity {
+    type Kind = InstructType;
+
+    fn target(&self) -> &str {
+        &self.info.receive_manipulate_submodule
+    }
+
+    fn kind(&self) -> Self::Kind {
+        self.info.instruct_type.clone()
+    }
+}
+
+impl Route for ManipulateEntity {
+    type Kind = ManipulateType;
+
+    fn target(&self) -> &str {
+        &self.info.use_module_name
+    }
+
+    fn kind(&self) -> Self::Kind {
+        self.info.manipulate_type.clone()
+    }
+}
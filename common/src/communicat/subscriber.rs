@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::debug;
+
+use crate::communicat::router::{Deliver, Route};
+use crate::entity::instruct::InstructEntity;
+use crate::entity::manipulate::ManipulateEntity;
+use crate::entity::subscribe::PushEntity;
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::utils::metrics::metrics;
+
+pub const DEFAULT_SUBSCRIPTION_BUFFER: usize = 128;
+
+/// 管理子模块的订阅流，核心模块通过此处向未部署服务端的子模块推送实体
+///
+/// 子模块注册后才能订阅，下线时关闭其订阅流，推送时按实体的目标子模块名称查找订阅
+pub struct SubscriberHub {
+    buffer: usize,
+    registered: RwLock<HashMap<String, String>>,
+    subscribers: RwLock<HashMap<String, Subscriber>>,
+}
+
+/// 订阅流与订阅时使用的auth_id，只有该auth_id下线时才关闭
+struct Subscriber {
+    auth_id: String,
+    sender: mpsc::Sender<PushEntity>,
+}
+
+impl Default for SubscriberHub {
+    fn default() -> Self {
+        SubscriberHub::new(DEFAULT_SUBSCRIPTION_BUFFER)
+    }
+}
+
+impl SubscriberHub {
+    /// `buffer`为每个订阅流未发送实体的上限，超出时推送失败
+    pub fn new(buffer: usize) -> Self {
+        SubscriberHub {
+            buffer,
+            registered: RwLock::new(HashMap::new()),
+            subscribers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn push<E: Into<PushEntity> + Route>(&self, entity: E) -> WrapResult<()> {
+        let submodule_name = entity.target().to_string();
+        let result = match self.subscribers.read().unwrap().get(&submodule_name) {
+            None => return Err(NihilityCommonError::NotSubscribed(submodule_name)),
+            Some(Subscriber { sender, .. }) => {
+                let result = sender.try_send(entity.into());
+                metrics().set_queue_depth(
+                    &format!("subscription:{}", &submodule_name),
+                    sender.max_capacity() - sender.capacity(),
+                );
+                result
+            }
+        };
+        match result {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                Err(NihilityCommonError::SubscriptionFull(submodule_name))
+            }
+            Err(TrySendError::Closed(_)) => {
+                debug!("Submodule {} Subscription Closed", &submodule_name);
+                let mut subscribers = self.subscribers.write().unwrap();
+                // 期间可能已重新订阅，只移除已关闭的订阅流
+                if subscribers
+                    .get(&submodule_name)
+                    .is_some_and(|subscriber| subscriber.sender.is_closed())
+                {
+                    subscribers.remove(&submodule_name);
+                }
+                Err(NihilityCommonError::NotSubscribed(submodule_name))
+            }
+        }
+    }
+
+    pub fn is_subscribed(&self, submodule_name: &str) -> bool {
+        self.subscribers
+            .read()
+            .unwrap()
+            .get(submodule_name)
+            .is_some_and(|subscriber| !subscriber.sender.is_closed())
+    }
+
+    /// 同一子模块重新注册时移除之前的auth_id及其订阅流
+    pub(crate) fn bind(&self, auth_id: &str, submodule_name: &str) {
+        let mut registered = self.registered.write().unwrap();
+        registered.retain(|_, name| name != submodule_name);
+        registered.insert(auth_id.to_string(), submodule_name.to_string());
+        self.remove_subscriber(submodule_name, |subscriber| subscriber.auth_id != auth_id);
+    }
+
+    /// 只关闭该auth_id自己的订阅流，不影响同名子模块之后的注册
+    pub(crate) fn unbind(&self, auth_id: &str) {
+        if let Some(submodule_name) = self.registered.write().unwrap().remove(auth_id) {
+            self.remove_subscriber(&submodule_name, |subscriber| subscriber.auth_id == auth_id);
+        }
+    }
+
+    fn remove_subscriber(&self, submodule_name: &str, condition: impl Fn(&Subscriber) -> bool) {
+        let mut subscribers = self.subscribers.write().unwrap();
+        if subscribers.get(submodule_name).is_some_and(condition) {
+            subscribers.remove(submodule_name);
+        }
+    }
+
+    /// 未注册的auth_id返回`None`，同一子模块重复订阅时替换之前的订阅流
+    pub(crate) fn subscribe(&self, auth_id: &str) -> Option<(String, mpsc::Receiver<PushEntity>)> {
+        let submodule_name = self.registered.read().unwrap().get(auth_id)?.to_string();
+        let (sender, rx) = mpsc::channel(self.buffer);
+        self.subscribers.write().unwrap().insert(
+            submodule_name.to_string(),
+            Subscriber {
+                auth_id: auth_id.to_string(),
+                sender,
+            },
+        );
+        Some((submodule_name, rx))
+    }
+}
+
+impl Deliver<InstructEntity> for SubscriberHub {
+    fn deliver(&self, entity: InstructEntity) -> WrapResult<()> {
+        self.push(entity)
+    }
+}
+
+impl Deliver<ManipulateEntity> for SubscriberHub {
+    fn deliver(&self, entity: ManipulateEntity) -> WrapResult<()> {
+        self.push(entity)
+    }
+}
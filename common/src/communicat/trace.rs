@@ -0,0 +1,164 @@
+use tracing::{info_span, Span};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Registry;
+use uuid::Uuid;
+
+use crate::entity::instruct::InstructEntity;
+use crate::entity::manipulate::ManipulateEntity;
+use crate::entity::module_operate::ModuleOperate;
+use crate::entity::subscribe::PushEntity;
+
+const TRACEPARENT_VERSION: &str = "00";
+const TRACEPARENT_FLAGS: &str = "01";
+const TRACE_ID_LEN: usize = 32;
+const SPAN_ID_LEN: usize = 16;
+
+/// 跨子模块传递的追踪上下文，以W3C `traceparent`格式写入请求元数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32位十六进制，同一追踪经过的各子模块相同
+    pub trace_id: String,
+    /// 16位十六进制，每一跳不同
+    pub span_id: String,
+}
+
+impl TraceContext {
+    /// 开始新的追踪
+    pub fn new_root() -> Self {
+        TraceContext {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+        }
+    }
+
+    /// 延续已有的trace_id，trace_id格式不正确时返回`None`
+    pub fn from_trace_id(trace_id: &str) -> Option<Self> {
+        is_id(trace_id, TRACE_ID_LEN).then(|| TraceContext {
+            trace_id: trace_id.to_ascii_lowercase(),
+            span_id: new_span_id(),
+        })
+    }
+
+    /// 同一追踪中的下一跳
+    pub fn child(&self) -> Self {
+        TraceContext {
+            trace_id: self.trace_id.to_string(),
+            span_id: new_span_id(),
+        }
+    }
+
+    /// 当前span及其上级span中由`span`创建的追踪上下文
+    ///
+    /// 需使用`Log::init`等基于`Registry`的日志输出，span被日志级别过滤时返回`None`
+    pub fn current() -> Option<Self> {
+        Span::current()
+            .with_subscriber(|(id, dispatch)| {
+                dispatch
+                    .downcast_ref::<Registry>()?
+                    .span(id)?
+                    .scope()
+                    .find_map(|span| span.extensions().get::<TraceContext>().cloned())
+            })
+            .flatten()
+    }
+
+    /// 实体携带的trace_id优先，其次为当前span的追踪上下文，均没有时开始新的追踪
+    pub(crate) fn resolve(trace_id: Option<&str>) -> Self {
+        trace_id
+            .and_then(TraceContext::from_trace_id)
+            .or_else(|| TraceContext::current().map(|current| current.child()))
+            .unwrap_or_else(TraceContext::new_root)
+    }
+
+    /// 创建记录trace_id与span_id的span，在其中发送的请求延续本追踪
+    pub fn span(&self, name: &str) -> Span {
+        let span = info_span!(
+            "trace",
+            name,
+            trace_id = %self.trace_id,
+            span_id = %self.span_id
+        );
+        span.with_subscriber(|(id, dispatch)| {
+            if let Some(span) = dispatch
+                .downcast_ref::<Registry>()
+                .and_then(|registry| registry.span(id))
+            {
+                span.extensions_mut().insert(self.clone());
+            }
+        });
+        span
+    }
+
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "{}-{}-{}-{}",
+            TRACEPARENT_VERSION, self.trace_id, self.span_id, TRACEPARENT_FLAGS
+        )
+    }
+
+    /// 解析`traceparent`，格式不正确或id全为0时返回`None`
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (Some(version), Some(trace_id), Some(span_id), Some(flags), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+        if !is_hex(version, 2)
+            || !is_hex(flags, 2)
+            || !is_id(trace_id, TRACE_ID_LEN)
+            || !is_id(span_id, SPAN_ID_LEN)
+        {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id: trace_id.to_ascii_lowercase(),
+            span_id: span_id.to_ascii_lowercase(),
+        })
+    }
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..SPAN_ID_LEN].to_string()
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// 全为0的id无效
+fn is_id(id: &str, len: usize) -> bool {
+    is_hex(id, len) && id.chars().any(|c| c != '0')
+}
+
+/// 服务端校验通过后写入请求携带的trace_id
+pub(crate) trait Traced {
+    fn set_trace_id(&mut self, _trace_id: &str) {}
+}
+
+impl Traced for InstructEntity {
+    fn set_trace_id(&mut self, trace_id: &str) {
+        self.trace_id = Some(trace_id.to_string());
+    }
+}
+
+impl Traced for ManipulateEntity {
+    fn set_trace_id(&mut self, trace_id: &str) {
+        self.trace_id = Some(trace_id.to_string());
+    }
+}
+
+impl Traced for ModuleOperate {}
+
+impl Traced for PushEntity {
+    fn set_trace_id(&mut self, trace_id: &str) {
+        match self {
+            PushEntity::Instruct(instruct) => instruct.set_trace_id(trace_id),
+            PushEntity::Manipulate(manipulate) => manipulate.set_trace_id(trace_id),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use nihility_procmacro::Sign;
+
+use crate::admin;
+use crate::admin::AdminReq;
+use crate::entity::module_operate::{ConnParams, SubmoduleInfo};
+use crate::error::NihilityCommonError;
+use crate::submodule::ConnectionParams;
+use crate::utils::auth::Signature;
+
+/// 运维请求，`auth_id`为操作的子模块，列表与统计请求时为空
+///
+/// 签名覆盖RPC名称，截获的请求不能用于其他RPC
+#[derive(Serialize, Sign)]
+pub(crate) struct AdminRequest {
+    /// 不随请求发送，服务端以被调用的RPC填入后校验签名
+    pub rpc: String,
+    pub auth_id: String,
+    /// 签名时间，毫秒时间戳
+    pub timestamp: u64,
+    /// 服务端在有效期内拒绝重复的随机数
+    pub nonce: u64,
+    sign: Vec<u8>,
+}
+
+impl AdminRequest {
+    pub(crate) fn new(rpc: &str, auth_id: &str) -> Self {
+        AdminRequest {
+            rpc: rpc.to_string(),
+            auth_id: auth_id.to_string(),
+            timestamp: to_millis(SystemTime::now()),
+            nonce: rand::random(),
+            sign: Vec::new(),
+        }
+    }
+
+    /// 服务端收到的请求，`rpc`为被调用的RPC
+    pub(crate) fn received(rpc: &str, value: AdminReq) -> Self {
+        AdminRequest {
+            rpc: rpc.to_string(),
+            auth_id: value.auth_id,
+            timestamp: value.timestamp,
+            nonce: value.nonce,
+            sign: value.sign,
+        }
+    }
+
+    pub(crate) fn signed_at(&self) -> SystemTime {
+        from_millis(self.timestamp)
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        match SystemTime::now().duration_since(self.signed_at()) {
+            Ok(elapsed) => elapsed,
+            Err(e) => e.duration(),
+        }
+    }
+}
+
+/// 核心模块记录的子模块状态
+#[derive(Debug, Clone)]
+pub struct SubmoduleStatus {
+    pub name: String,
+    pub auth_id: String,
+    pub info: SubmoduleInfo,
+    pub registered_at: SystemTime,
+    pub last_heartbeat: Option<SystemTime>,
+    /// 按RPC统计的已转发至核心模块的消息数
+    pub message_count: HashMap<String, u64>,
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+impl From<AdminRequest> for AdminReq {
+    fn from(value: AdminRequest) -> Self {
+        AdminReq {
+            auth_id: value.auth_id,
+            timestamp: value.timestamp,
+            sign: value.sign,
+            nonce: value.nonce,
+        }
+    }
+}
+
+impl From<SubmoduleStatus> for admin::SubmoduleStatus {
+    fn from(value: SubmoduleStatus) -> Self {
+        admin::SubmoduleStatus {
+            name: value.name,
+            auth_id: value.auth_id,
+            connection_params: Some(ConnectionParams::from(value.info.conn_params)),
+            default_instruct: value.info.default_instruct,
+            registered_at: to_millis(value.registered_at),
+            last_heartbeat: value.last_heartbeat.map(to_millis).unwrap_or_default(),
+            message_count: value.message_count,
+        }
+    }
+}
+
+impl TryFrom<admin::SubmoduleStatus> for SubmoduleStatus {
+    type Error = NihilityCommonError;
+
+    fn try_from(value: admin::SubmoduleStatus) -> Result<Self, Self::Error> {
+        let Some(connection_params) = value.connection_params else {
+            return Err(NihilityCommonError::CreateSubmoduleStatus);
+        };
+        Ok(SubmoduleStatus {
+            name: value.name,
+            auth_id: value.auth_id,
+            info: SubmoduleInfo {
+                default_instruct: value.default_instruct,
+                conn_params: ConnParams::from(connection_params),
+            },
+            registered_at: from_millis(value.registered_at),
+            last_heartbeat: match value.last_heartbeat {
+                0 => None,
+                last_heartbeat => Some(from_millis(last_heartbeat)),
+            },
+            message_count: value.message_count,
+        })
+    }
+}
@@ -9,9 +9,9 @@ use nihility_procmacro::Sign;
 use crate::error::NihilityCommonError;
 use crate::get_default_receiver_submodule;
 use crate::instruct::{InstructInfo, TextInstruct, Type};
-use crate::utils::auth::{get_auth_id_bytes, Signature};
+use crate::utils::auth::{get_auth_id_bytes, EncryptPayload, Signature};
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
 pub enum InstructType {
     #[default]
     DefaultType,
@@ -36,6 +36,9 @@ pub struct InstructEntity {
     pub info: InstructInfoEntity,
     pub instruct: InstructData,
     sign: Vec<u8>,
+    /// 服务端按请求携带的追踪上下文写入，客户端发送时优先使用，不参与签名
+    #[serde(skip)]
+    pub trace_id: Option<String>,
 }
 
 impl InstructEntity {
@@ -44,6 +47,7 @@ impl InstructEntity {
             info: InstructInfoEntity::default(),
             instruct: InstructData::Text(text),
             sign: get_auth_id_bytes(),
+            trace_id: None,
         }
     }
 }
@@ -52,8 +56,8 @@ impl fmt::Debug for InstructEntity {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Instruct ( info: {:?}, instruct: {:?} )",
-            self.info, self.instruct,
+            "Instruct ( info: {:?}, instruct: {:?}, trace_id: {:?} )",
+            self.info, self.instruct, self.trace_id,
         )
     }
 }
@@ -115,11 +119,13 @@ impl From<TextInstruct> for InstructEntity {
                 info: InstructInfoEntity::default(),
                 instruct: InstructData::Text(value.instruct),
                 sign: value.sign,
+                trace_id: None,
             },
             Some(info) => InstructEntity {
                 info: info.into(),
                 instruct: InstructData::Text(value.instruct),
                 sign: value.sign,
+                trace_id: None,
             },
         }
     }
@@ -134,11 +140,30 @@ impl TryInto<TextInstruct> for InstructEntity {
                 info: Some(self.info.into()),
                 instruct: text,
                 sign: self.sign,
+                encrypted_payload: Vec::new(),
             }),
         }
     }
 }
 
+impl EncryptPayload for TextInstruct {
+    fn get_payload(&self) -> &str {
+        &self.instruct
+    }
+
+    fn set_payload(&mut self, payload: String) {
+        self.instruct = payload;
+    }
+
+    fn get_encrypted_payload(&self) -> &Vec<u8> {
+        &self.encrypted_payload
+    }
+
+    fn set_encrypted_payload(&mut self, encrypted_payload: Vec<u8>) {
+        self.encrypted_payload = encrypted_payload;
+    }
+}
+
 impl Default for InstructData {
     fn default() -> Self {
         InstructData::Text(String::new())
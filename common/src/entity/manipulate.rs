@@ -13,9 +13,9 @@ use crate::manipulate::{
     DirectConnectionManipulate, ManipulateInfo, SimpleManipulate, TextDisplayManipulate, Type,
 };
 use crate::submodule::ConnectionParams;
-use crate::utils::auth::{get_auth_id_bytes, Signature};
+use crate::utils::auth::{get_auth_id_bytes, EncryptPayload, Signature};
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
 pub enum ManipulateType {
     #[default]
     DefaultType,
@@ -46,6 +46,9 @@ pub struct ManipulateEntity {
     pub info: ManipulateInfoEntity,
     pub manipulate: ManipulateData,
     sign: Vec<u8>,
+    /// 服务端按请求携带的追踪上下文写入，客户端发送时优先使用，不参与签名
+    #[serde(skip)]
+    pub trace_id: Option<String>,
 }
 
 impl ManipulateEntity {
@@ -54,6 +57,7 @@ impl ManipulateEntity {
             info: ManipulateInfoEntity::default(),
             manipulate: ManipulateData::Text(text),
             sign: get_auth_id_bytes(),
+            trace_id: None,
         }
     }
 
@@ -62,6 +66,7 @@ impl ManipulateEntity {
             info: ManipulateInfoEntity::default(),
             manipulate: ManipulateData::Simple,
             sign: get_auth_id_bytes(),
+            trace_id: None,
         }
     }
 
@@ -70,6 +75,7 @@ impl ManipulateEntity {
             info: ManipulateInfoEntity::default(),
             manipulate: ManipulateData::ConnectionParams(conn_params),
             sign: get_auth_id_bytes(),
+            trace_id: None,
         }
     }
 }
@@ -78,8 +84,8 @@ impl fmt::Debug for ManipulateEntity {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Manipulate ( info: {:?}, manipulate: {:?} )",
-            self.info, self.manipulate,
+            "Manipulate ( info: {:?}, manipulate: {:?}, trace_id: {:?} )",
+            self.info, self.manipulate, self.trace_id,
         )
     }
 }
@@ -147,11 +153,13 @@ impl From<TextDisplayManipulate> for ManipulateEntity {
                 info: ManipulateInfoEntity::default(),
                 manipulate: ManipulateData::Text(value.text),
                 sign: value.sign,
+                trace_id: None,
             },
             Some(info) => ManipulateEntity {
                 info: info.into(),
                 manipulate: ManipulateData::Text(value.text),
                 sign: value.sign,
+                trace_id: None,
             },
         }
     }
@@ -166,12 +174,31 @@ impl TryInto<TextDisplayManipulate> for ManipulateEntity {
                 info: Some(self.info.into()),
                 text,
                 sign: self.sign,
+                encrypted_payload: Vec::new(),
             }),
             other_type => Err(CreateManipulateReq(other_type)),
         }
     }
 }
 
+impl EncryptPayload for TextDisplayManipulate {
+    fn get_payload(&self) -> &str {
+        &self.text
+    }
+
+    fn set_payload(&mut self, payload: String) {
+        self.text = payload;
+    }
+
+    fn get_encrypted_payload(&self) -> &Vec<u8> {
+        &self.encrypted_payload
+    }
+
+    fn set_encrypted_payload(&mut self, encrypted_payload: Vec<u8>) {
+        self.encrypted_payload = encrypted_payload;
+    }
+}
+
 impl From<SimpleManipulate> for ManipulateEntity {
     fn from(value: SimpleManipulate) -> Self {
         match value.info {
@@ -179,11 +206,13 @@ impl From<SimpleManipulate> for ManipulateEntity {
                 info: ManipulateInfoEntity::default(),
                 manipulate: ManipulateData::Simple,
                 sign: value.sign,
+                trace_id: None,
             },
             Some(info) => ManipulateEntity {
                 info: info.into(),
                 manipulate: ManipulateData::Simple,
                 sign: value.sign,
+                trace_id: None,
             },
         }
     }
@@ -216,6 +245,7 @@ impl TryFrom<DirectConnectionManipulate> for ManipulateEntity {
                         connection_params,
                     )),
                     sign: value.sign,
+                    trace_id: None,
                 },
                 Some(info) => ManipulateEntity {
                     info: info.into(),
@@ -223,6 +253,7 @@ impl TryFrom<DirectConnectionManipulate> for ManipulateEntity {
                         connection_params,
                     )),
                     sign: value.sign,
+                    trace_id: None,
                 },
             }),
         }
@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod instruct;
 pub mod manipulate;
-pub mod response;
 pub mod module_operate;
+pub mod response;
+pub mod subscribe;
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::fmt::Formatter;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
 use nihility_procmacro::Sign;
 
@@ -11,6 +11,7 @@ use crate::get_submodule_name;
 use crate::submodule::{
     ConnectionParams, ReceiveType, SubmoduleHeartbeat, SubmoduleReq, SubmoduleType,
 };
+use crate::subscribe::SubscribeReq;
 use crate::utils::auth::{get_auth_id_bytes, Signature};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -31,7 +32,7 @@ pub enum ClientType {
     ManipulateType,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub enum OperateType {
     #[default]
     Undefined,
@@ -39,12 +40,14 @@ pub enum OperateType {
     Offline,
     Heartbeat,
     Update,
+    Subscribe,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnParams {
     pub connection_type: ConnectionType,
     pub client_type: ClientType,
+    #[serde(serialize_with = "ordered_map")]
     pub conn_config: HashMap<String, String>,
 }
 
@@ -62,6 +65,17 @@ pub struct ModuleOperate {
     sign: Vec<u8>,
 }
 
+/// 签名时按键排序序列化，保证双方计算的摘要一致
+fn ordered_map<S: Serializer>(
+    value: &HashMap<String, String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
 impl fmt::Debug for ModuleOperate {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
@@ -83,6 +97,18 @@ impl Default for ModuleOperate {
     }
 }
 
+impl ModuleOperate {
+    /// 核心模块强制子模块下线时发送给核心模块的操作
+    pub(crate) fn offline(name: &str, auth_id: &str) -> Self {
+        ModuleOperate {
+            name: name.to_string(),
+            info: None,
+            operate_type: OperateType::Offline,
+            sign: auth_id.as_bytes().into(),
+        }
+    }
+}
+
 impl From<SubmoduleType> for ConnectionType {
     fn from(value: SubmoduleType) -> Self {
         match value {
@@ -157,6 +183,17 @@ impl From<SubmoduleHeartbeat> for ModuleOperate {
     }
 }
 
+impl From<SubscribeReq> for ModuleOperate {
+    fn from(value: SubscribeReq) -> Self {
+        ModuleOperate {
+            name: value.name,
+            info: None,
+            operate_type: OperateType::Subscribe,
+            sign: value.sign,
+        }
+    }
+}
+
 impl From<ConnectionType> for SubmoduleType {
     fn from(value: ConnectionType) -> Self {
         match value {
@@ -209,3 +246,17 @@ impl TryInto<SubmoduleHeartbeat> for ModuleOperate {
         }
     }
 }
+
+impl TryInto<SubscribeReq> for ModuleOperate {
+    type Error = NihilityCommonError;
+
+    fn try_into(self) -> Result<SubscribeReq, Self::Error> {
+        match self.operate_type {
+            OperateType::Subscribe => Ok(SubscribeReq {
+                name: self.name,
+                sign: self.sign,
+            }),
+            other_type => Err(NihilityCommonError::CreateSubscribeReq(other_type)),
+        }
+    }
+}
@@ -15,6 +15,8 @@ pub enum ResponseCode {
     UnknownError,
     UnableToProcess,
     AuthenticationFail,
+    /// 超出服务端速率限制，请求未被处理
+    TooManyRequests,
 }
 
 #[derive(Default, Serialize, Sign)]
@@ -36,6 +38,9 @@ impl ResponseEntity {
     pub fn authentication_fail(&mut self) {
         self.code = ResponseCode::AuthenticationFail;
     }
+    pub fn too_many_requests(&mut self) {
+        self.code = ResponseCode::TooManyRequests;
+    }
     pub fn code(&self) -> &ResponseCode {
         &self.code
     }
@@ -54,6 +59,7 @@ impl From<RespCode> for ResponseCode {
             RespCode::Success => ResponseCode::Success,
             RespCode::UnableToProcess => ResponseCode::UnableToProcess,
             RespCode::AuthenticationFail => ResponseCode::AuthenticationFail,
+            RespCode::TooManyRequests => ResponseCode::TooManyRequests,
         }
     }
 }
@@ -65,6 +71,7 @@ impl From<ResponseCode> for RespCode {
             ResponseCode::UnknownError => RespCode::UnknownError,
             ResponseCode::UnableToProcess => RespCode::UnableToProcess,
             ResponseCode::AuthenticationFail => RespCode::AuthenticationFail,
+            ResponseCode::TooManyRequests => RespCode::TooManyRequests,
         }
     }
 }
@@ -0,0 +1,117 @@
+use crate::communicat::trace::Traced;
+use crate::entity::instruct::InstructEntity;
+use crate::entity::manipulate::{ManipulateData, ManipulateEntity};
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::subscribe::subscribe_resp::Entity;
+use crate::subscribe::SubscribeResp;
+use crate::utils::auth::{signature, verify, AuthenticationMode, Authenticator, EncryptPayload};
+
+/// 核心模块通过订阅流推送至子模块的实体
+#[derive(Debug)]
+pub enum PushEntity {
+    Instruct(InstructEntity),
+    Manipulate(ManipulateEntity),
+}
+
+impl PushEntity {
+    pub(crate) fn signature(
+        &mut self,
+        authenticator: &dyn Authenticator,
+        auth_id: &str,
+        mode: &AuthenticationMode,
+        buf: &mut [u8],
+    ) -> WrapResult<()> {
+        match self {
+            PushEntity::Instruct(instruct) => {
+                signature(authenticator, instruct, auth_id, mode, buf)
+            }
+            PushEntity::Manipulate(manipulate) => {
+                signature(authenticator, manipulate, auth_id, mode, buf)
+            }
+        }
+    }
+
+    /// 核心模块收到的请求携带的trace_id，推送时随实体发送
+    pub fn trace_id(&self) -> Option<&str> {
+        match self {
+            PushEntity::Instruct(instruct) => instruct.trace_id.as_deref(),
+            PushEntity::Manipulate(manipulate) => manipulate.trace_id.as_deref(),
+        }
+    }
+
+    pub(crate) fn verify(&mut self, authenticator: &dyn Authenticator, buf: &mut [u8]) -> bool {
+        match self {
+            PushEntity::Instruct(instruct) => verify(authenticator, instruct, buf).is_some(),
+            PushEntity::Manipulate(manipulate) => verify(authenticator, manipulate, buf).is_some(),
+        }
+    }
+}
+
+/// 推送中可加密的文本负载，其余实体没有文本负载
+pub(crate) fn encrypted_payload(resp: &mut SubscribeResp) -> Option<&mut dyn EncryptPayload> {
+    match resp.entity.as_mut()? {
+        Entity::TextInstruct(instruct) => Some(instruct),
+        Entity::TextDisplayManipulate(manipulate) => Some(manipulate),
+        _ => None,
+    }
+}
+
+impl From<InstructEntity> for PushEntity {
+    fn from(value: InstructEntity) -> Self {
+        PushEntity::Instruct(value)
+    }
+}
+
+impl From<ManipulateEntity> for PushEntity {
+    fn from(value: ManipulateEntity) -> Self {
+        PushEntity::Manipulate(value)
+    }
+}
+
+impl TryFrom<SubscribeResp> for PushEntity {
+    type Error = NihilityCommonError;
+
+    fn try_from(value: SubscribeResp) -> Result<Self, Self::Error> {
+        let mut entity = match value.entity {
+            None => return Err(NihilityCommonError::CreatePushEntity),
+            Some(Entity::TextInstruct(instruct)) => {
+                PushEntity::Instruct(InstructEntity::from(instruct))
+            }
+            Some(Entity::SimpleManipulate(manipulate)) => {
+                PushEntity::Manipulate(ManipulateEntity::from(manipulate))
+            }
+            Some(Entity::TextDisplayManipulate(manipulate)) => {
+                PushEntity::Manipulate(ManipulateEntity::from(manipulate))
+            }
+            Some(Entity::DirectConnectionManipulate(manipulate)) => {
+                PushEntity::Manipulate(ManipulateEntity::try_from(manipulate)?)
+            }
+        };
+        if !value.trace_id.is_empty() {
+            entity.set_trace_id(&value.trace_id);
+        }
+        Ok(entity)
+    }
+}
+
+impl TryInto<SubscribeResp> for PushEntity {
+    type Error = NihilityCommonError;
+
+    fn try_into(self) -> Result<SubscribeResp, Self::Error> {
+        let trace_id = self.trace_id().map(String::from).unwrap_or_default();
+        let entity = match self {
+            PushEntity::Instruct(instruct) => Entity::TextInstruct(instruct.try_into()?),
+            PushEntity::Manipulate(manipulate) => match manipulate.manipulate {
+                ManipulateData::Text(_) => Entity::TextDisplayManipulate(manipulate.try_into()?),
+                ManipulateData::Simple => Entity::SimpleManipulate(manipulate.try_into()?),
+                ManipulateData::ConnectionParams(_) => {
+                    Entity::DirectConnectionManipulate(manipulate.try_into()?)
+                }
+            },
+        };
+        Ok(SubscribeResp {
+            entity: Some(entity),
+            trace_id,
+        })
+    }
+}
@@ -17,8 +17,30 @@ pub enum NihilityCommonError {
     CreateManipulateEntity,
     #[error("This Module Operate Is In Other Type, Please Create {0:?} Type Req")]
     CreateSubmoduleHeartbeat(OperateType),
+    #[error("This Module Operate Is In Other Type, Please Create {0:?} Type Req")]
+    CreateSubscribeReq(OperateType),
+    #[error("This SubscribeResp Don't Have Entity")]
+    CreatePushEntity,
+    #[error("This SubmoduleStatus Don't Have ConnectionParams")]
+    CreateSubmoduleStatus,
+    #[error("Submodule {0} Not Subscribed")]
+    NotSubscribed(String),
+    #[error("Submodule {0} Subscription Buffer Full")]
+    SubscriptionFull(String),
+    #[error("Rejected By Middleware: {0}")]
+    Rejected(String),
     #[error("Auth Id Not Exist")]
     AuthId,
+    #[error("Private Key Not Init")]
+    PrivateKey,
+    #[error("Session Key Not Exist")]
+    SessionKey,
+    #[error("Submodule {0} Public Key Not Match Pinned Key")]
+    KeyFingerprint(String),
+    #[error("Submodule {0} Register Proof Invalid")]
+    RegisterProof(String),
+    #[error("Core Public Key Not Match Discovery Fingerprint")]
+    CoreKeyNotMatch,
     #[error("Submodule Info Not Set")]
     SubmoduleInfo,
     #[error("This File Not Exist: {0:?}")]
@@ -27,22 +49,36 @@ pub enum NihilityCommonError {
     NotConnected(String),
     #[error("{0:?} Thread not started")]
     ThreadNotStarted(String),
+    #[error("Channel Closed")]
+    ChannelClosed,
     #[error("Config Field Missing")]
     ConfigFieldMissing,
+    #[error("Payload Encrypt Error")]
+    PayloadEncrypt,
+    #[error("Payload Decrypt Error")]
+    PayloadDecrypt,
     #[error("Log Config Error")]
     LogConfig,
+    #[error("Config {0} Invalid: {1}")]
+    Config(String, String),
     #[error("Std IO Error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("FromUtf8Error: {0}")]
     FromUtf8(#[from] std::string::FromUtf8Error),
+    #[error("Serde Json Error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
     #[error("Postcard: {0}")]
     Postcard(#[from] postcard::Error),
+    #[error("Hex Decode Error: {0}")]
+    Hex(#[from] hex::FromHexError),
     #[error("Parse Addr Error: {0}")]
     AddrParse(#[from] std::net::AddrParseError),
     #[error("Tonic Transport Error: {0}")]
     Tonic(#[from] tonic::transport::Error),
+    #[error("Join Error: {0}")]
+    Join(#[from] tokio::task::JoinError),
     #[error("Tonic Status: {0}")]
-    Status(#[from] tonic::Status),
+    Status(Box<tonic::Status>),
     #[error("Rsa Error: {0}")]
     Rsa(#[from] rsa::Error),
     #[error("Rsa Pkcs8 Error: {0}")]
@@ -50,3 +86,9 @@ pub enum NihilityCommonError {
     #[error("Rsa Spki Error: {0}")]
     RsaSpki(#[from] rsa::pkcs8::spki::Error),
 }
+
+impl From<tonic::Status> for NihilityCommonError {
+    fn from(value: tonic::Status) -> Self {
+        NihilityCommonError::Status(Box::new(value))
+    }
+}
@@ -2,13 +2,24 @@ use std::sync::OnceLock;
 
 use tracing::error;
 
+pub use communicat::discovery::{default_discovery_path, discover, Discovery, DiscoveryInfo};
+pub use communicat::event_bus::{Event, EventBus, Subscription, DEFAULT_EVENT_BUS_CAPACITY};
 pub use communicat::grpc::{
-    client::GrpcClient,
-    config::{GrpcClientConfig, GrpcServerConfig},
-    server::GrpcServer,
+    client::{GrpcAdminClient, GrpcClient},
+    config::{BindAddr, GrpcClientConfig, GrpcServerConfig},
+    server::{GrpcServer, ServerHandle},
 };
+pub use communicat::matcher::{InstructMatch, InstructMatcher, MatchKind};
+pub use communicat::middleware::{Middleware, MiddlewareContext, MiddlewareEntity};
+pub use communicat::rate_limit::{RateLimitRule, RateLimiter};
+pub use communicat::registry::SubmoduleRegistry;
+pub use communicat::router::{Deliver, Route, Router};
+pub use communicat::set_heartbeat_interval;
+pub use communicat::subscriber::{SubscriberHub, DEFAULT_SUBSCRIPTION_BUFFER};
+pub use communicat::trace::TraceContext;
 pub use communicat::NihilityClient;
 pub use communicat::NihilityServer;
+pub use entity::admin::SubmoduleStatus;
 pub use entity::instruct::{InstructData, InstructEntity, InstructInfoEntity, InstructType};
 pub use entity::manipulate::{
     ManipulateData, ManipulateEntity, ManipulateInfoEntity, ManipulateType,
@@ -16,13 +27,24 @@ pub use entity::manipulate::{
 pub use entity::module_operate::{
     ClientType, ConnParams, ConnectionType, ModuleOperate, OperateType, SubmoduleInfo,
 };
-pub use entity::response::ResponseCode;
+pub use entity::response::{ResponseCode, ResponseEntity};
+pub use entity::subscribe::PushEntity;
+pub use error::{NihilityCommonError, WrapResult};
+pub use tonic_health::{server::HealthReporter, ServingStatus};
+#[cfg(feature = "prometheus")]
+pub use utils::metrics::serve_metrics;
 pub use utils::{
+    audit::{set_audit_log_file, AuditEvent, AuditEventKind, AUDIT_TARGET},
     auth::{
-        core_authentication_core_init, get_auth_id, remove_submodule_public_key, set_auth_id,
-        set_core_public_key_path,
+        core_authentication_core_init, default_authenticator, get_auth_id, key_fingerprint,
+        remove_submodule_public_key, set_auth_id, set_core_public_key_path, set_submodule_key_dir,
+        AuthenticationMode, Authenticator, EncryptPayload, Identity, NoopAuthenticator,
+        RsaAuthenticator,
     },
-    log::{Log, LogConfig, LogLevel, LogOutType},
+    config::{ConfigWatcher, KeyConfig, NihilityConfig, ENV_PREFIX},
+    log::{Log, LogConfig, LogFormat, LogHandle, LogLevel, LogOutType, LogRotation, LogSpanEvents},
+    metrics::{metrics, Metrics, MetricsSide},
+    shutdown::{cancel_on_shutdown_signal, shutdown_client_on_cancel, shutdown_signal},
 };
 
 mod communicat;
@@ -30,18 +52,35 @@ mod entity;
 mod error;
 mod utils;
 
+#[allow(clippy::enum_variant_names)]
 pub(crate) mod manipulate {
     tonic::include_proto!("manipulate");
 }
 
+#[allow(clippy::enum_variant_names)]
 pub(crate) mod instruct {
     tonic::include_proto!("instruct");
 }
 
+#[allow(clippy::enum_variant_names)]
 pub(crate) mod submodule {
     tonic::include_proto!("submodule");
 }
 
+#[allow(clippy::enum_variant_names)]
+pub(crate) mod subscribe {
+    tonic::include_proto!("subscribe");
+}
+
+#[allow(clippy::enum_variant_names)]
+pub(crate) mod session {
+    tonic::include_proto!("session");
+}
+
+pub(crate) mod admin {
+    tonic::include_proto!("admin");
+}
+
 pub(crate) mod response_code {
     tonic::include_proto!("response_code");
 }
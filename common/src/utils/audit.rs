@@ -0,0 +1,113 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+
+use crate::error::WrapResult;
+
+/// 审计事件使用的tracing target，可单独配置过滤或输出
+pub const AUDIT_TARGET: &str = "nihility_audit";
+
+static AUDIT_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum AuditEventKind {
+    Register,
+    Offline,
+    AuthenticationFail,
+    RevokedAuthId,
+    KeyChange,
+    Revoke,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AuditEvent {
+    pub timestamp: String,
+    pub kind: AuditEventKind,
+    pub rpc: String,
+    pub submodule_name: Option<String>,
+    pub auth_id: Option<String>,
+    pub peer_addr: Option<SocketAddr>,
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, rpc: &str, peer_addr: Option<SocketAddr>) -> Self {
+        AuditEvent {
+            timestamp: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            kind,
+            rpc: rpc.to_string(),
+            submodule_name: None,
+            auth_id: None,
+            peer_addr,
+            detail: None,
+        }
+    }
+
+    pub fn submodule_name(mut self, submodule_name: &str) -> Self {
+        self.submodule_name = Some(submodule_name.to_string());
+        self
+    }
+
+    pub fn auth_id(mut self, auth_id: &str) -> Self {
+        self.auth_id = Some(auth_id.to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// 输出至审计target，设置了审计文件时同时追加写入一行JSON
+    pub fn emit(self) {
+        match self.kind {
+            AuditEventKind::Register | AuditEventKind::Offline => info!(
+                target: AUDIT_TARGET,
+                kind = ?self.kind,
+                rpc = %self.rpc,
+                submodule_name = ?self.submodule_name,
+                auth_id = ?self.auth_id,
+                peer_addr = ?self.peer_addr,
+                detail = ?self.detail,
+                "Audit Event"
+            ),
+            _ => warn!(
+                target: AUDIT_TARGET,
+                kind = ?self.kind,
+                rpc = %self.rpc,
+                submodule_name = ?self.submodule_name,
+                auth_id = ?self.auth_id,
+                peer_addr = ?self.peer_addr,
+                detail = ?self.detail,
+                "Audit Event"
+            ),
+        }
+        if let Some(file) = AUDIT_FILE.get() {
+            let result = serde_json::to_vec(&self)
+                .map_err(Into::into)
+                .and_then(|mut line| {
+                    line.push(b'\n');
+                    file.lock().unwrap().write_all(&line)
+                });
+            if let Err(e) = result {
+                error!("Write Audit Event Error: {:?}", e);
+            }
+        }
+    }
+}
+
+/// 审计事件以JSON Lines格式追加写入此文件
+pub fn set_audit_log_file<P: AsRef<Path>>(path: P) -> WrapResult<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    AUDIT_FILE.get_or_init(|| Mutex::new(file));
+    Ok(())
+}
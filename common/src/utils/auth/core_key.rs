@@ -0,0 +1,40 @@
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::error::WrapResult;
+
+use super::Signature;
+
+/// 使用核心模块私钥签名，签名内容为签名字段置空后实体的编码结果
+pub(crate) fn core_key_sign<T: Signature>(
+    private_key: &RsaPrivateKey,
+    entity: &mut T,
+    buf: &mut [u8],
+) -> WrapResult<()> {
+    entity.set_sign(Vec::new());
+    let digest = Sha256::digest(postcard::to_slice(&entity, buf)?);
+    let sign = private_key.sign(Pkcs1v15Sign::new::<Sha256>(), &digest)?;
+    entity.set_sign(sign);
+    Ok(())
+}
+
+pub(crate) fn core_key_verify<T: Signature>(
+    public_key: &RsaPublicKey,
+    entity: &mut T,
+    buf: &mut [u8],
+) -> bool {
+    let sign = entity.get_sign().clone();
+    entity.set_sign(Vec::new());
+    let result = match postcard::to_slice(&entity, buf) {
+        Ok(data) => public_key
+            .verify(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(data), &sign)
+            .is_ok(),
+        Err(e) => {
+            debug!("Encode Entity Error: {}", e);
+            false
+        }
+    };
+    entity.set_sign(sign);
+    result
+}
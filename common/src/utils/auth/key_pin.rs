@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File};
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use rsa::pkcs8::EncodePublicKey;
+use rsa::RsaPublicKey;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::error::{NihilityCommonError, WrapResult};
+
+/// 公钥指纹，为公钥DER编码的SHA-256
+pub fn key_fingerprint(public_key: &RsaPublicKey) -> WrapResult<String> {
+    Ok(hex::encode(Sha256::digest(
+        public_key.to_public_key_der()?.as_bytes(),
+    )))
+}
+
+/// 子模块首次注册时记录其公钥指纹，之后同名子模块注册时公钥指纹必须一致
+///
+/// 指纹不一致的注册会被拒绝并记录为待审批，由运维人员确认后替换记录的指纹
+#[derive(Default)]
+pub(super) struct KeyPinStore {
+    path: OnceLock<PathBuf>,
+    pins: RwLock<HashMap<String, String>>,
+    pending: RwLock<HashMap<String, String>>,
+}
+
+impl KeyPinStore {
+    /// 加载已记录的指纹，之后的变更都会写回此文件
+    pub(super) fn load<P: AsRef<Path>>(&self, path: P) -> WrapResult<()> {
+        let path = path.as_ref();
+        if path.exists() {
+            let pins: HashMap<String, String> = serde_json::from_reader(File::open(path)?)?;
+            info!("Load {} Pinned Submodule Key From {:?}", pins.len(), path);
+            self.pins.write().unwrap().extend(pins);
+        }
+        self.path.get_or_init(|| path.to_path_buf());
+        Ok(())
+    }
+
+    /// 重新读取指纹文件并替换内存中的记录，用于运维人员直接修改文件后生效
+    ///
+    /// 文件中已删除或已修改指纹的子模块，其待审批的指纹随之失效
+    pub(super) fn reload(&self) -> WrapResult<()> {
+        let Some(path) = self.path.get() else {
+            return Ok(());
+        };
+        let pins: HashMap<String, String> = match path.exists() {
+            true => serde_json::from_reader(File::open(path)?)?,
+            false => HashMap::new(),
+        };
+        info!("Reload {} Pinned Submodule Key From {:?}", pins.len(), path);
+        let mut pinned = self.pins.write().unwrap();
+        self.pending.write().unwrap().retain(|name, fingerprint| {
+            let retain = pins
+                .get(name)
+                .is_some_and(|pin| pinned.get(name) == Some(pin) && pin != fingerprint);
+            if !retain {
+                info!("Drop Submodule {} Pending Public Key {}", name, fingerprint);
+            }
+            retain
+        });
+        *pinned = pins;
+        Ok(())
+    }
+
+    pub(super) fn pins(&self) -> HashMap<String, String> {
+        self.pins.read().unwrap().clone()
+    }
+
+    pub(super) fn check(&self, name: &str, fingerprint: &str) -> WrapResult<()> {
+        let mut pins = self.pins.write().unwrap();
+        match pins.get(name) {
+            Some(pinned) if pinned == fingerprint => Ok(()),
+            Some(_) => {
+                warn!(
+                    "Submodule {} Register With Changed Public Key {}, Waiting For Approval",
+                    name, fingerprint
+                );
+                self.pending
+                    .write()
+                    .unwrap()
+                    .insert(name.to_string(), fingerprint.to_string());
+                Err(NihilityCommonError::KeyFingerprint(name.to_string()))
+            }
+            None => {
+                info!("Pin Submodule {} Public Key {}", name, fingerprint);
+                pins.insert(name.to_string(), fingerprint.to_string());
+                self.save(&pins)
+            }
+        }
+    }
+
+    pub(super) fn pending(&self) -> HashMap<String, String> {
+        self.pending.read().unwrap().clone()
+    }
+
+    pub(super) fn approve(&self, name: &str, fingerprint: &str) -> WrapResult<()> {
+        let mut pending = self.pending.write().unwrap();
+        match pending.get(name) {
+            Some(pending_fingerprint) if pending_fingerprint == fingerprint => {
+                pending.remove(name);
+                let mut pins = self.pins.write().unwrap();
+                pins.insert(name.to_string(), fingerprint.to_string());
+                info!("Approve Submodule {} Public Key {}", name, fingerprint);
+                self.save(&pins)
+            }
+            _ => Err(NihilityCommonError::KeyFingerprint(name.to_string())),
+        }
+    }
+
+    pub(super) fn unpin(&self, name: &str) -> WrapResult<()> {
+        self.pending.write().unwrap().remove(name);
+        let mut pins = self.pins.write().unwrap();
+        pins.remove(name);
+        self.save(&pins)
+    }
+
+    fn save(&self, pins: &HashMap<String, String>) -> WrapResult<()> {
+        if let Some(path) = self.path.get() {
+            if let Some(dir) = path.parent() {
+                create_dir_all(dir)?;
+            }
+            serde_json::to_writer_pretty(File::create(path)?, pins)?;
+        }
+        Ok(())
+    }
+}
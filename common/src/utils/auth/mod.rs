@@ -0,0 +1,170 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use rsa::RsaPublicKey;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+pub(crate) use core_key::{core_key_sign, core_key_verify};
+pub use key_pin::key_fingerprint;
+pub use noop_authenticator::NoopAuthenticator;
+pub use rsa_authenticator::RsaAuthenticator;
+
+use crate::error::WrapResult;
+use crate::{get_submodule_name, ModuleOperate, SubmoduleInfo};
+
+mod core_key;
+mod key_pin;
+mod noop_authenticator;
+mod rsa_authenticator;
+
+static SUBMODULE_AUTH_ID: OnceLock<String> = OnceLock::new();
+static DEFAULT_AUTHENTICATOR: OnceLock<Arc<RsaAuthenticator>> = OnceLock::new();
+pub static CORE_PUBLIC_KEY_PATH: OnceLock<String> = OnceLock::new();
+pub static SUBMODULE_KEY_DIR: OnceLock<String> = OnceLock::new();
+
+pub const CORE_PUBLIC_KEY_FILE_NAME: &str = "id_rsa.pub";
+pub const CORE_PRIVATE_KEY_FILE_NAME: &str = "id_rsa";
+pub const AUTHENTICATION_ERROR_MESSAGE: &str = "Authentication Error";
+pub const SUBMODULE_PUBLIC_KEY: &str = "public_key";
+pub const SUBMODULE_SESSION_SEED: &str = "session_seed";
+pub const SUBMODULE_REGISTER_PROOF: &str = "register_proof";
+
+/// 注册完成后消息的签名方式，`Hmac`使用注册时双方协商的会话密钥
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+pub enum AuthenticationMode {
+    #[default]
+    Rsa,
+    Hmac,
+}
+
+pub trait Signature: Serialize {
+    fn get_sign(&self) -> &Vec<u8>;
+    fn set_sign(&mut self, sign: Vec<u8>);
+}
+
+/// 可加密负载的消息，目前为`TextInstruct`和`TextDisplayManipulate`
+pub trait EncryptPayload {
+    fn get_payload(&self) -> &str;
+    fn set_payload(&mut self, payload: String);
+    fn get_encrypted_payload(&self) -> &Vec<u8>;
+    fn set_encrypted_payload(&mut self, encrypted_payload: Vec<u8>);
+}
+
+/// 从签名中解析出的签名方身份
+#[derive(Clone, Debug)]
+pub struct Identity {
+    pub auth_id: String,
+    pub mode: AuthenticationMode,
+    pub proof: Vec<u8>,
+}
+
+/// 消息签名与身份管理，`GrpcClient`与`GrpcServer`均通过此trait完成认证
+///
+/// `data`为签名字段设置为`auth_id`后实体的编码结果
+pub trait Authenticator: Send + Sync {
+    fn sign(&self, auth_id: &str, mode: &AuthenticationMode, data: &[u8]) -> WrapResult<Vec<u8>>;
+    /// 签名无法解析时返回`None`
+    fn identify(&self, sign: &[u8]) -> Option<Identity>;
+    fn verify(&self, identity: &Identity, data: &[u8]) -> bool;
+    /// 子模块注册前向注册信息中写入自身身份信息，`mode`为注册后使用的签名方式
+    fn prepare_register(
+        &self,
+        submodule_info: &mut SubmoduleInfo,
+        mode: &AuthenticationMode,
+    ) -> WrapResult<()>;
+    /// 子模块注册成功后记录核心模块分配的auth_id
+    fn register_success(&self, auth_id: &str, mode: &AuthenticationMode) -> WrapResult<()>;
+    /// 核心模块登记子模块身份，返回分配的auth_id
+    fn register(&self, module_operate: &mut ModuleOperate) -> WrapResult<String>;
+    fn revoke(&self, auth_id: &str) -> WrapResult<()>;
+    /// 已撤销的auth_id即使签名正确也不再接受
+    fn is_revoked(&self, _auth_id: &str) -> bool {
+        false
+    }
+    fn encrypt_payload(&self, auth_id: &str, message: &mut dyn EncryptPayload) -> WrapResult<()>;
+    /// 未加密的消息直接跳过
+    fn decrypt_payload(&self, message: &mut dyn EncryptPayload) -> WrapResult<()>;
+    /// 配置文件重新加载时调用，重新读取可热更新的身份信息
+    fn reload(&self) -> WrapResult<()> {
+        Ok(())
+    }
+}
+
+/// 进程内默认使用的认证方式，核心模块与子模块共用
+pub fn default_authenticator() -> Arc<RsaAuthenticator> {
+    DEFAULT_AUTHENTICATOR
+        .get_or_init(|| Arc::new(RsaAuthenticator::default()))
+        .clone()
+}
+
+pub fn set_core_public_key_path(path: &str) {
+    CORE_PUBLIC_KEY_PATH.get_or_init(|| path.to_string());
+}
+
+/// 设置后子模块密钥保存在此目录，重启后公钥不变，核心模块记录的公钥指纹才能匹配
+pub fn set_submodule_key_dir(path: &str) {
+    SUBMODULE_KEY_DIR.get_or_init(|| path.to_string());
+}
+
+pub fn core_authentication_core_init<P: AsRef<Path>>(key_dir: P) -> WrapResult<()> {
+    default_authenticator().init_core(key_dir)
+}
+
+pub fn get_auth_id_bytes() -> Vec<u8> {
+    match SUBMODULE_AUTH_ID.get() {
+        None => get_submodule_name().as_bytes().to_vec(),
+        Some(auth_id) => auth_id.as_bytes().to_vec(),
+    }
+}
+
+pub(crate) fn set_submodule_auth_id(auth_id: &str) {
+    debug!("Register Id: {}", auth_id);
+    SUBMODULE_AUTH_ID.get_or_init(|| auth_id.to_string());
+}
+
+pub fn get_auth_id<T: Signature>(entity: &T) -> WrapResult<String> {
+    Ok(String::from_utf8_lossy(entity.get_sign()).to_string())
+}
+
+pub fn set_auth_id<T: Signature>(auth_id: String, entity: &mut T) -> WrapResult<()> {
+    entity.set_sign(auth_id.as_bytes().into());
+    Ok(())
+}
+
+pub async fn remove_submodule_public_key(
+    module_operate: &ModuleOperate,
+) -> WrapResult<RsaPublicKey> {
+    default_authenticator().remove_public_key(&get_auth_id(module_operate)?)
+}
+
+pub fn signature<T: Signature>(
+    authenticator: &dyn Authenticator,
+    entity: &mut T,
+    auth_id: &str,
+    mode: &AuthenticationMode,
+    buf: &mut [u8],
+) -> WrapResult<()> {
+    entity.set_sign(auth_id.as_bytes().into());
+    let sign = authenticator.sign(auth_id, mode, postcard::to_slice(&entity, buf)?)?;
+    entity.set_sign(sign);
+    Ok(())
+}
+
+/// 校验通过后实体签名字段还原为auth_id
+pub fn verify<T: Signature>(
+    authenticator: &dyn Authenticator,
+    entity: &mut T,
+    buf: &mut [u8],
+) -> Option<Identity> {
+    let identity = authenticator.identify(entity.get_sign())?;
+    entity.set_sign(identity.auth_id.as_bytes().into());
+    match postcard::to_slice(&entity, buf) {
+        Ok(data) if authenticator.verify(&identity, data) => Some(identity),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Encode Entity Error: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::error::WrapResult;
+use crate::{ModuleOperate, SubmoduleInfo};
+
+use super::{AuthenticationMode, Authenticator, EncryptPayload, Identity};
+
+/// 不做任何校验，签名内容即为auth_id，仅用于本地开发调试
+#[derive(Default, Clone, Debug)]
+pub struct NoopAuthenticator;
+
+impl Authenticator for NoopAuthenticator {
+    fn sign(&self, auth_id: &str, _mode: &AuthenticationMode, _data: &[u8]) -> WrapResult<Vec<u8>> {
+        Ok(auth_id.as_bytes().to_vec())
+    }
+
+    fn identify(&self, sign: &[u8]) -> Option<Identity> {
+        Some(Identity {
+            auth_id: String::from_utf8_lossy(sign).to_string(),
+            mode: AuthenticationMode::default(),
+            proof: Vec::new(),
+        })
+    }
+
+    fn verify(&self, _identity: &Identity, _data: &[u8]) -> bool {
+        true
+    }
+
+    fn prepare_register(
+        &self,
+        _submodule_info: &mut SubmoduleInfo,
+        _mode: &AuthenticationMode,
+    ) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn register_success(&self, _auth_id: &str, _mode: &AuthenticationMode) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn register(&self, _module_operate: &mut ModuleOperate) -> WrapResult<String> {
+        Ok(Uuid::new_v4().to_string())
+    }
+
+    fn revoke(&self, _auth_id: &str) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn encrypt_payload(&self, _auth_id: &str, _message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn decrypt_payload(&self, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        if !message.get_encrypted_payload().is_empty() {
+            warn!("Noop Authenticator Can Not Decrypt Payload");
+        }
+        Ok(())
+    }
+}
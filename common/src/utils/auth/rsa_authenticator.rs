@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rsa::pkcs8::{EncodePublicKey, LineEnding};
+use rsa::{
+    pkcs8::DecodePrivateKey, pkcs8::DecodePublicKey, pkcs8::EncodePrivateKey, Pkcs1v15Encrypt,
+    Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::{get_submodule_name, ModuleOperate, SubmoduleInfo, CORE_FLAG};
+
+use super::key_pin::{key_fingerprint, KeyPinStore};
+use super::{
+    AuthenticationMode, Authenticator, EncryptPayload, Identity, CORE_PRIVATE_KEY_FILE_NAME,
+    CORE_PUBLIC_KEY_FILE_NAME, CORE_PUBLIC_KEY_PATH, SUBMODULE_KEY_DIR, SUBMODULE_PUBLIC_KEY,
+    SUBMODULE_REGISTER_PROOF, SUBMODULE_SESSION_SEED,
+};
+
+const BIT_SIZE: usize = 2000;
+const PAYLOAD_NONCE_SIZE: usize = 12;
+const SESSION_SEED_SIZE: usize = 32;
+const PINNED_KEY_FILE_NAME: &str = "pinned_keys.json";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedPayload {
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// 注册时交换RSA公钥，之后按`AuthenticationMode`使用RSA或HMAC会话密钥签名
+#[derive(Default)]
+pub struct RsaAuthenticator {
+    core_public_key_path: Option<String>,
+    key_dir: Option<PathBuf>,
+    private_key: OnceLock<RsaPrivateKey>,
+    public_key_map: RwLock<HashMap<String, RsaPublicKey>>,
+    session_key_map: RwLock<HashMap<String, Vec<u8>>>,
+    session_seed: OnceLock<Vec<u8>>,
+    revoked: RwLock<HashSet<String>>,
+    key_pins: KeyPinStore,
+}
+
+impl RsaAuthenticator {
+    /// 子模块使用，未指定时使用`set_core_public_key_path`设置的路径
+    pub fn with_core_public_key_path(path: &str) -> Self {
+        RsaAuthenticator {
+            core_public_key_path: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// 子模块密钥保存目录，未指定时使用`set_submodule_key_dir`设置的目录，都未设置时每次启动重新生成
+    pub fn with_key_dir<P: AsRef<Path>>(mut self, key_dir: P) -> Self {
+        self.key_dir = Some(key_dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn init_core<P: AsRef<Path>>(&self, key_dir: P) -> WrapResult<()> {
+        CORE_FLAG.get_or_init(|| true);
+        let dir_path = key_dir.as_ref();
+        self.load_or_create_key_pair(dir_path)?;
+        self.key_pins.load(dir_path.join(PINNED_KEY_FILE_NAME))
+    }
+
+    /// 公钥与记录指纹不一致而被拒绝注册的子模块，返回子模块名称与新公钥指纹
+    pub fn pending_key_changes(&self) -> HashMap<String, String> {
+        self.key_pins.pending()
+    }
+
+    /// 确认子模块更换公钥，`fingerprint`需与待审批的指纹一致，确认后子模块可重新注册
+    pub fn approve_key_change(&self, name: &str, fingerprint: &str) -> WrapResult<()> {
+        self.key_pins.approve(name, fingerprint)
+    }
+
+    /// 删除子模块记录的指纹，下次注册时重新记录
+    pub fn unpin_key(&self, name: &str) -> WrapResult<()> {
+        self.key_pins.unpin(name)
+    }
+
+    /// 已记录的子模块名称与公钥指纹
+    pub fn pinned_keys(&self) -> HashMap<String, String> {
+        self.key_pins.pins()
+    }
+
+    /// 重新读取核心模块密钥目录中的指纹文件，已建立的连接不受影响
+    pub fn reload_key_pins(&self) -> WrapResult<()> {
+        self.key_pins.reload()
+    }
+
+    pub fn remove_public_key(&self, auth_id: &str) -> WrapResult<RsaPublicKey> {
+        self.session_key_map.write().unwrap().remove(auth_id);
+        self.revoked.write().unwrap().insert(auth_id.to_string());
+        match self.public_key_map.write().unwrap().remove(auth_id) {
+            None => Err(NihilityCommonError::AuthId),
+            Some(public_key) => Ok(public_key),
+        }
+    }
+
+    fn init_submodule(&self) -> WrapResult<RsaPublicKey> {
+        CORE_FLAG.get_or_init(|| false);
+        let core_public_key_path = match (&self.core_public_key_path, CORE_PUBLIC_KEY_PATH.get()) {
+            (Some(path), _) | (None, Some(path)) => path.to_string(),
+            (None, None) => CORE_PUBLIC_KEY_FILE_NAME.to_string(),
+        };
+        let public_key = RsaPublicKey::read_public_key_pem_file(core_public_key_path)?;
+        self.public_key_map
+            .write()
+            .unwrap()
+            .insert(get_submodule_name(), public_key);
+        let key_dir = self
+            .key_dir
+            .clone()
+            .or_else(|| SUBMODULE_KEY_DIR.get().map(PathBuf::from));
+        match key_dir {
+            None => Ok(RsaPublicKey::from(self.get_or_create_private_key()?)),
+            Some(key_dir) => Ok(RsaPublicKey::from(self.load_or_create_key_pair(&key_dir)?)),
+        }
+    }
+
+    fn load_or_create_key_pair(&self, dir_path: &Path) -> WrapResult<&RsaPrivateKey> {
+        let private_key_path = dir_path.join(CORE_PRIVATE_KEY_FILE_NAME);
+        let public_key_path = dir_path.join(CORE_PUBLIC_KEY_FILE_NAME);
+        if private_key_path.exists() && public_key_path.exists() {
+            let private_key = RsaPrivateKey::read_pkcs8_pem_file(private_key_path)?;
+            Ok(self.private_key.get_or_init(|| private_key))
+        } else {
+            info!("Private Key Or Public Key Not Exists, Create New Key");
+            create_dir_all(dir_path)?;
+            let private_key = self.get_or_create_private_key()?;
+            private_key.write_pkcs8_pem_file(private_key_path, LineEnding::default())?;
+            let public_key = RsaPublicKey::from(private_key);
+            public_key.write_public_key_pem_file(public_key_path, LineEnding::default())?;
+            Ok(private_key)
+        }
+    }
+
+    fn get_or_create_private_key(&self) -> WrapResult<&RsaPrivateKey> {
+        if let Some(private_key) = self.private_key.get() {
+            return Ok(private_key);
+        }
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), BIT_SIZE)?;
+        Ok(self.private_key.get_or_init(|| private_key))
+    }
+
+    fn get_private_key(&self) -> WrapResult<&RsaPrivateKey> {
+        self.private_key
+            .get()
+            .ok_or(NihilityCommonError::PrivateKey)
+    }
+
+    fn get_public_key(&self, auth_id: &str) -> WrapResult<RsaPublicKey> {
+        match self.public_key_map.read().unwrap().get(auth_id) {
+            None => Err(NihilityCommonError::AuthId),
+            Some(public_key) => Ok(public_key.clone()),
+        }
+    }
+
+    fn get_session_key(&self, auth_id: &str) -> Option<Vec<u8>> {
+        self.session_key_map.read().unwrap().get(auth_id).cloned()
+    }
+
+    /// 生成会话种子并使用核心模块公钥加密，用于注册时提交
+    fn create_session_seed(&self) -> WrapResult<String> {
+        let seed = self.session_seed.get_or_init(|| {
+            let mut seed = vec![0u8; SESSION_SEED_SIZE];
+            rand::thread_rng().fill_bytes(&mut seed);
+            seed
+        });
+        let public_key = self.get_public_key(&get_submodule_name())?;
+        Ok(hex::encode(public_key.encrypt(
+            &mut rand::thread_rng(),
+            Pkcs1v15Encrypt,
+            seed,
+        )?))
+    }
+}
+
+impl Authenticator for RsaAuthenticator {
+    fn sign(&self, auth_id: &str, mode: &AuthenticationMode, data: &[u8]) -> WrapResult<Vec<u8>> {
+        match mode {
+            AuthenticationMode::Rsa => Ok(self.get_public_key(auth_id)?.encrypt(
+                &mut rand::thread_rng(),
+                Pkcs1v15Encrypt,
+                format!("{}|{}", auth_id, hex::encode(Sha256::digest(data))).as_bytes(),
+            )?),
+            AuthenticationMode::Hmac => {
+                let session_key = self
+                    .get_session_key(auth_id)
+                    .ok_or(NihilityCommonError::SessionKey)?;
+                let mut mac = new_mac(&session_key);
+                mac.update(data);
+                Ok(
+                    format!("{}|{}", auth_id, hex::encode(mac.finalize().into_bytes()))
+                        .into_bytes(),
+                )
+            }
+        }
+    }
+
+    fn identify(&self, sign: &[u8]) -> Option<Identity> {
+        if let Ok(sign) = std::str::from_utf8(sign) {
+            if let Some((auth_id, sign_hex)) = sign.split_once('|') {
+                if self.session_key_map.read().unwrap().contains_key(auth_id) {
+                    return Some(Identity {
+                        auth_id: auth_id.to_string(),
+                        mode: AuthenticationMode::Hmac,
+                        proof: hex::decode(sign_hex).ok()?,
+                    });
+                }
+            }
+        }
+        let sign = match self.get_private_key().ok()?.decrypt(Pkcs1v15Encrypt, sign) {
+            Ok(sign_data) => String::from_utf8(sign_data).ok()?,
+            Err(e) => {
+                debug!("Decrypt Error: {}", e);
+                return None;
+            }
+        };
+        let (auth_id, hash_hex) = sign.split_once('|')?;
+        if self.session_key_map.read().unwrap().contains_key(auth_id) {
+            debug!("Rsa Sign Rejected For Hmac Session: {}", auth_id);
+            return None;
+        }
+        Some(Identity {
+            auth_id: auth_id.to_string(),
+            mode: AuthenticationMode::Rsa,
+            proof: hex::decode(hash_hex).ok()?,
+        })
+    }
+
+    fn verify(&self, identity: &Identity, data: &[u8]) -> bool {
+        match identity.mode {
+            AuthenticationMode::Rsa => Sha256::digest(data).as_slice() == identity.proof,
+            AuthenticationMode::Hmac => match self.get_session_key(&identity.auth_id) {
+                None => false,
+                Some(session_key) => {
+                    let mut mac = new_mac(&session_key);
+                    mac.update(data);
+                    mac.verify_slice(&identity.proof).is_ok()
+                }
+            },
+        }
+    }
+
+    /// 使用子模块私钥签名注册信息，核心模块据此确认子模块持有所提交公钥的私钥
+    ///
+    /// 仅`Hmac`方式提交会话种子，协商会话密钥后该auth_id不再接受RSA签名
+    fn prepare_register(
+        &self,
+        submodule_info: &mut SubmoduleInfo,
+        mode: &AuthenticationMode,
+    ) -> WrapResult<()> {
+        let public_key = self.init_submodule()?;
+        let conn_config = &mut submodule_info.conn_params.conn_config;
+        conn_config.insert(
+            SUBMODULE_PUBLIC_KEY.to_string(),
+            public_key.to_public_key_pem(LineEnding::default())?,
+        );
+        if let AuthenticationMode::Hmac = mode {
+            conn_config.insert(
+                SUBMODULE_SESSION_SEED.to_string(),
+                self.create_session_seed()?,
+            );
+        }
+        let proof = self.get_private_key()?.sign(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &register_digest(&get_submodule_name(), submodule_info)?,
+        )?;
+        submodule_info
+            .conn_params
+            .conn_config
+            .insert(SUBMODULE_REGISTER_PROOF.to_string(), hex::encode(proof));
+        Ok(())
+    }
+
+    fn register_success(&self, auth_id: &str, mode: &AuthenticationMode) -> WrapResult<()> {
+        let core_public_key = self.get_public_key(&get_submodule_name())?;
+        if let (AuthenticationMode::Hmac, Some(seed)) = (mode, self.session_seed.get()) {
+            self.session_key_map
+                .write()
+                .unwrap()
+                .insert(auth_id.to_string(), derive_session_key(seed, auth_id));
+        }
+        self.public_key_map
+            .write()
+            .unwrap()
+            .insert(auth_id.to_string(), core_public_key);
+        Ok(())
+    }
+
+    fn register(&self, module_operate: &mut ModuleOperate) -> WrapResult<String> {
+        let uuid = Uuid::new_v4().to_string();
+        let Some(info) = &mut module_operate.info else {
+            return Err(NihilityCommonError::ConfigFieldMissing);
+        };
+        let Some(public_key_string) = info.conn_params.conn_config.get(SUBMODULE_PUBLIC_KEY) else {
+            return Err(NihilityCommonError::ConfigFieldMissing);
+        };
+        let public_key = RsaPublicKey::from_public_key_pem(public_key_string.as_str())?;
+        let proof = info
+            .conn_params
+            .conn_config
+            .remove(SUBMODULE_REGISTER_PROOF)
+            .ok_or(NihilityCommonError::ConfigFieldMissing)?;
+        if public_key
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &register_digest(&module_operate.name, info)?,
+                &hex::decode(proof)?,
+            )
+            .is_err()
+        {
+            return Err(NihilityCommonError::RegisterProof(
+                module_operate.name.to_string(),
+            ));
+        }
+        self.key_pins
+            .check(&module_operate.name, &key_fingerprint(&public_key)?)?;
+        if let Some(session_seed) = info.conn_params.conn_config.remove(SUBMODULE_SESSION_SEED) {
+            let seed = self
+                .get_private_key()?
+                .decrypt(Pkcs1v15Encrypt, &hex::decode(session_seed)?)?;
+            self.session_key_map
+                .write()
+                .unwrap()
+                .insert(uuid.to_string(), derive_session_key(&seed, &uuid));
+        }
+        self.public_key_map
+            .write()
+            .unwrap()
+            .insert(uuid.to_string(), public_key);
+        Ok(uuid)
+    }
+
+    fn revoke(&self, auth_id: &str) -> WrapResult<()> {
+        self.remove_public_key(auth_id).map(|_| ())
+    }
+
+    fn is_revoked(&self, auth_id: &str) -> bool {
+        self.revoked.read().unwrap().contains(auth_id)
+    }
+
+    /// 使用单次随机生成的对称密钥加密负载，对称密钥由接收方公钥加密后随负载一同发送
+    fn encrypt_payload(&self, auth_id: &str, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        let public_key = self.get_public_key(auth_id)?;
+        let mut rng = rand::thread_rng();
+        let key = Aes256Gcm::generate_key(&mut rng);
+        let nonce = Aes256Gcm::generate_nonce(&mut rng);
+        let data = Aes256Gcm::new(&key)
+            .encrypt(&nonce, message.get_payload().as_bytes())
+            .map_err(|_| NihilityCommonError::PayloadEncrypt)?;
+        let payload = EncryptedPayload {
+            key: public_key.encrypt(&mut rng, Pkcs1v15Encrypt, key.as_slice())?,
+            nonce: nonce.to_vec(),
+            data,
+        };
+        message.set_encrypted_payload(postcard::to_allocvec(&payload)?);
+        message.set_payload(String::new());
+        Ok(())
+    }
+
+    fn decrypt_payload(&self, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        if message.get_encrypted_payload().is_empty() {
+            return Ok(());
+        }
+        let payload: EncryptedPayload = postcard::from_bytes(message.get_encrypted_payload())?;
+        if payload.nonce.len() != PAYLOAD_NONCE_SIZE {
+            return Err(NihilityCommonError::PayloadDecrypt);
+        }
+        let key = self
+            .get_private_key()?
+            .decrypt(Pkcs1v15Encrypt, &payload.key)?;
+        let data = Aes256Gcm::new_from_slice(&key)
+            .map_err(|_| NihilityCommonError::PayloadDecrypt)?
+            .decrypt(Nonce::from_slice(&payload.nonce), payload.data.as_slice())
+            .map_err(|_| NihilityCommonError::PayloadDecrypt)?;
+        message.set_payload(String::from_utf8(data)?);
+        message.set_encrypted_payload(Vec::new());
+        Ok(())
+    }
+
+    fn reload(&self) -> WrapResult<()> {
+        self.reload_key_pins()
+    }
+}
+
+/// 注册证明签名的内容，为子模块名称与不含注册证明的注册信息
+fn register_digest(name: &str, submodule_info: &SubmoduleInfo) -> WrapResult<Vec<u8>> {
+    Ok(Sha256::digest(postcard::to_allocvec(&(name, submodule_info))?).to_vec())
+}
+
+fn derive_session_key(seed: &[u8], auth_id: &str) -> Vec<u8> {
+    let mut mac = new_mac(seed);
+    mac.update(auth_id.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn new_mac(key: &[u8]) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(key).expect("Hmac Accept Any Key Size")
+}
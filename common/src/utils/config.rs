@@ -0,0 +1,368 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tokio::{select, spawn, time};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::communicat::grpc::config::{GrpcClientConfig, GrpcServerConfig, UNIX_SCHEME};
+use crate::communicat::rate_limit::{RateLimitRule, RateLimiter};
+use crate::communicat::set_heartbeat_interval;
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::utils::auth::{set_core_public_key_path, set_submodule_key_dir, Authenticator};
+use crate::utils::log::{Log, LogConfig, LogOutType, LogRotation};
+use crate::{set_default_receiver_submodule, set_submodule_name};
+
+/// 环境变量前缀，嵌套字段使用双下划线分隔，如`NIHILITY_SERVER__BIND_PORT`
+pub const ENV_PREFIX: &str = "NIHILITY_";
+const ENV_SEPARATOR: &str = "__";
+const HEARTBEAT_SECS: u64 = 30;
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const CORE_KEY_DIR: &str = "auth";
+const CORE_PUBLIC_KEY_PATH: &str = "auth/id_rsa.pub";
+
+enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+/// 密钥相关路径
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct KeyConfig {
+    /// 核心模块密钥目录
+    pub core_key_dir: String,
+    /// 子模块读取的核心模块公钥
+    pub core_public_key_path: String,
+    /// 未设置时子模块每次启动生成新密钥
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submodule_key_dir: Option<String>,
+}
+
+/// 核心模块与子模块共用的配置，`submodule_name`与`default_receiver`仅子模块需要
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct NihilityConfig {
+    pub submodule_name: String,
+    pub default_receiver: String,
+    pub heartbeat_secs: u64,
+    /// 核心模块的限速规则，可热更新
+    pub rate_limit: Vec<RateLimitRule>,
+    pub key: KeyConfig,
+    pub server: GrpcServerConfig,
+    pub client: GrpcClientConfig,
+    pub log: Vec<LogConfig>,
+}
+
+impl Default for KeyConfig {
+    fn default() -> Self {
+        KeyConfig {
+            core_key_dir: CORE_KEY_DIR.to_string(),
+            core_public_key_path: CORE_PUBLIC_KEY_PATH.to_string(),
+            submodule_key_dir: None,
+        }
+    }
+}
+
+impl Default for NihilityConfig {
+    fn default() -> Self {
+        NihilityConfig {
+            submodule_name: String::new(),
+            default_receiver: String::new(),
+            heartbeat_secs: HEARTBEAT_SECS,
+            rate_limit: Vec::new(),
+            key: KeyConfig::default(),
+            server: GrpcServerConfig::default(),
+            client: GrpcClientConfig::default(),
+            log: vec![LogConfig::default()],
+        }
+    }
+}
+
+impl NihilityConfig {
+    /// 读取配置文件并应用`NIHILITY_`环境变量，完成后校验
+    pub fn load<P: AsRef<Path>>(path: P) -> WrapResult<Self> {
+        let mut config = Self::from_file(path)?;
+        config.apply_env_overrides(std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 按扩展名解析toml、yaml或json，缺少的字段使用默认值
+    pub fn from_file<P: AsRef<Path>>(path: P) -> WrapResult<Self> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let content = fs::read_to_string(path)?;
+        let result = match format {
+            ConfigFormat::Toml => toml::from_str(&content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        };
+        result.map_err(|e| NihilityCommonError::Config(path.display().to_string(), e))
+    }
+
+    /// 按配置格式写出默认配置，作为配置文件模板
+    pub fn write_template<P: AsRef<Path>>(path: P) -> WrapResult<()> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let config = NihilityConfig::default();
+        let content = match format {
+            ConfigFormat::Toml => toml::to_string_pretty(&config).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::to_string(&config).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::to_string_pretty(&config).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| NihilityCommonError::Config(path.display().to_string(), e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// 只处理`NIHILITY_`开头的变量，字段不存在或值类型不符时返回对应变量名
+    pub fn apply_env_overrides<I>(&mut self, vars: I) -> WrapResult<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut tree = serde_json::to_value(&*self)?;
+        for (key, value) in vars {
+            let Some(field_path) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let field_path = field_path.to_lowercase();
+            let Some(field) =
+                field_path
+                    .split(ENV_SEPARATOR)
+                    .try_fold(&mut tree, |node, segment| match node {
+                        Value::Object(map) => map.get_mut(segment),
+                        Value::Array(list) => {
+                            segment.parse::<usize>().ok().and_then(|i| list.get_mut(i))
+                        }
+                        _ => None,
+                    })
+            else {
+                return Err(NihilityCommonError::Config(
+                    key,
+                    String::from("Unknown Field"),
+                ));
+            };
+            // 原为字符串的字段不按json解析，避免名称等被解析为数字
+            *field = match (serde_json::from_str(&value).ok(), &*field) {
+                (Some(parsed @ (Value::Object(_) | Value::Array(_))), _) => parsed,
+                (Some(parsed), current) if !current.is_string() => parsed,
+                _ => Value::String(value),
+            };
+            *self = serde_json::from_value(tree.clone())
+                .map_err(|e| NihilityCommonError::Config(key, e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> WrapResult<()> {
+        if self.heartbeat_secs == 0 {
+            return Err(invalid("heartbeat_secs", "Must Be Greater Than 0"));
+        }
+        if self.key.core_key_dir.is_empty() {
+            return Err(invalid("key.core_key_dir", "Must Not Be Empty"));
+        }
+        if self.key.core_public_key_path.is_empty() {
+            return Err(invalid("key.core_public_key_path", "Must Not Be Empty"));
+        }
+        if self.server.bind_addrs.is_empty() {
+            return Err(invalid("server.bind_addrs", "Must Not Be Empty"));
+        }
+        for (index, bind_addr) in self.server.bind_addrs.iter().enumerate() {
+            if self.server.bind_addrs[..index].contains(bind_addr) {
+                return Err(invalid(
+                    &format!("server.bind_addrs[{}]", index),
+                    "Duplicate Bind Addr",
+                ));
+            }
+        }
+        let server_address = &self.client.server_address;
+        if !["http://", "https://", UNIX_SCHEME]
+            .iter()
+            .any(|scheme| server_address.starts_with(scheme))
+        {
+            return Err(invalid(
+                "client.server_address",
+                "Must Start With http://, https:// Or unix:",
+            ));
+        }
+        for (index, log) in self.log.iter().enumerate() {
+            if let LogOutType::File(path) = &log.out_type {
+                if path.is_empty() {
+                    return Err(invalid(
+                        &format!("log[{}].out_type", index),
+                        "File Path Must Not Be Empty",
+                    ));
+                }
+            }
+            if let Err(NihilityCommonError::Config(_, reason)) = log.parse_utc_offset() {
+                return Err(invalid(&format!("log[{}].utc_offset", index), &reason));
+            }
+            if let Err(NihilityCommonError::Config(_, reason)) = log.env_filter() {
+                return Err(invalid(&format!("log[{}].filter", index), &reason));
+            }
+            if log.rotation == LogRotation::Size(0) {
+                return Err(invalid(
+                    &format!("log[{}].rotation", index),
+                    "Size Must Be Greater Than 0",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置子模块名称、默认接收子模块、密钥路径与心跳间隔，均为进程内首次设置生效
+    pub fn apply(&self) {
+        if !self.submodule_name.is_empty() {
+            set_submodule_name(&self.submodule_name);
+        }
+        if !self.default_receiver.is_empty() {
+            set_default_receiver_submodule(&self.default_receiver);
+        }
+        set_core_public_key_path(&self.key.core_public_key_path);
+        if let Some(submodule_key_dir) = &self.key.submodule_key_dir {
+            set_submodule_key_dir(submodule_key_dir);
+        }
+        set_heartbeat_interval(self.heartbeat_secs);
+    }
+}
+
+/// 轮询配置文件，内容变化时重新加载并应用日志级别、限速规则与公钥指纹记录，已建立的连接不受影响
+///
+/// 其余字段变化时仅提示需要重启
+pub struct ConfigWatcher {
+    path: PathBuf,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+}
+
+impl ConfigWatcher {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        ConfigWatcher {
+            path: path.as_ref().to_path_buf(),
+            rate_limiter: None,
+            authenticator: None,
+        }
+    }
+
+    /// 与`NihilityServer::set_rate_limiter`设置的为同一实例
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// 重新加载时调用`Authenticator::reload`
+    pub fn with_authenticator(mut self, authenticator: Arc<dyn Authenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// 应用可热更新的部分，`Log`未初始化时跳过日志级别
+    pub fn reload(&self, config: &NihilityConfig) -> WrapResult<()> {
+        if let Err(e) = Log::reload(&config.log) {
+            debug!("Reload Log Config Error: {}", e);
+        }
+        if let Some(authenticator) = &self.authenticator {
+            authenticator.reload()?;
+        }
+        if let Some(rate_limiter) = &self.rate_limiter {
+            if rate_limiter.rules() != config.rate_limit {
+                info!("Reload {} Rate Limit Rules", config.rate_limit.len());
+                rate_limiter.set_rules(config.rate_limit.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// 需在tokio运行时中调用，加载或校验失败时保留当前配置，之后每次轮询重试直到加载成功
+    pub fn watch(self, cancellation_token: CancellationToken) -> WrapResult<JoinHandle<()>> {
+        let mut content = fs::read_to_string(&self.path)?;
+        let mut current = NihilityConfig::load(&self.path)?;
+        Ok(spawn(async move {
+            let mut interval = time::interval(CONFIG_POLL_INTERVAL);
+            loop {
+                select! {
+                    _ = interval.tick() => {},
+                    _ = cancellation_token.cancelled() => break,
+                }
+                let new_content = match fs::read_to_string(&self.path) {
+                    Ok(new_content) if new_content != content => new_content,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        debug!("Read Config File {:?} Error: {}", &self.path, e);
+                        continue;
+                    }
+                };
+                let config = match NihilityConfig::load(&self.path) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        error!("Reload Config File {:?} Error: {}", &self.path, e);
+                        continue;
+                    }
+                };
+                content = new_content;
+                info!("Config File {:?} Changed, Reload", &self.path);
+                warn_restart_required(&current, &config);
+                if let Err(e) = self.reload(&config) {
+                    error!("Apply Config Error: {}", e);
+                }
+                current = config;
+            }
+        }))
+    }
+}
+
+fn warn_restart_required(current: &NihilityConfig, config: &NihilityConfig) {
+    let sections = [
+        (
+            "submodule_name",
+            json!(current.submodule_name),
+            json!(config.submodule_name),
+        ),
+        (
+            "default_receiver",
+            json!(current.default_receiver),
+            json!(config.default_receiver),
+        ),
+        (
+            "heartbeat_secs",
+            json!(current.heartbeat_secs),
+            json!(config.heartbeat_secs),
+        ),
+        ("key", json!(current.key), json!(config.key)),
+        ("server", json!(current.server), json!(config.server)),
+        ("client", json!(current.client), json!(config.client)),
+    ];
+    for (field, current, config) in sections {
+        if current != config {
+            warn!("Config {} Changed, Restart Required", field);
+        }
+    }
+}
+
+fn invalid(field: &str, reason: &str) -> NihilityCommonError {
+    NihilityCommonError::Config(field.to_string(), reason.to_string())
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> WrapResult<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+            Some("json") => Ok(ConfigFormat::Json),
+            _ => Err(NihilityCommonError::Config(
+                path.display().to_string(),
+                String::from("Unsupported Config Format"),
+            )),
+        }
+    }
+}
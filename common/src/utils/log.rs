@@ -1,27 +1,33 @@
-use std::sync::OnceLock;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
 use serde::{Deserialize, Serialize};
+use time::format_description::BorrowedFormatItem;
 use time::macros::format_description;
 use time::UtcOffset;
-use tracing::debug;
 use tracing::level_filters::LevelFilter;
+use tracing::{debug, warn};
+use tracing_appender::non_blocking;
+use tracing_appender::non_blocking::NonBlocking;
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_appender::{non_blocking, rolling};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::fmt::time::OffsetTime;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, Layer};
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
 
-use crate::error::WrapResult;
+use crate::error::{NihilityCommonError, WrapResult};
+use crate::utils::rolling::SizeRollingAppender;
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
 pub enum LogOutType {
     #[default]
     Console,
     File(String),
 }
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum LogLevel {
     Trace,
@@ -32,11 +38,58 @@ pub enum LogLevel {
     Error,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// 文件输出的滚动方式
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+    /// 当前文件超过指定字节数时滚动
+    Size(u64),
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    #[default]
+    Full,
+    /// 每行一个JSON对象，便于日志采集
+    Json,
+}
+
+/// 对应`tracing_subscriber::fmt::format::FmtSpan`
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogSpanEvents {
+    #[default]
+    None,
+    New,
+    Close,
+    Active,
+    Full,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
 pub struct LogConfig {
     pub enable: bool,
     pub out_type: LogOutType,
     pub level: LogLevel,
+    /// `EnvFilter`格式的指令，如`nihility_common::communicat=debug,tonic=warn`，未匹配的target使用`level`
+    pub filter: String,
+    pub format: LogFormat,
+    /// 时间戳偏移，格式为`+08:00`，`local`为系统本地时区
+    pub utc_offset: String,
+    /// 仅对控制台输出生效
+    pub ansi: bool,
+    pub span_events: LogSpanEvents,
+    /// 以下仅对文件输出生效
+    pub file_prefix: String,
+    pub rotation: LogRotation,
+    /// 保留的日志文件数，0为不限制
+    pub max_files: usize,
     pub with_file: bool,
     pub with_line_number: bool,
     pub with_thread_ids: bool,
@@ -45,10 +98,29 @@ pub struct LogConfig {
 
 pub struct Log;
 
-static CONSOLE_WORK_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
-static FILE_WORK_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+/// `Log::init`返回的句柄，持有各输出的后台写入线程，drop时写出缓冲中的日志
+///
+/// 需持有至进程退出，提前drop后的日志将丢失
+#[must_use = "dropping LogHandle stops writing logs"]
+pub struct LogHandle {
+    _guards: Vec<WorkerGuard>,
+}
+
+/// `init_once`初始化时持有的句柄
+static INIT_ONCE_HANDLE: Mutex<Option<LogHandle>> = Mutex::new(None);
+/// 与`init`时的配置一一对应，未启用的输出为`None`
+static RELOAD_HANDLES: OnceLock<Vec<Option<ReloadHandle>>> = OnceLock::new();
+
+type ReloadHandle = reload::Handle<EnvFilter, Registry>;
 
-const LOG_FILE_NAME: &str = ".log";
+const DEFAULT_FILE_PREFIX: &str = "nihility";
+const LOG_FILE_SUFFIX: &str = "log";
+const LOCAL_UTC_OFFSET: &str = "local";
+const DEFAULT_UTC_OFFSET: &str = "+08:00";
+const UTC_OFFSET_FORMAT: &[BorrowedFormatItem] =
+    format_description!("[offset_hour sign:mandatory]:[offset_minute]");
+const TIMESTAMP_FORMAT: &[BorrowedFormatItem] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]");
 
 impl Default for LogConfig {
     fn default() -> Self {
@@ -56,6 +128,14 @@ impl Default for LogConfig {
             enable: true,
             out_type: LogOutType::default(),
             level: LogLevel::default(),
+            filter: String::new(),
+            format: LogFormat::default(),
+            utc_offset: String::from(DEFAULT_UTC_OFFSET),
+            ansi: false,
+            span_events: LogSpanEvents::default(),
+            file_prefix: String::from(DEFAULT_FILE_PREFIX),
+            rotation: LogRotation::default(),
+            max_files: 0,
             with_file: false,
             with_line_number: false,
             with_thread_ids: true,
@@ -64,53 +144,164 @@ impl Default for LogConfig {
     }
 }
 
+impl LogConfig {
+    /// 解析`utc_offset`，系统本地时区无法获取时使用UTC
+    pub fn parse_utc_offset(&self) -> WrapResult<UtcOffset> {
+        if self.utc_offset.eq_ignore_ascii_case(LOCAL_UTC_OFFSET) {
+            return Ok(UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC));
+        }
+        UtcOffset::parse(&self.utc_offset, UTC_OFFSET_FORMAT).map_err(|_| {
+            NihilityCommonError::Config(
+                String::from("utc_offset"),
+                format!("{} Is Not Like +08:00 Or local", self.utc_offset),
+            )
+        })
+    }
+
+    /// 以`level`为默认级别，再按`filter`覆盖各target的级别
+    pub fn env_filter(&self) -> WrapResult<EnvFilter> {
+        let filter = EnvFilter::builder()
+            .parse(&self.filter)
+            .map_err(|e| NihilityCommonError::Config(String::from("filter"), e.to_string()))?;
+        Ok(filter.add_directive(LevelFilter::from(&self.level).into()))
+    }
+
+    fn file_writer(&self, out_path: &str) -> WrapResult<(NonBlocking, WorkerGuard)> {
+        Ok(match self.rotation {
+            LogRotation::Size(max_size) => non_blocking(SizeRollingAppender::new(
+                Path::new(out_path),
+                &self.file_prefix,
+                LOG_FILE_SUFFIX,
+                max_size,
+                self.max_files,
+            )?),
+            _ => {
+                let rotation = match self.rotation {
+                    LogRotation::Hourly => Rotation::HOURLY,
+                    LogRotation::Never => Rotation::NEVER,
+                    _ => Rotation::DAILY,
+                };
+                let file_appender = RollingFileAppender::builder()
+                    .rotation(rotation)
+                    .filename_prefix(&self.file_prefix)
+                    .filename_suffix(LOG_FILE_SUFFIX)
+                    .max_log_files(self.max_files)
+                    .build(out_path)
+                    .map_err(|e| {
+                        NihilityCommonError::Config(String::from("out_type"), e.to_string())
+                    })?;
+                non_blocking(file_appender)
+            }
+        })
+    }
+}
+
 impl Log {
-    pub fn init(configs: &Vec<LogConfig>) -> WrapResult<()> {
+    /// 设置全局日志输出，每个进程仅可成功调用一次，已设置时返回错误
+    pub fn init(configs: &[LogConfig]) -> WrapResult<LogHandle> {
         let mut layers = Vec::new();
+        let mut handles = Vec::new();
+        let mut guards = Vec::new();
 
         for config in configs {
             if !config.enable {
+                handles.push(None);
                 continue;
             }
-            let non_blocking = match &config.out_type {
-                LogOutType::Console => {
-                    let (non_blocking, guard) = non_blocking(std::io::stdout());
-                    CONSOLE_WORK_GUARD.get_or_init(|| guard);
-                    non_blocking
-                }
-                LogOutType::File(out_path) => {
-                    let file_appender = rolling::daily(out_path, LOG_FILE_NAME);
-                    let (non_blocking, guard) = non_blocking(file_appender);
-                    FILE_WORK_GUARD.get_or_init(|| guard);
-                    non_blocking
-                }
+            let (non_blocking, guard) = match &config.out_type {
+                LogOutType::Console => non_blocking(std::io::stdout()),
+                LogOutType::File(out_path) => config.file_writer(out_path)?,
             };
-            let timer = OffsetTime::new(
-                UtcOffset::from_hms(8, 0, 0).unwrap(),
-                format_description!(
-                    "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]"
-                ),
-            );
+            guards.push(guard);
+            let timer = OffsetTime::new(config.parse_utc_offset()?, TIMESTAMP_FORMAT);
+            let ansi = config.ansi && matches!(config.out_type, LogOutType::Console);
             let layer = fmt::layer()
-                .with_ansi(false)
+                .with_ansi(ansi)
                 .with_file(config.with_file)
                 .with_line_number(config.with_line_number)
                 .with_thread_ids(config.with_thread_ids)
                 .with_target(config.with_target)
+                .with_span_events(FmtSpan::from(&config.span_events))
                 .with_timer(timer)
                 .with_writer(non_blocking);
-            let layer = match config.level {
-                LogLevel::Trace => layer.with_filter(LevelFilter::TRACE),
-                LogLevel::Debug => layer.with_filter(LevelFilter::DEBUG),
-                LogLevel::Info => layer.with_filter(LevelFilter::INFO),
-                LogLevel::Warn => layer.with_filter(LevelFilter::WARN),
-                LogLevel::Error => layer.with_filter(LevelFilter::ERROR),
-            }
-            .boxed();
-            layers.push(layer);
+            let (filter, handle) = reload::Layer::new(config.env_filter()?);
+            layers.push(match config.format {
+                LogFormat::Full => layer.with_filter(filter).boxed(),
+                LogFormat::Json => layer.json().with_filter(filter).boxed(),
+            });
+            handles.push(Some(handle));
         }
-        tracing_subscriber::registry().with(layers).init();
+        tracing_subscriber::registry()
+            .with(layers)
+            .try_init()
+            .map_err(|_| NihilityCommonError::LogConfig)?;
+        RELOAD_HANDLES.get_or_init(|| handles);
         debug!("Log Subscriber Init Success");
+        Ok(LogHandle { _guards: guards })
+    }
+
+    /// 仅首次调用时按配置初始化，之后或已由`init`初始化时直接返回，便于测试中多处调用
+    ///
+    /// 句柄保留至进程退出，退出前未写出的日志可能丢失
+    pub fn init_once(configs: &[LogConfig]) -> WrapResult<()> {
+        let mut handle = INIT_ONCE_HANDLE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if handle.is_none() && RELOAD_HANDLES.get().is_none() {
+            *handle = Some(Log::init(configs)?);
+        }
+        Ok(())
+    }
+
+    /// 按`init`时的顺序更新各输出的日志级别与`filter`，`enable`改为`false`时停止输出
+    ///
+    /// 输出方式与格式需重启后生效
+    pub fn reload(configs: &[LogConfig]) -> WrapResult<()> {
+        let Some(handles) = RELOAD_HANDLES.get() else {
+            return Err(NihilityCommonError::LogConfig);
+        };
+        if configs.len() != handles.len() {
+            warn!("Log Config Count Changed, Restart Required");
+        }
+        for (config, handle) in configs.iter().zip(handles) {
+            match (handle, config.enable) {
+                (Some(handle), true) => handle.reload(config.env_filter()?),
+                (Some(handle), false) => handle.reload(EnvFilter::new("off")),
+                (None, true) => {
+                    warn!(
+                        "Log Output {:?} Enabled, Restart Required",
+                        &config.out_type
+                    );
+                    Ok(())
+                }
+                (None, false) => Ok(()),
+            }
+            .map_err(|_| NihilityCommonError::LogConfig)?;
+        }
         Ok(())
     }
 }
+
+impl From<&LogLevel> for LevelFilter {
+    fn from(value: &LogLevel) -> Self {
+        match value {
+            LogLevel::Trace => LevelFilter::TRACE,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Error => LevelFilter::ERROR,
+        }
+    }
+}
+
+impl From<&LogSpanEvents> for FmtSpan {
+    fn from(value: &LogSpanEvents) -> Self {
+        match value {
+            LogSpanEvents::None => FmtSpan::NONE,
+            LogSpanEvents::New => FmtSpan::NEW,
+            LogSpanEvents::Close => FmtSpan::CLOSE,
+            LogSpanEvents::Active => FmtSpan::ACTIVE,
+            LogSpanEvents::Full => FmtSpan::FULL,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+#[cfg(feature = "prometheus")]
+use std::net::SocketAddr;
+
+#[cfg(feature = "prometheus")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "prometheus")]
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(feature = "prometheus")]
+use tokio::select;
+#[cfg(feature = "prometheus")]
+use tokio_util::sync::CancellationToken;
+#[cfg(feature = "prometheus")]
+use tracing::{debug, info};
+
+#[cfg(feature = "prometheus")]
+use crate::error::WrapResult;
+
+/// 请求耗时直方图的分桶上限，单位为秒
+const DURATION_BUCKETS: [f64; 11] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MetricsSide {
+    Client,
+    Server,
+}
+
+impl MetricsSide {
+    fn label(&self) -> &'static str {
+        match self {
+            MetricsSide::Client => "client",
+            MetricsSide::Server => "server",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Grpc客户端与服务端的请求统计，按Prometheus文本格式输出
+#[derive(Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(MetricsSide, String, String, String), u64>>,
+    durations: Mutex<BTreeMap<(MetricsSide, String), Histogram>>,
+    verification_failures: Mutex<BTreeMap<(String, String), u64>>,
+    queue_depth: Mutex<BTreeMap<String, usize>>,
+}
+
+/// 进程内共用的统计
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+impl Metrics {
+    /// `code`为`ResponseCode`名称，请求未得到响应时为gRPC状态名称
+    pub fn record_request(&self, side: MetricsSide, rpc: &str, submodule_name: &str, code: &str) {
+        let key = (
+            side,
+            rpc.to_string(),
+            submodule_name.to_string(),
+            code.to_string(),
+        );
+        *self.requests.lock().unwrap().entry(key).or_default() += 1;
+    }
+
+    pub fn observe_duration(&self, side: MetricsSide, rpc: &str, elapsed: Duration) {
+        self.durations
+            .lock()
+            .unwrap()
+            .entry((side, rpc.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_verification_failure(&self, rpc: &str, submodule_name: &str) {
+        let key = (rpc.to_string(), submodule_name.to_string());
+        *self
+            .verification_failures
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default() += 1;
+    }
+
+    pub fn set_queue_depth(&self, queue: &str, depth: usize) {
+        self.queue_depth
+            .lock()
+            .unwrap()
+            .insert(queue.to_string(), depth);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        header(
+            &mut out,
+            "nihility_requests_total",
+            "Requests handled by grpc client and server",
+            "counter",
+        );
+        for ((side, rpc, submodule_name, code), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "nihility_requests_total{{side=\"{}\",rpc=\"{}\",submodule=\"{}\",code=\"{}\"}} {}",
+                side.label(),
+                escape(rpc),
+                escape(submodule_name),
+                escape(code),
+                count
+            );
+        }
+        header(
+            &mut out,
+            "nihility_request_duration_seconds",
+            "Request handling latency",
+            "histogram",
+        );
+        for ((side, rpc), histogram) in self.durations.lock().unwrap().iter() {
+            let labels = format!("side=\"{}\",rpc=\"{}\"", side.label(), escape(rpc));
+            for (count, bound) in histogram.buckets.iter().zip(DURATION_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "nihility_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "nihility_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "nihility_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "nihility_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+        header(
+            &mut out,
+            "nihility_verification_failures_total",
+            "Requests rejected by signature verification",
+            "counter",
+        );
+        for ((rpc, submodule_name), count) in self.verification_failures.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "nihility_verification_failures_total{{rpc=\"{}\",submodule=\"{}\"}} {}",
+                escape(rpc),
+                escape(submodule_name),
+                count
+            );
+        }
+        header(
+            &mut out,
+            "nihility_queue_depth",
+            "Entities waiting in delivery queues",
+            "gauge",
+        );
+        for (queue, depth) in self.queue_depth.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "nihility_queue_depth{{queue=\"{}\"}} {}",
+                escape(queue),
+                depth
+            );
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 在`bind_addr`上提供`/metrics`，取消后停止
+#[cfg(feature = "prometheus")]
+pub async fn serve_metrics(
+    bind_addr: SocketAddr,
+    cancellation_token: CancellationToken,
+) -> WrapResult<()> {
+    let listener = TcpListener::bind(bind_addr).await?;
+    info!("Metrics Server Bind At {}", listener.local_addr()?);
+    loop {
+        select! {
+            accepted = listener.accept() => {
+                let (stream, peer_addr) = accepted?;
+                debug!("Metrics Server Accept {}", peer_addr);
+                tokio::spawn(respond_metrics(stream));
+            }
+            _ = cancellation_token.cancelled() => break,
+        }
+    }
+    info!("Metrics Server Stop");
+    Ok(())
+}
+
+#[cfg(feature = "prometheus")]
+async fn respond_metrics(mut stream: TcpStream) {
+    let mut buf = [0u8; 1024];
+    let Ok(len) = stream.read(&mut buf).await else {
+        return;
+    };
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", metrics().render()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("Metrics Server Write Error: {:?}", e);
+    }
+}
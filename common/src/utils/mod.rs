@@ -1,2 +1,7 @@
+pub mod audit;
 pub mod auth;
+pub mod config;
 pub mod log;
+pub mod metrics;
+pub(crate) mod rolling;
+pub mod shutdown;
@@ -0,0 +1,94 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// 按文件大小滚动的日志文件，当前文件为`{prefix}.{suffix}`，
+/// 滚动后依次重命名为`{prefix}.1.{suffix}`、`{prefix}.2.{suffix}`，序号越大越旧
+pub(crate) struct SizeRollingAppender {
+    directory: PathBuf,
+    prefix: String,
+    suffix: String,
+    max_size: u64,
+    /// 包含当前文件在内保留的文件数，0为不限制
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl SizeRollingAppender {
+    pub(crate) fn new(
+        directory: &Path,
+        prefix: &str,
+        suffix: &str,
+        max_size: u64,
+        max_files: usize,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        let file = open(&directory.join(format!("{}.{}", prefix, suffix)))?;
+        Ok(SizeRollingAppender {
+            directory: directory.to_path_buf(),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            max_size,
+            max_files,
+            size: file.metadata()?.len(),
+            file,
+        })
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", self.prefix, self.suffix))
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}.{}", self.prefix, index, self.suffix))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut count = 0;
+        while self.rotated_path(count + 1).exists() {
+            count += 1;
+        }
+        let keep = match self.max_files {
+            0 => count + 1,
+            max_files => max_files - 1,
+        };
+        for index in (1..=count).rev() {
+            if index >= keep {
+                fs::remove_file(self.rotated_path(index))?;
+            } else {
+                fs::rename(self.rotated_path(index), self.rotated_path(index + 1))?;
+            }
+        }
+        if keep > 0 {
+            fs::rename(self.current_path(), self.rotated_path(1))?;
+        } else {
+            fs::remove_file(self.current_path())?;
+        }
+        self.file = open(&self.current_path())?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for SizeRollingAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,62 @@
+use tokio::spawn;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::communicat::NihilityClient;
+use crate::error::WrapResult;
+
+/// 收到SIGTERM或Ctrl-C后返回
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Listen Ctrl-C Error: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Listen SIGTERM Error: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl-C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// 收到终止信号后取消`cancellation_token`，可传入`GrpcServer::init`以平滑停止服务端
+pub fn cancel_on_shutdown_signal(cancellation_token: CancellationToken) -> JoinHandle<()> {
+    spawn(async move {
+        tokio::select! {
+            _ = shutdown_signal() => cancellation_token.cancel(),
+            _ = cancellation_token.cancelled() => {}
+        }
+    })
+}
+
+/// 取消`cancellation_token`后执行客户端的`shutdown`，与`cancel_on_shutdown_signal`共用令牌时收到终止信号即停止心跳并下线
+///
+/// `GrpcClient`可传入克隆，下线后其余克隆释放时不再重复下线
+pub fn shutdown_client_on_cancel<C>(
+    mut client: C,
+    cancellation_token: CancellationToken,
+) -> JoinHandle<WrapResult<()>>
+where
+    C: NihilityClient + Send + 'static,
+{
+    spawn(async move {
+        cancellation_token.cancelled().await;
+        client.shutdown().await
+    })
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use nihility_common::{
+    BindAddr, LogLevel, LogOutType, LogRotation, NihilityCommonError, NihilityConfig, ENV_PREFIX,
+};
+
+fn config_path(file_name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("nihility-config-{}", std::process::id()))
+        .join(file_name)
+}
+
+fn env(key: &str, value: &str) -> (String, String) {
+    (format!("{}{}", ENV_PREFIX, key), value.to_string())
+}
+
+fn invalid_field(result: nihility_common::WrapResult<()>) -> String {
+    match result {
+        Err(NihilityCommonError::Config(field, _)) => field,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_config_template() {
+    for file_name in ["nihility.toml", "nihility.yaml", "nihility.json"] {
+        let path = config_path(file_name);
+        NihilityConfig::write_template(&path).unwrap();
+        let config = NihilityConfig::from_file(&path).unwrap();
+        config.validate().unwrap();
+        let default = NihilityConfig::default();
+        assert_eq!(config.heartbeat_secs, default.heartbeat_secs);
+        assert_eq!(config.key.core_key_dir, default.key.core_key_dir);
+        assert_eq!(config.server.bind_port, default.server.bind_port);
+        assert_eq!(config.client.server_address, default.client.server_address);
+        assert_eq!(config.log.len(), 1);
+    }
+    assert!(NihilityConfig::write_template(config_path("nihility.ini")).is_err());
+}
+
+#[test]
+fn test_config_partial_file() {
+    let path = config_path("partial.yml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        "submodule_name: test\nserver:\n  bind_port: 6060\nlog:\n  - level: debug\n",
+    )
+    .unwrap();
+    let config = NihilityConfig::from_file(&path).unwrap();
+    assert_eq!(config.submodule_name, "test");
+    assert_eq!(config.server.bind_port, 6060);
+    assert_eq!(config.server.drain_timeout_secs, 10);
+    assert!(matches!(config.log[0].level, LogLevel::Debug));
+    assert!(config.log[0].enable);
+
+    let path = config_path("broken.toml");
+    fs::write(&path, "[server]\nbind_port = \"port\"\n").unwrap();
+    assert!(matches!(
+        NihilityConfig::from_file(&path),
+        Err(NihilityCommonError::Config(..))
+    ));
+}
+
+#[test]
+fn test_config_env_overrides() {
+    let mut config = NihilityConfig::default();
+    config
+        .apply_env_overrides(vec![
+            env("SUBMODULE_NAME", "123"),
+            env("SERVER__BIND_PORT", "7070"),
+            env("CLIENT__SESSION_MODE", "true"),
+            env("CLIENT__AUTHENTICATION_MODE", "Hmac"),
+            env("LOG__0__LEVEL", "warn"),
+            env("LOG__0__OUT_TYPE", r#"{"File":"logs"}"#),
+            (String::from("PATH"), String::from("/usr/bin")),
+        ])
+        .unwrap();
+    assert_eq!(config.submodule_name, "123");
+    assert_eq!(config.server.bind_port, 7070);
+    assert!(config.client.session_mode);
+    assert!(matches!(config.log[0].level, LogLevel::Warn));
+    assert!(matches!(&config.log[0].out_type, LogOutType::File(path) if path == "logs"));
+
+    let field = invalid_field(config.apply_env_overrides(vec![env("SERVER__PORT", "1")]));
+    assert_eq!(field, "NIHILITY_SERVER__PORT");
+    let field = invalid_field(config.apply_env_overrides(vec![env("HEARTBEAT_SECS", "soon")]));
+    assert_eq!(field, "NIHILITY_HEARTBEAT_SECS");
+    let field = invalid_field(config.apply_env_overrides(vec![env("SERVER__BIND_PORT", "70000")]));
+    assert_eq!(field, "NIHILITY_SERVER__BIND_PORT");
+    config
+        .apply_env_overrides(vec![env(
+            "SERVER__BIND_ADDRS",
+            r#"["0.0.0.0","::","unix:/run/nihility.sock"]"#,
+        )])
+        .unwrap();
+    assert_eq!(config.server.bind_addrs.len(), 3);
+    let field = invalid_field(
+        config.apply_env_overrides(vec![env("SERVER__BIND_ADDRS", r#"["localhost"]"#)]),
+    );
+    assert_eq!(field, "NIHILITY_SERVER__BIND_ADDRS");
+}
+
+#[test]
+fn test_config_validate() {
+    let config = NihilityConfig {
+        heartbeat_secs: 0,
+        ..Default::default()
+    };
+    assert_eq!(invalid_field(config.validate()), "heartbeat_secs");
+
+    let mut config = NihilityConfig::default();
+    config.server.bind_addrs.clear();
+    assert_eq!(invalid_field(config.validate()), "server.bind_addrs");
+
+    let mut config = NihilityConfig::default();
+    config.server.bind_addrs = vec![BindAddr::from_str("127.0.0.1").unwrap(); 2];
+    assert_eq!(invalid_field(config.validate()), "server.bind_addrs[1]");
+
+    let mut config = NihilityConfig::default();
+    config.client.server_address = String::from("unix:/run/nihility.sock");
+    config.validate().unwrap();
+
+    let mut config = NihilityConfig::default();
+    config.client.server_address = String::from("127.0.0.1:5050");
+    assert_eq!(invalid_field(config.validate()), "client.server_address");
+
+    let mut config = NihilityConfig::default();
+    config.log[0].out_type = LogOutType::File(String::new());
+    assert_eq!(invalid_field(config.validate()), "log[0].out_type");
+
+    let mut config = NihilityConfig::default();
+    config.log[0].utc_offset = String::from("local");
+    config.validate().unwrap();
+    config.log[0].utc_offset = String::from("-05:30");
+    config.validate().unwrap();
+    config.log[0].utc_offset = String::from("8");
+    assert_eq!(invalid_field(config.validate()), "log[0].utc_offset");
+
+    let mut config = NihilityConfig::default();
+    config.log[0].filter = String::from("nihility_common::communicat=debug,tonic=warn");
+    config.validate().unwrap();
+    config.log[0].filter = String::from("nihility_common=loud");
+    assert_eq!(invalid_field(config.validate()), "log[0].filter");
+
+    let mut config = NihilityConfig::default();
+    config.log[0].rotation = LogRotation::Size(0);
+    assert_eq!(invalid_field(config.validate()), "log[0].rotation");
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+use tracing::Level;
+
+use nihility_common::{ConfigWatcher, Log, LogConfig, RateLimiter, RsaAuthenticator};
+
+const CONFIG: &str = "
+heartbeat_secs: 10
+log:
+  - level: info
+";
+
+const RELOADED_CONFIG: &str = "
+heartbeat_secs: 10
+rate_limit:
+  - rpc: send_text_instruct
+    capacity: 1
+    refill_per_second: 0.001
+log:
+  - level: debug
+";
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_config_reload() {
+    let _log_handle = Log::init(&[LogConfig::default()]).unwrap();
+    let test_dir = std::env::temp_dir().join(format!("nihility-reload-{}", std::process::id()));
+    let key_dir = test_dir.join("auth");
+    let config_path = test_dir.join("nihility.yaml");
+    let authenticator = Arc::new(RsaAuthenticator::default());
+    authenticator.init_core(&key_dir).unwrap();
+    let rate_limiter = Arc::new(RateLimiter::new(Vec::new()));
+    fs::write(&config_path, CONFIG).unwrap();
+
+    let cancellation_token = CancellationToken::new();
+    let watcher = ConfigWatcher::new(&config_path)
+        .with_rate_limiter(rate_limiter.clone())
+        .with_authenticator(authenticator.clone())
+        .watch(cancellation_token.clone())
+        .unwrap();
+    assert!(!tracing::enabled!(Level::DEBUG));
+
+    fs::write(
+        key_dir.join("pinned_keys.json"),
+        r#"{"test": "fingerprint"}"#,
+    )
+    .unwrap();
+    fs::write(&config_path, RELOADED_CONFIG).unwrap();
+    wait_until(|| !rate_limiter.rules().is_empty()).await;
+    assert!(tracing::enabled!(Level::DEBUG));
+    assert_eq!(authenticator.pinned_keys()["test"], "fingerprint");
+    assert!(rate_limiter.check("test", "send_text_instruct"));
+    assert!(!rate_limiter.check("test", "send_text_instruct"));
+
+    // 校验失败的配置不生效
+    fs::write(&config_path, "heartbeat_secs: 0\nlog:\n  - level: error\n").unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(tracing::enabled!(Level::DEBUG));
+    assert_eq!(rate_limiter.rules().len(), 1);
+
+    // 加载失败的内容在之后的轮询中重试，环境变量修正后无需改写文件即可生效
+    std::env::set_var("NIHILITY_HEARTBEAT_SECS", "10");
+    wait_until(|| !tracing::enabled!(Level::INFO)).await;
+    assert!(rate_limiter.rules().is_empty());
+    std::env::remove_var("NIHILITY_HEARTBEAT_SECS");
+
+    cancellation_token.cancel();
+    watcher.await.unwrap();
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+use tokio::spawn;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use nihility_common::{
+    discover, key_fingerprint, set_submodule_name, BindAddr, ClientType, ConnParams,
+    ConnectionType, DiscoveryInfo, GrpcClient, GrpcServer, GrpcServerConfig, InstructEntity,
+    NihilityClient, NihilityCommonError, NihilityServer, ResponseCode, RsaAuthenticator,
+    ServerHandle, SubmoduleInfo, SubmoduleRegistry,
+};
+
+fn test_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("nihility-discovery-{}", std::process::id()))
+}
+
+fn start_core(
+    key_dir: &Path,
+    discovery_path: &Path,
+    authenticator: Arc<RsaAuthenticator>,
+    registry: Arc<SubmoduleRegistry>,
+) -> ServerHandle {
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        ..Default::default()
+    };
+    let mut server = GrpcServer::init(server_config, CancellationToken::new());
+    server.set_authenticator(authenticator).unwrap();
+    server.set_submodule_registry(registry).unwrap();
+    let (module_tx, mut module_rx) = mpsc::unbounded_channel();
+    let (instruct_tx, mut instruct_rx) = mpsc::unbounded_channel();
+    server.set_submodule_operate_sender(module_tx).unwrap();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    spawn(async move { while module_rx.recv().await.is_some() {} });
+    spawn(async move { while instruct_rx.recv().await.is_some() {} });
+    server
+        .enable_discovery(key_dir.to_str().unwrap(), discovery_path)
+        .unwrap();
+    server.start().unwrap()
+}
+
+fn submodule_info() -> SubmoduleInfo {
+    SubmoduleInfo {
+        default_instruct: vec![String::from("discovery_instruct")],
+        conn_params: ConnParams {
+            connection_type: ConnectionType::GrpcType,
+            client_type: ClientType::NotReceiveType,
+            conn_config: HashMap::new(),
+        },
+    }
+}
+
+async fn send_instruct(client: &GrpcClient) {
+    let instruct = InstructEntity::new_text(String::from("test discovery instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_discovery() {
+    set_submodule_name("discovery");
+    let key_dir = test_dir().join("auth");
+    let discovery_path = test_dir().join("core.json");
+    let core_authenticator = Arc::new(RsaAuthenticator::default());
+    core_authenticator.init_core(&key_dir).unwrap();
+    let core_public_key_path = key_dir.join("id_rsa.pub");
+    let core_public_key = RsaPublicKey::read_public_key_pem_file(&core_public_key_path).unwrap();
+    assert!(matches!(
+        discover(&discovery_path, &core_public_key_path),
+        Err(NihilityCommonError::FileNotExist(_))
+    ));
+
+    let mut handle = start_core(
+        &key_dir,
+        &discovery_path,
+        core_authenticator.clone(),
+        Arc::default(),
+    );
+    let local_addr = handle.ready().await.unwrap().unwrap();
+    let discovery = discover(&discovery_path, &core_public_key_path).unwrap();
+    let info = discovery.current();
+    assert_eq!(info.pid, std::process::id());
+    assert_eq!(
+        info.core_key_fingerprint,
+        key_fingerprint(&core_public_key).unwrap()
+    );
+    info.verify_core_key(&core_public_key_path).unwrap();
+    let client_config = info.client_config().unwrap();
+    assert_eq!(
+        client_config.server_address,
+        format!("http://{}", local_addr)
+    );
+
+    let mut client = GrpcClient::init(client_config);
+    client
+        .set_authenticator(Arc::new(
+            RsaAuthenticator::with_core_public_key_path(core_public_key_path.to_str().unwrap())
+                .with_key_dir(test_dir().join("submodule").display().to_string()),
+        ))
+        .unwrap();
+    client.set_submodule_info(submodule_info()).unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    client.connection_instruct_server().await.unwrap();
+    let resp = client.register().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let follower = client.follow_discovery(discovery);
+
+    // 指纹不符的发现文件不会使子模块改连
+    let forged = DiscoveryInfo {
+        connection_params: HashMap::new(),
+        core_key_fingerprint: String::from("forged"),
+        ..info
+    };
+    std::fs::write(&discovery_path, serde_json::to_vec(&forged).unwrap()).unwrap();
+    assert!(matches!(
+        discover(&discovery_path, &core_public_key_path),
+        Err(NihilityCommonError::CoreKeyNotMatch)
+    ));
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    send_instruct(&client).await;
+    handle.shutdown().await.unwrap();
+    assert!(!discovery_path.exists());
+
+    // 核心模块在新端口重启后子模块按发现文件重新连接并重新注册
+    let registry = Arc::new(SubmoduleRegistry::default());
+    let mut handle = start_core(
+        &key_dir,
+        &discovery_path,
+        core_authenticator,
+        registry.clone(),
+    );
+    handle.ready().await.unwrap().unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while registry.list().is_empty() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+    send_instruct(&client).await;
+    follower.abort();
+    handle.shutdown().await.unwrap();
+    assert!(!discovery_path.exists());
+}
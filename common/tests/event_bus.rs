@@ -0,0 +1,51 @@
+use nihility_common::{
+    set_default_receiver_submodule, set_submodule_name, EventBus, InstructEntity, InstructType,
+};
+
+fn instruct(text: &str, instruct_type: InstructType) -> InstructEntity {
+    let mut entity = InstructEntity::new_text(text.to_string());
+    entity.info.instruct_type = instruct_type;
+    entity
+}
+
+#[tokio::test]
+async fn test_event_bus() {
+    set_submodule_name("event_bus");
+    set_default_receiver_submodule("event_bus");
+    let bus = EventBus::new(4);
+    assert!(bus
+        .publish(instruct("nobody", InstructType::DefaultType))
+        .is_ok());
+
+    let mut all = bus.subscribe();
+    let mut special = bus.subscribe_kind(InstructType::SpecialType);
+    let mut slow = bus.subscribe();
+    assert_eq!(bus.subscriber_count(), 3);
+
+    bus.publish(instruct("first", InstructType::DefaultType))
+        .unwrap();
+    bus.publish(instruct("second", InstructType::SpecialType))
+        .unwrap();
+    assert!(all.recv().await.is_some());
+    assert!(all.recv().await.is_some());
+    assert_eq!(
+        special.recv().await.unwrap().info.instruct_type,
+        InstructType::SpecialType
+    );
+    assert!(special.try_recv().is_none());
+
+    for _ in 0..4 {
+        bus.publish(instruct("flood", InstructType::DefaultType))
+            .unwrap();
+    }
+    let mut received = 0;
+    while slow.try_recv().is_some() {
+        received += 1;
+    }
+    assert_eq!(received, 4);
+    assert_eq!(slow.missed(), 2);
+
+    drop(bus);
+    while all.try_recv().is_some() {}
+    assert!(all.recv().await.is_none());
+}
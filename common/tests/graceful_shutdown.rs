@@ -0,0 +1,117 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::spawn;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::Channel;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+
+use nihility_common::{
+    BindAddr, GrpcClient, GrpcClientConfig, GrpcServer, GrpcServerConfig, InstructEntity,
+    Middleware, MiddlewareContext, MiddlewareEntity, NihilityClient, NihilityServer,
+    NoopAuthenticator, ResponseCode, WrapResult,
+};
+
+/// 模拟处理缓慢的请求
+struct SlowInstruct;
+
+impl Middleware for SlowInstruct {
+    fn before(&self, _context: &MiddlewareContext, _entity: MiddlewareEntity) -> WrapResult<()> {
+        std::thread::sleep(Duration::from_millis(500));
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_graceful_shutdown() {
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        drain_timeout_secs: 1,
+        ..Default::default()
+    };
+    let cancellation_token = CancellationToken::new();
+    let mut server = GrpcServer::init(server_config, cancellation_token.clone());
+    let (instruct_tx, _instruct_rx) = mpsc::unbounded_channel();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    let mut handle = server.start().unwrap();
+    let local_addr = handle.ready().await.unwrap().unwrap();
+
+    let channel = Channel::from_shared(format!("http://{}", local_addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut health_client = HealthClient::new(channel);
+    let mut watch = health_client
+        .watch(HealthCheckRequest {
+            service: String::from("instruct.Instruct"),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(watch.message().await.unwrap().is_some());
+
+    cancellation_token.cancel();
+    tokio::time::timeout(Duration::from_secs(5), handle.join())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(HealthClient::new(
+        Channel::from_shared(format!("http://{}", local_addr))
+            .unwrap()
+            .connect_lazy()
+    )
+    .check(HealthCheckRequest::default())
+    .await
+    .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_drain_in_flight_request() {
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        drain_timeout_secs: 5,
+        ..Default::default()
+    };
+    let cancellation_token = CancellationToken::new();
+    let mut server = GrpcServer::init(server_config, cancellation_token.clone());
+    server
+        .set_authenticator(Arc::new(NoopAuthenticator))
+        .unwrap();
+    server.add_middleware(Arc::new(SlowInstruct)).unwrap();
+    let (instruct_tx, _instruct_rx) = mpsc::unbounded_channel();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    let mut handle = server.start().unwrap();
+    let local_addr = handle.ready().await.unwrap().unwrap();
+
+    let mut client = GrpcClient::init(GrpcClientConfig {
+        server_address: format!("http://{}", local_addr),
+        ..Default::default()
+    });
+    client
+        .set_authenticator(Arc::new(NoopAuthenticator))
+        .unwrap();
+    client.connection_instruct_server().await.unwrap();
+    let in_flight = spawn(async move {
+        client
+            .text_instruct(InstructEntity::new_text(String::from("slow")))
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // 停止后进行中的请求在排空期间完成
+    let started = Instant::now();
+    cancellation_token.cancel();
+    let resp = in_flight.await.unwrap().unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    tokio::time::timeout(Duration::from_secs(5), handle.join())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -1,111 +1,1097 @@
 use std::collections::HashMap;
-use std::sync::OnceLock;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+use tokio::net::TcpListener;
+use tokio::spawn;
 use tokio::sync::mpsc;
-use tracing::info;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_util::sync::CancellationToken;
+use tonic::body::BoxBody;
+use tonic::codegen::{http, Body as HttpBody};
+use tonic::server::NamedService;
+use tonic::transport::{Body, Channel, Server};
+use tonic::Status;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tower::{Service, ServiceExt};
 
 use nihility_common::{
-    set_core_public_key_path, set_default_receiver_submodule, set_submodule_name, ClientType,
-    ConnParams, ConnectionType, GrpcClient, GrpcClientConfig, InstructEntity, Log, LogConfig,
-    ManipulateEntity, ModuleOperate, NihilityClient, OperateType, SubmoduleInfo,
+    get_auth_id, metrics, set_auth_id, set_default_receiver_submodule, set_submodule_name,
+    shutdown_client_on_cancel, AuthenticationMode, Authenticator, BindAddr, ClientType, ConnParams,
+    ConnectionType, EncryptPayload, EventBus, GrpcAdminClient, GrpcClient, GrpcClientConfig,
+    GrpcServer, GrpcServerConfig, Identity, InstructData, InstructEntity, InstructMatcher,
+    ManipulateData, ManipulateEntity, Middleware, MiddlewareContext, MiddlewareEntity,
+    ModuleOperate, NihilityClient, NihilityCommonError, NihilityServer, OperateType, PushEntity,
+    RateLimitRule, RateLimiter, ResponseCode, ResponseEntity, RsaAuthenticator, ServerHandle,
+    SubmoduleInfo, SubmoduleRegistry, SubscriberHub, WrapResult,
 };
 
-static _CLIENT: OnceLock<Box<dyn NihilityClient + Send + Sync>> = OnceLock::new();
+const KEY_DIR: &str = "./auth/grpc_client";
 
-#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
-async fn test_client() {
-    Log::init(&vec![LogConfig::default()]).unwrap();
-    tokio::time::sleep(Duration::from_secs(3)).await;
-    set_submodule_name("test");
-    set_core_public_key_path("./auth/id_rsa.pub");
-    set_default_receiver_submodule("test");
-    test_grpc_submodule_operate_client().await;
-    test_grpc_instruct_client().await;
-    test_grpc_manipulate_client().await;
-    tokio::time::sleep(Duration::from_secs(15)).await;
+#[derive(Default)]
+struct CountResponse {
+    count: AtomicUsize,
 }
 
-async fn test_grpc_submodule_operate_client() {
-    info!("Sleep, Wait Server Start");
-    let config = GrpcClientConfig::default();
-    let mut client = GrpcClient::init(config);
-    client
-        .set_submodule_info(SubmoduleInfo {
-            default_instruct: vec![String::from("test_instruct")],
-            conn_params: ConnParams {
-                connection_type: ConnectionType::GrpcType,
-                client_type: ClientType::NotReceiveType,
-                conn_config: HashMap::new(),
+impl Middleware for CountResponse {
+    fn after(&self, context: &MiddlewareContext, _resp: &ResponseEntity) {
+        if context.rpc == "send_text_instruct" {
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+struct BlockInstruct;
+
+impl Middleware for BlockInstruct {
+    fn before(&self, _context: &MiddlewareContext, entity: MiddlewareEntity) -> WrapResult<()> {
+        if let MiddlewareEntity::Instruct(instruct) = entity {
+            let InstructData::Text(text) = &instruct.instruct;
+            if text == "test send blocked instruct" {
+                return Err(NihilityCommonError::Rejected(text.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+struct RejectInstruct;
+
+impl Middleware for RejectInstruct {
+    fn before(&self, _context: &MiddlewareContext, entity: MiddlewareEntity) -> WrapResult<()> {
+        match entity {
+            MiddlewareEntity::Instruct(instruct) => match &instruct.instruct {
+                InstructData::Text(text) if text == "test send rejected instruct" => {
+                    Err(NihilityCommonError::Rejected(text.to_string()))
+                }
+                _ => Ok(()),
             },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 下线与更新时改写为其他子模块的名称
+struct RenameOperate;
+
+impl Middleware for RenameOperate {
+    fn before(&self, _context: &MiddlewareContext, entity: MiddlewareEntity) -> WrapResult<()> {
+        if let MiddlewareEntity::ModuleOperate(operate) = entity {
+            if matches!(
+                operate.operate_type,
+                OperateType::Update | OperateType::Offline
+            ) {
+                operate.name = String::from("other");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 复制已登记子模块的公钥注册，但只持有自己的私钥
+struct CopiedKeyAuthenticator {
+    inner: RsaAuthenticator,
+    public_key: String,
+}
+
+impl Authenticator for CopiedKeyAuthenticator {
+    fn sign(&self, auth_id: &str, mode: &AuthenticationMode, data: &[u8]) -> WrapResult<Vec<u8>> {
+        self.inner.sign(auth_id, mode, data)
+    }
+
+    fn identify(&self, sign: &[u8]) -> Option<Identity> {
+        self.inner.identify(sign)
+    }
+
+    fn verify(&self, identity: &Identity, data: &[u8]) -> bool {
+        self.inner.verify(identity, data)
+    }
+
+    fn prepare_register(
+        &self,
+        submodule_info: &mut SubmoduleInfo,
+        mode: &AuthenticationMode,
+    ) -> WrapResult<()> {
+        self.inner.prepare_register(submodule_info, mode)?;
+        submodule_info
+            .conn_params
+            .conn_config
+            .insert(String::from("public_key"), self.public_key.to_string());
+        Ok(())
+    }
+
+    fn register_success(&self, auth_id: &str, mode: &AuthenticationMode) -> WrapResult<()> {
+        self.inner.register_success(auth_id, mode)
+    }
+
+    fn register(&self, module_operate: &mut ModuleOperate) -> WrapResult<String> {
+        self.inner.register(module_operate)
+    }
+
+    fn revoke(&self, auth_id: &str) -> WrapResult<()> {
+        self.inner.revoke(auth_id)
+    }
+
+    fn encrypt_payload(&self, auth_id: &str, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        self.inner.encrypt_payload(auth_id, message)
+    }
+
+    fn decrypt_payload(&self, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        self.inner.decrypt_payload(message)
+    }
+}
+
+/// 以任意auth_id发送无法通过校验的签名
+struct ForgedSignAuthenticator;
+
+impl Authenticator for ForgedSignAuthenticator {
+    fn sign(&self, auth_id: &str, _mode: &AuthenticationMode, _data: &[u8]) -> WrapResult<Vec<u8>> {
+        Ok(format!("{}|forged", auth_id).into_bytes())
+    }
+
+    fn identify(&self, _sign: &[u8]) -> Option<Identity> {
+        None
+    }
+
+    fn verify(&self, _identity: &Identity, _data: &[u8]) -> bool {
+        false
+    }
+
+    fn prepare_register(
+        &self,
+        _submodule_info: &mut SubmoduleInfo,
+        _mode: &AuthenticationMode,
+    ) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn register_success(&self, _auth_id: &str, _mode: &AuthenticationMode) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn register(&self, _module_operate: &mut ModuleOperate) -> WrapResult<String> {
+        Err(NihilityCommonError::AuthId)
+    }
+
+    fn revoke(&self, _auth_id: &str) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn encrypt_payload(&self, _auth_id: &str, _message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        Ok(())
+    }
+
+    fn decrypt_payload(&self, _message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        Ok(())
+    }
+}
+
+/// 截获运维请求后转发，`replay_path`为空时原样发送两次，否则改写为该RPC后发送
+#[derive(Clone)]
+struct ReplayProxy {
+    channel: Channel,
+    replay_path: Option<&'static str>,
+}
+
+impl NamedService for ReplayProxy {
+    const NAME: &'static str = "admin.Admin";
+}
+
+impl Service<http::Request<Body>> for ReplayProxy {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let proxy = self.clone();
+        Box::pin(async move { Ok(proxy.forward(request).await.unwrap_or_else(Status::to_http)) })
+    }
+}
+
+impl ReplayProxy {
+    async fn forward(
+        mut self,
+        request: http::Request<Body>,
+    ) -> Result<http::Response<BoxBody>, Status> {
+        let (parts, mut body) = request.into_parts();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk.map_err(|e| Status::internal(e.to_string()))?);
+        }
+        let (path, times) = match self.replay_path {
+            Some(path) => (path, 1),
+            None => (parts.uri.path(), 2),
+        };
+        let mut response = None;
+        for _ in 0..times {
+            let mut request = http::Request::new(boxed(Body::from(data.clone())));
+            *request.method_mut() = parts.method.clone();
+            *request.uri_mut() = http::Uri::try_from(path).unwrap();
+            *request.headers_mut() = parts.headers.clone();
+            let channel = self
+                .channel
+                .ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            response = Some(
+                channel
+                    .call(request)
+                    .await
+                    .map_err(|e| Status::unavailable(e.to_string()))?,
+            );
+        }
+        Ok(response.unwrap().map(boxed))
+    }
+}
+
+fn boxed(body: Body) -> BoxBody {
+    body.map_err(|e| Status::internal(e.to_string()))
+        .boxed_unsync()
+}
+
+/// 在随机端口启动转发至核心模块运维服务的代理，返回代理地址
+async fn start_replay_proxy(core: &Core, replay_path: Option<&'static str>) -> String {
+    let proxy = ReplayProxy {
+        channel: Channel::from_shared(core.server_address.to_string())
+            .unwrap()
+            .connect()
+            .await
+            .unwrap(),
+        replay_path,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let local_addr = listener.local_addr().unwrap();
+    spawn(
+        Server::builder()
+            .add_service(proxy)
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    format!("http://{}", local_addr)
+}
+
+/// 同一进程内各测试的核心模块共用密钥，子模块在任一核心模块注册后均可校验
+fn core_authenticator() -> Arc<RsaAuthenticator> {
+    static CORE_AUTHENTICATOR: OnceLock<Arc<RsaAuthenticator>> = OnceLock::new();
+    CORE_AUTHENTICATOR
+        .get_or_init(|| {
+            let authenticator = RsaAuthenticator::default();
+            authenticator.init_core(KEY_DIR).unwrap();
+            Arc::new(authenticator)
         })
+        .clone()
+}
+
+/// 记录收到的推送负载是否已加密，其余均交由子模块认证
+struct RecordDecryptAuthenticator {
+    inner: Arc<RsaAuthenticator>,
+    encrypted: AtomicBool,
+}
+
+impl Authenticator for RecordDecryptAuthenticator {
+    fn sign(&self, auth_id: &str, mode: &AuthenticationMode, data: &[u8]) -> WrapResult<Vec<u8>> {
+        self.inner.sign(auth_id, mode, data)
+    }
+
+    fn identify(&self, sign: &[u8]) -> Option<Identity> {
+        self.inner.identify(sign)
+    }
+
+    fn verify(&self, identity: &Identity, data: &[u8]) -> bool {
+        self.inner.verify(identity, data)
+    }
+
+    fn prepare_register(
+        &self,
+        submodule_info: &mut SubmoduleInfo,
+        mode: &AuthenticationMode,
+    ) -> WrapResult<()> {
+        self.inner.prepare_register(submodule_info, mode)
+    }
+
+    fn register_success(&self, auth_id: &str, mode: &AuthenticationMode) -> WrapResult<()> {
+        self.inner.register_success(auth_id, mode)
+    }
+
+    fn register(&self, module_operate: &mut ModuleOperate) -> WrapResult<String> {
+        self.inner.register(module_operate)
+    }
+
+    fn revoke(&self, auth_id: &str) -> WrapResult<()> {
+        self.inner.revoke(auth_id)
+    }
+
+    fn encrypt_payload(&self, auth_id: &str, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        self.inner.encrypt_payload(auth_id, message)
+    }
+
+    fn decrypt_payload(&self, message: &mut dyn EncryptPayload) -> WrapResult<()> {
+        if !message.get_encrypted_payload().is_empty() && message.get_payload().is_empty() {
+            self.encrypted.store(true, Ordering::SeqCst);
+        }
+        self.inner.decrypt_payload(message)
+    }
+}
+
+fn submodule_authenticator() -> Arc<RsaAuthenticator> {
+    static SUBMODULE_AUTHENTICATOR: OnceLock<Arc<RsaAuthenticator>> = OnceLock::new();
+    SUBMODULE_AUTHENTICATOR
+        .get_or_init(|| Arc::new(new_submodule_authenticator("submodule")))
+        .clone()
+}
+
+fn new_submodule_authenticator(key_dir: &str) -> RsaAuthenticator {
+    core_authenticator();
+    RsaAuthenticator::with_core_public_key_path(&format!("{}/id_rsa.pub", KEY_DIR))
+        .with_key_dir(format!("{}/{}", KEY_DIR, key_dir))
+}
+
+fn submodule_info() -> SubmoduleInfo {
+    SubmoduleInfo {
+        default_instruct: vec![String::from("test_instruct")],
+        conn_params: ConnParams {
+            connection_type: ConnectionType::GrpcType,
+            client_type: ClientType::NotReceiveType,
+            conn_config: HashMap::new(),
+        },
+    }
+}
+
+struct Core {
+    _handle: ServerHandle,
+    server_address: String,
+    registry: Arc<SubmoduleRegistry>,
+    instruct_matcher: Arc<InstructMatcher>,
+    subscriber_hub: Arc<SubscriberHub>,
+}
+
+/// 在随机端口启动核心模块，`setup`在启动前调整服务端配置
+async fn start_core(setup: impl FnOnce(&mut GrpcServer)) -> Core {
+    start_core_with(GrpcServerConfig::default(), setup).await
+}
+
+async fn start_core_with(
+    server_config: GrpcServerConfig,
+    setup: impl FnOnce(&mut GrpcServer),
+) -> Core {
+    set_submodule_name("test");
+    set_default_receiver_submodule("test");
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        ..server_config
+    };
+    let mut server = GrpcServer::init(server_config, CancellationToken::new());
+    server.set_authenticator(core_authenticator()).unwrap();
+    let (module_tx, mut module_rx) = mpsc::unbounded_channel();
+    let (instruct_tx, mut instruct_rx) = mpsc::unbounded_channel();
+    let (manipulate_tx, mut manipulate_rx) = mpsc::unbounded_channel();
+    server.set_submodule_operate_sender(module_tx).unwrap();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    server.set_manipulate_sender(manipulate_tx).unwrap();
+    spawn(async move { while module_rx.recv().await.is_some() {} });
+    spawn(async move { while instruct_rx.recv().await.is_some() {} });
+    spawn(async move { while manipulate_rx.recv().await.is_some() {} });
+    let instruct_matcher = Arc::new(InstructMatcher::new());
+    server
+        .set_instruct_matcher(instruct_matcher.clone())
         .unwrap();
-    client.connection_submodule_operate_server().await.unwrap();
-    info!("Connection Success!");
-    client.register().await.unwrap();
-    info!("register finish");
-    client
-        .set_submodule_info(SubmoduleInfo {
-            default_instruct: vec![String::from("test_instruct")],
-            conn_params: ConnParams {
-                connection_type: ConnectionType::GrpcType,
-                client_type: ClientType::NotReceiveType,
-                conn_config: HashMap::new(),
-            },
+    let subscriber_hub = Arc::new(SubscriberHub::default());
+    server.set_subscriber_hub(subscriber_hub.clone()).unwrap();
+    let registry = Arc::new(SubmoduleRegistry::default());
+    server.set_submodule_registry(registry.clone()).unwrap();
+    setup(&mut server);
+    let mut handle = server.start().unwrap();
+    let local_addr = handle.ready().await.unwrap().unwrap();
+    Core {
+        _handle: handle,
+        server_address: format!("http://{}", local_addr),
+        registry,
+        instruct_matcher,
+        subscriber_hub,
+    }
+}
+
+impl Core {
+    fn client(&self, config: GrpcClientConfig) -> GrpcClient {
+        let mut client = GrpcClient::init(GrpcClientConfig {
+            server_address: self.server_address.to_string(),
+            ..config
+        });
+        client.set_authenticator(submodule_authenticator()).unwrap();
+        client.set_submodule_info(submodule_info()).unwrap();
+        client
+    }
+
+    async fn registered_client(&self, config: GrpcClientConfig) -> GrpcClient {
+        let mut client = self.client(config);
+        client.connection_submodule_operate_server().await.unwrap();
+        let resp = client.register().await.unwrap();
+        assert!(matches!(resp.code(), ResponseCode::Success));
+        client
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_health_client() {
+    let core = start_core(|_| {}).await;
+    let channel = Channel::from_shared(core.server_address.to_string())
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut health_client = HealthClient::new(channel);
+    for service in ["", "instruct.Instruct", "session.Session"] {
+        let resp = health_client
+            .check(HealthCheckRequest {
+                service: service.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(resp.status(), ServingStatus::Serving);
+    }
+    let status = health_client
+        .check(HealthCheckRequest {
+            service: String::from("unknown.Unknown"),
         })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_submodule_operate_client() {
+    let core = start_core(|_| {}).await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
+    client.set_submodule_info(submodule_info()).unwrap();
+    let resp = client.update().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    client.connection_subscribe_server().await.unwrap();
+    let mut push_rx = client.subscribe().await.unwrap();
+    while !core.subscriber_hub.is_subscribed("test") {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let mut manipulate = ManipulateEntity::new_text(String::from("pushed manipulate"));
+    manipulate.info.use_module_name = String::from("test");
+    core.subscriber_hub.push(manipulate).unwrap();
+    let pushed = tokio::time::timeout(Duration::from_secs(10), push_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let PushEntity::Manipulate(manipulate) = pushed else {
+        panic!("Unexpected Push Entity: {:?}", pushed);
+    };
+    assert!(
+        matches!(manipulate.manipulate, ManipulateData::Text(text) if text == "pushed manipulate")
+    );
+    let resp = client.heartbeat().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let resp = client.offline().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_register_without_event_bus_subscriber() {
+    let core = start_core(|server| {
+        server
+            .set_submodule_operate_deliver(Arc::new(EventBus::default()))
+            .unwrap();
+    })
+    .await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
+    let resp = client.offline().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stale_offline_keeps_subscription() {
+    let core = start_core(|_| {}).await;
+    let mut stale_client = core.registered_client(GrpcClientConfig::default()).await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
+    client.connection_subscribe_server().await.unwrap();
+    let mut push_rx = client.subscribe().await.unwrap();
+    while !core.subscriber_hub.is_subscribed("test") {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    // 之前注册的auth_id下线不关闭同名子模块重新注册后的订阅流
+    stale_client.offline().await.unwrap();
+    assert!(core.subscriber_hub.is_subscribed("test"));
+    let mut manipulate = ManipulateEntity::new_text(String::from("pushed manipulate"));
+    manipulate.info.use_module_name = String::from("test");
+    core.subscriber_hub.push(manipulate).unwrap();
+    tokio::time::timeout(Duration::from_secs(10), push_rx.recv())
+        .await
+        .unwrap()
         .unwrap();
-    client.update().await.unwrap();
-    info!("update finish");
-    let mut operate = ModuleOperate::default();
-    operate.name = String::from("test");
-    operate.operate_type = OperateType::Heartbeat;
-    client.heartbeat().await.unwrap();
-    info!("heartbeat finish");
     client.offline().await.unwrap();
-    info!("offline finish");
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while core.subscriber_hub.is_subscribed("test") {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
 }
 
-async fn test_grpc_instruct_client() {
-    info!("Sleep, Wait Server Start");
-    let config = GrpcClientConfig::default();
-    let mut client = GrpcClient::init(config);
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_operate_other_submodule_rejected() {
+    let core = start_core(|_| {}).await;
+    core.instruct_matcher
+        .insert("other", &[String::from("other_instruct")]);
+    let mut client = core.client(GrpcClientConfig::default());
+    client.add_middleware(Arc::new(RenameOperate)).unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    client.register().await.unwrap();
+    assert!(client.update().await.is_err());
+    assert!(client.offline().await.is_err());
+    let instruct = InstructEntity::new_text(String::from("other_instruct"));
+    assert_eq!(core.instruct_matcher.match_instruct(&instruct).len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_instruct_client() {
+    let core = start_core(|_| {}).await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
     client.connection_instruct_server().await.unwrap();
-    info!("Connection Success!");
     let instruct = InstructEntity::new_text(String::from("test send instruct"));
-    client.text_instruct(instruct).await.unwrap();
-    info!("text_instruct finish");
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
     let (tx, rx) = mpsc::channel(1);
     let instruct = InstructEntity::new_text(String::from("test send instruct"));
     tx.send(instruct).await.unwrap();
-    client.multiple_text_instruct(rx).await.unwrap();
-    info!("multiple_text_instruct finish");
+    let mut resp_rx = client.multiple_text_instruct(rx).await.unwrap();
+    let resp = resp_rx.recv().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    assert!(metrics().render().contains(
+        "nihility_requests_total{side=\"client\",rpc=\"send_text_instruct\",submodule=\"test\",code=\"Success\"}"
+    ));
+    assert!(metrics().render().contains(
+        "nihility_requests_total{side=\"server\",rpc=\"send_text_instruct\",submodule=\"test\",code=\"Success\"}"
+    ));
 }
 
-async fn test_grpc_manipulate_client() {
-    info!("Sleep, Wait Server Start");
-    let config = GrpcClientConfig::default();
-    let mut client = GrpcClient::init(config);
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_manipulate_client() {
+    let core = start_core(|_| {}).await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
     client.connection_manipulate_server().await.unwrap();
-    info!("Connection Success!");
     let manipulate = ManipulateEntity::new_simple();
-    client.simple_manipulate(manipulate).await.unwrap();
-    info!("simple_manipulate finish");
+    let resp = client.simple_manipulate(manipulate).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
     let manipulate = ManipulateEntity::new_text(String::from("text_display_manipulate"));
-    client.text_display_manipulate(manipulate).await.unwrap();
-    info!("text_display_manipulate finish");
+    let resp = client.text_display_manipulate(manipulate).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
     let (tx, rx) = mpsc::channel(1);
     let manipulate = ManipulateEntity::new_text(String::from("multiple_text_display_manipulate"));
     tx.send(manipulate).await.unwrap();
-    client.multiple_text_display_manipulate(rx).await.unwrap();
-    info!("multiple_text_display_manipulate finish");
+    let mut resp_rx = client.multiple_text_display_manipulate(rx).await.unwrap();
+    let resp = resp_rx.recv().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
     let manipulate = ManipulateEntity::new_connection_params(ConnParams {
         connection_type: ConnectionType::GrpcType,
         client_type: ClientType::NotReceiveType,
         conn_config: Default::default(),
     });
-    client
+    let resp = client
         .direct_connection_manipulate(manipulate)
         .await
         .unwrap();
-    info!("direct_connection_manipulate finish");
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_payload_encryption_client() {
+    let core = start_core(|_| {}).await;
+    let config = GrpcClientConfig {
+        payload_encryption: true,
+        ..Default::default()
+    };
+    let mut client = core.registered_client(config).await;
+    client.connection_instruct_server().await.unwrap();
+    client.connection_manipulate_server().await.unwrap();
+    let instruct = InstructEntity::new_text(String::from("test send encrypted instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let manipulate = ManipulateEntity::new_text(String::from("encrypted text_display_manipulate"));
+    let resp = client.text_display_manipulate(manipulate).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_push_payload_encryption() {
+    let server_config = GrpcServerConfig {
+        payload_encryption: true,
+        ..Default::default()
+    };
+    let core = start_core_with(server_config, |_| {}).await;
+    for session_mode in [false, true] {
+        let config = GrpcClientConfig {
+            session_mode,
+            ..Default::default()
+        };
+        let mut client = core.registered_client(config).await;
+        let authenticator = Arc::new(RecordDecryptAuthenticator {
+            inner: submodule_authenticator(),
+            encrypted: AtomicBool::new(false),
+        });
+        client.set_authenticator(authenticator.clone()).unwrap();
+        client.connection_subscribe_server().await.unwrap();
+        let mut push_rx = client.subscribe().await.unwrap();
+        while !core.subscriber_hub.is_subscribed("test") {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let mut manipulate = ManipulateEntity::new_text(String::from("encrypted push"));
+        manipulate.info.use_module_name = String::from("test");
+        core.subscriber_hub.push(manipulate).unwrap();
+        let pushed = tokio::time::timeout(Duration::from_secs(10), push_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let PushEntity::Manipulate(manipulate) = pushed else {
+            panic!("Unexpected Push Entity: {:?}", pushed);
+        };
+        assert!(
+            matches!(manipulate.manipulate, ManipulateData::Text(text) if text == "encrypted push")
+        );
+        assert!(authenticator.encrypted.load(Ordering::SeqCst));
+        let resp = client.offline().await.unwrap();
+        assert!(matches!(resp.code(), ResponseCode::Success));
+        while core.subscriber_hub.is_subscribed("test") {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_hmac_authentication_client() {
+    let core = start_core(|_| {}).await;
+    let config = GrpcClientConfig {
+        authentication_mode: AuthenticationMode::Hmac,
+        ..Default::default()
+    };
+    let mut client = core.registered_client(config).await;
+    client.connection_instruct_server().await.unwrap();
+    let instruct = InstructEntity::new_text(String::from("test send hmac instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let (tx, rx) = mpsc::channel(1);
+    let instruct = InstructEntity::new_text(String::from("test send hmac instruct"));
+    tx.send(instruct).await.unwrap();
+    let mut resp_rx = client.multiple_text_instruct(rx).await.unwrap();
+    let resp = resp_rx.recv().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_hmac_auth_id_rejects_rsa_sign() {
+    let core = start_core(|_| {}).await;
+    let mut client = core.client(GrpcClientConfig {
+        authentication_mode: AuthenticationMode::Hmac,
+        ..Default::default()
+    });
+    client.connection_submodule_operate_server().await.unwrap();
+    let resp = client.register().await.unwrap();
+    let auth_id = get_auth_id(&resp).unwrap();
+
+    // 未注册的客户端使用实体携带的auth_id，以RSA方式冒充已协商会话密钥的子模块
+    let mut forged_client = core.client(GrpcClientConfig::default());
+    forged_client.connection_instruct_server().await.unwrap();
+    let mut instruct = InstructEntity::new_text(String::from("test send forged instruct"));
+    set_auth_id(auth_id, &mut instruct).unwrap();
+    assert!(!matches!(
+        forged_client.text_instruct(instruct).await,
+        Ok(resp) if matches!(resp.code(), ResponseCode::Success)
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_session_client() {
+    let core = start_core(|_| {}).await;
+    let config = GrpcClientConfig {
+        session_mode: true,
+        ..Default::default()
+    };
+    let mut client = core.registered_client(config).await;
+    client.connection_instruct_server().await.unwrap();
+    client.connection_manipulate_server().await.unwrap();
+    let instruct = InstructEntity::new_text(String::from("test send session instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let manipulate = ManipulateEntity::new_text(String::from("session text_display_manipulate"));
+    let resp = client.text_display_manipulate(manipulate).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let (tx, rx) = mpsc::channel(2);
+    let mut resp_rx = client.multiple_text_instruct(rx).await.unwrap();
+    for _ in 0..2 {
+        let instruct = InstructEntity::new_text(String::from("test send session instruct"));
+        tx.send(instruct).await.unwrap();
+        let resp = resp_rx.recv().await.unwrap();
+        assert!(matches!(resp.code(), ResponseCode::Success));
+    }
+    let (update, heartbeat) = tokio::join!(client.update(), client.heartbeat());
+    assert!(matches!(update.unwrap().code(), ResponseCode::Success));
+    assert!(matches!(heartbeat.unwrap().code(), ResponseCode::Success));
+    let resp = client.offline().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_middleware_client() {
+    let core = start_core(|server| {
+        server.add_middleware(Arc::new(RejectInstruct)).unwrap();
+    })
+    .await;
+    let mut client = core.client(GrpcClientConfig::default());
+    let count_response = Arc::new(CountResponse::default());
+    client.add_middleware(count_response.clone()).unwrap();
+    client.add_middleware(Arc::new(BlockInstruct)).unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    client.register().await.unwrap();
+    client.connection_instruct_server().await.unwrap();
+    let instruct = InstructEntity::new_text(String::from("test send middleware instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let instruct = InstructEntity::new_text(String::from("test send rejected instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::UnableToProcess));
+    let instruct = InstructEntity::new_text(String::from("test send blocked instruct"));
+    assert!(client.text_instruct(instruct).await.is_err());
+    assert_eq!(count_response.count.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_rate_limit_client() {
+    let core = start_core(|server| {
+        server
+            .set_rate_limiter(Arc::new(RateLimiter::new(vec![RateLimitRule {
+                submodule_name: Some(String::from("test")),
+                rpc: Some(String::from("send_direct_connection_manipulate")),
+                capacity: 2,
+                refill_per_second: 0.0,
+            }])))
+            .unwrap();
+    })
+    .await;
+    let mut client = core.client(GrpcClientConfig::default());
+    client.connection_submodule_operate_server().await.unwrap();
+    let resp = client.register().await.unwrap();
+    let auth_id = get_auth_id(&resp).unwrap();
+    client.connection_manipulate_server().await.unwrap();
+
+    // 冒充者使用相同的子模块名称与auth_id，签名校验失败，不消耗该子模块的令牌
+    let mut spoofed_client = core.client(GrpcClientConfig::default());
+    spoofed_client
+        .set_authenticator(Arc::new(ForgedSignAuthenticator))
+        .unwrap();
+    spoofed_client.connection_manipulate_server().await.unwrap();
+    for _ in 0..3 {
+        let mut manipulate = connection_manipulate();
+        set_auth_id(auth_id.to_string(), &mut manipulate).unwrap();
+        let resp = spoofed_client
+            .direct_connection_manipulate(manipulate)
+            .await;
+        assert!(!matches!(resp, Ok(resp) if matches!(resp.code(), ResponseCode::Success)));
+    }
+    // 校验失败的请求不采信声明的名称，统计为unknown
+    let rendered = metrics().render();
+    assert!(rendered.contains(
+        "nihility_verification_failures_total{rpc=\"send_direct_connection_manipulate\",submodule=\"unknown\"}"
+    ));
+    assert!(!rendered.contains(
+        "nihility_verification_failures_total{rpc=\"send_direct_connection_manipulate\",submodule=\"test\"}"
+    ));
+    for expect_limited in [false, false, true] {
+        let resp = client
+            .direct_connection_manipulate(connection_manipulate())
+            .await
+            .unwrap();
+        assert_eq!(
+            matches!(resp.code(), ResponseCode::TooManyRequests),
+            expect_limited
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_rate_limit_before_verification() {
+    let core = start_core(|server| {
+        server
+            .set_rate_limiter(Arc::new(RateLimiter::new(vec![RateLimitRule {
+                submodule_name: None,
+                rpc: Some(String::from("send_text_instruct")),
+                capacity: 1,
+                refill_per_second: 0.0,
+            }])))
+            .unwrap();
+    })
+    .await;
+    // 签名无法校验的请求同样按来源IP消耗令牌，超出后不再校验签名与解密载荷
+    let mut spoofed_client = core.client(GrpcClientConfig::default());
+    spoofed_client
+        .set_authenticator(Arc::new(ForgedSignAuthenticator))
+        .unwrap();
+    spoofed_client.connection_instruct_server().await.unwrap();
+    let instruct = || InstructEntity::new_text(String::from("test flood instruct"));
+    let resp = spoofed_client.text_instruct(instruct()).await;
+    assert!(!matches!(resp, Ok(resp) if matches!(resp.code(), ResponseCode::TooManyRequests)));
+    let resp = spoofed_client.text_instruct(instruct()).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::TooManyRequests));
+}
+
+fn connection_manipulate() -> ManipulateEntity {
+    ManipulateEntity::new_connection_params(ConnParams {
+        connection_type: ConnectionType::GrpcType,
+        client_type: ClientType::NotReceiveType,
+        conn_config: Default::default(),
+    })
+}
+
+/// 返回的客户端释放时自动下线，需在使用auth_id期间持有
+async fn register_admin_target(
+    core: &Core,
+    admin_client: &GrpcAdminClient,
+) -> (String, GrpcClient) {
+    let registered = admin_client.list_submodules().await.unwrap();
+    let client = core.registered_client(GrpcClientConfig::default()).await;
+    let auth_id = admin_client
+        .list_submodules()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|status| {
+            registered
+                .iter()
+                .all(|registered| registered.auth_id != status.auth_id)
+        })
+        .unwrap()
+        .auth_id;
+    (auth_id, client)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_admin_client() {
+    let core = start_core(|server| {
+        server.enable_admin(KEY_DIR).unwrap();
+    })
+    .await;
+    let admin_client = GrpcAdminClient::connect(&core.server_address, KEY_DIR)
+        .await
+        .unwrap();
+    assert!(admin_client
+        .dump_stats()
+        .await
+        .unwrap()
+        .contains("nihility_requests_total"));
+
+    let (auth_id, _client) = register_admin_target(&core, &admin_client).await;
+    let status = admin_client.get_submodule(&auth_id).await.unwrap();
+    assert_eq!(status.name, "test");
+    let status = admin_client.force_offline(&auth_id).await.unwrap();
+    assert_eq!(status.auth_id, auth_id);
+    assert!(admin_client.get_submodule(&auth_id).await.is_err());
+    assert!(admin_client.force_offline(&auth_id).await.is_err());
+    let (auth_id, _client) = register_admin_target(&core, &admin_client).await;
+    admin_client.revoke(&auth_id).await.unwrap();
+    assert!(admin_client.get_submodule(&auth_id).await.is_err());
+
+    let forged_client =
+        GrpcAdminClient::connect(&core.server_address, &format!("{}/submodule", KEY_DIR))
+            .await
+            .unwrap();
+    assert!(forged_client.list_submodules().await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_admin_replay_rejected() {
+    let core = start_core(|server| {
+        server.enable_admin(KEY_DIR).unwrap();
+    })
+    .await;
+    let admin_client = GrpcAdminClient::connect(&core.server_address, KEY_DIR)
+        .await
+        .unwrap();
+    let (auth_id, _client) = register_admin_target(&core, &admin_client).await;
+
+    // 查询请求的签名不能用于强制下线
+    let proxy_address = start_replay_proxy(&core, Some("/admin.Admin/ForceOffline")).await;
+    let replay_client = GrpcAdminClient::connect(&proxy_address, KEY_DIR)
+        .await
+        .unwrap();
+    assert!(replay_client.get_submodule(&auth_id).await.is_err());
+    assert!(admin_client.get_submodule(&auth_id).await.is_ok());
+
+    // 同一请求在有效期内只接受一次
+    let proxy_address = start_replay_proxy(&core, None).await;
+    let replay_client = GrpcAdminClient::connect(&proxy_address, KEY_DIR)
+        .await
+        .unwrap();
+    assert!(replay_client.get_submodule(&auth_id).await.is_err());
+    assert!(admin_client.get_submodule(&auth_id).await.is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shutdown_client() {
+    let core = start_core(|_| {}).await;
+    let mut client = core.registered_client(GrpcClientConfig::default()).await;
+    client.connection_instruct_server().await.unwrap();
+    client.shutdown().await.unwrap();
+    let instruct = InstructEntity::new_text(String::from("test send after shutdown"));
+    assert!(matches!(
+        client.text_instruct(instruct).await,
+        Err(NihilityCommonError::NotConnected(_))
+    ));
+    client.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shutdown_disconnect_on_offline_error() {
+    let core = start_core(|_| {}).await;
+    let mut client = GrpcClient::init(GrpcClientConfig {
+        server_address: core.server_address.to_string(),
+        ..Default::default()
+    });
+    client.connection_submodule_operate_server().await.unwrap();
+    client.connection_instruct_server().await.unwrap();
+    // 未设置子模块信息时注册失败但心跳已启动，下线无法发送时仍断开全部连接
+    assert!(matches!(
+        client.register().await,
+        Err(NihilityCommonError::SubmoduleInfo)
+    ));
+    assert!(matches!(
+        client.shutdown().await,
+        Err(NihilityCommonError::SubmoduleInfo)
+    ));
+    let instruct = InstructEntity::new_text(String::from("test send instruct"));
+    assert!(matches!(
+        client.text_instruct(instruct).await,
+        Err(NihilityCommonError::NotConnected(_))
+    ));
+}
+
+/// 等待auth_id从核心模块的注册表中移除
+async fn wait_offline(core: &Core, auth_id: &str) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while core.registry.get(auth_id).is_some() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_offline_on_drop() {
+    let core = start_core(|_| {}).await;
+    let client = core.registered_client(GrpcClientConfig::default()).await;
+    let auth_id = core.registry.list().first().unwrap().auth_id.to_string();
+    let cloned = client.clone();
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(core.registry.get(&auth_id).is_some());
+    drop(cloned);
+    wait_offline(&core, &auth_id).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_shutdown_client_on_cancel() {
+    let core = start_core(|_| {}).await;
+    let client = core.registered_client(GrpcClientConfig::default()).await;
+    let auth_id = core.registry.list().first().unwrap().auth_id.to_string();
+    let cancellation_token = CancellationToken::new();
+    let shutdown = shutdown_client_on_cancel(client.clone(), cancellation_token.clone());
+    cancellation_token.cancel();
+    shutdown.await.unwrap().unwrap();
+    assert!(core.registry.get(&auth_id).is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_key_pinning_client() {
+    let core = start_core(|_| {}).await;
+    core.registered_client(GrpcClientConfig::default()).await;
+    let mut client = core.client(GrpcClientConfig::default());
+    client
+        .set_authenticator(Arc::new(new_submodule_authenticator("changed")))
+        .unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    assert!(client.register().await.is_err());
+}
+
+#[test]
+fn test_reload_key_pins_drops_stale_pending() {
+    set_submodule_name("test");
+    let key_dir = format!("{}/pin_reload", KEY_DIR);
+    let core = RsaAuthenticator::default();
+    core.init_core(&key_dir).unwrap();
+    let register = |authenticator: &RsaAuthenticator| {
+        let mut info = submodule_info();
+        authenticator
+            .prepare_register(&mut info, &AuthenticationMode::Rsa)
+            .unwrap();
+        let mut operate = ModuleOperate::default();
+        operate.info = Some(info);
+        operate.operate_type = OperateType::Register;
+        core.register(&mut operate)
+    };
+    let original = submodule_authenticator();
+    let changed = new_submodule_authenticator("pin_reload_changed");
+    let pins_path = format!("{}/pinned_keys.json", key_dir);
+    std::fs::remove_file(&pins_path).ok();
+    core.reload_key_pins().unwrap();
+    register(&original).unwrap();
+    let original_fingerprint = core.pinned_keys()["test"].to_string();
+    assert!(register(&changed).is_err());
+    let changed_fingerprint = core.pending_key_changes()["test"].to_string();
+
+    // 文件中的指纹未变时待审批的指纹保留
+    core.reload_key_pins().unwrap();
+    assert_eq!(core.pending_key_changes()["test"], changed_fingerprint);
+
+    // 运维人员直接在文件中替换指纹后，原待审批的指纹失效
+    std::fs::write(
+        &pins_path,
+        format!(r#"{{"test": "{}"}}"#, changed_fingerprint),
+    )
+    .unwrap();
+    core.reload_key_pins().unwrap();
+    assert!(core.pending_key_changes().is_empty());
+    register(&changed).unwrap();
+
+    // 文件中删除的子模块，待审批的指纹同样失效
+    assert!(register(&original).is_err());
+    assert_eq!(core.pending_key_changes()["test"], original_fingerprint);
+    std::fs::write(&pins_path, "{}").unwrap();
+    core.reload_key_pins().unwrap();
+    assert!(core.pending_key_changes().is_empty());
+    assert!(core
+        .approve_key_change("test", &original_fingerprint)
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_copied_public_key_register_rejected() {
+    let core = start_core(|_| {}).await;
+    core.registered_client(GrpcClientConfig::default()).await;
+    let public_key = std::fs::read_to_string(format!("{}/submodule/id_rsa.pub", KEY_DIR)).unwrap();
+    let mut client = core.client(GrpcClientConfig::default());
+    client
+        .set_authenticator(Arc::new(CopiedKeyAuthenticator {
+            inner: new_submodule_authenticator("impostor"),
+            public_key,
+        }))
+        .unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    assert!(client.register().await.is_err());
+    core.registered_client(GrpcClientConfig::default()).await;
 }
@@ -1,57 +1,153 @@
-use std::net::IpAddr;
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use tokio::sync::mpsc;
-use tokio::{join, spawn};
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
 use nihility_common::{
-    core_authentication_core_init, GrpcClientConfig, GrpcServer, GrpcServerConfig, Log, LogConfig,
-    LogLevel, NihilityServer,
+    set_default_receiver_submodule, set_submodule_name, BindAddr, ClientType, ConnParams,
+    ConnectionType, EventBus, GrpcClient, GrpcClientConfig, GrpcServer, GrpcServerConfig,
+    InstructData, InstructEntity, InstructMatcher, Log, LogConfig, ManipulateData,
+    ManipulateEntity, Middleware, MiddlewareContext, MiddlewareEntity, NihilityClient,
+    NihilityCommonError, NihilityServer, NoopAuthenticator, OperateType, PushEntity, ResponseCode,
+    SubmoduleInfo, SubscriberHub, WrapResult,
 };
 
-#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
-async fn test_server() {
-    let mut log_config = LogConfig::default();
-    log_config.level = LogLevel::Debug;
-    Log::init(&vec![log_config]).unwrap();
-    core_authentication_core_init("auth").unwrap();
-    join!(test_grpc_server(),);
-    tokio::time::sleep(Duration::from_secs(30)).await;
+struct RejectInstruct;
+
+impl Middleware for RejectInstruct {
+    fn before(&self, context: &MiddlewareContext, entity: MiddlewareEntity) -> WrapResult<()> {
+        info!("Middleware {} From {:?}", context.rpc, context.peer_addr);
+        match entity {
+            MiddlewareEntity::Instruct(instruct) => match &instruct.instruct {
+                InstructData::Text(text) if text == "test send rejected instruct" => {
+                    Err(NihilityCommonError::Rejected(text.to_string()))
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+async fn recv<T>(receiver: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn test_grpc_server() {
-    let mut server_config = GrpcServerConfig::default();
-    server_config.bind_ip = IpAddr::from_str("127.0.0.1").unwrap();
-    let connection_params = server_config.create_connection_params();
-    info!("connection_params: {:?}", &connection_params);
-    let client_config = GrpcClientConfig::try_from(connection_params.clone()).unwrap();
-    info!("client_config: {:?}", &client_config);
+    Log::init_once(&[LogConfig::default()]).unwrap();
+    set_submodule_name("test");
+    set_default_receiver_submodule("test");
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        ..Default::default()
+    };
     let mut server = GrpcServer::init(server_config, CancellationToken::new());
-    let (module_tx, mut module_rx) = mpsc::unbounded_channel();
+    server
+        .set_authenticator(Arc::new(NoopAuthenticator))
+        .unwrap();
+    let module_bus = Arc::new(EventBus::default());
+    let mut register_rx = module_bus.subscribe_kind(OperateType::Register);
+    let mut offline_rx = module_bus.subscribe_kind(OperateType::Offline);
     let (instruct_tx, mut instruct_rx) = mpsc::unbounded_channel();
     let (manipulate_tx, mut manipulate_rx) = mpsc::unbounded_channel();
-    server.set_submodule_operate_sender(module_tx).unwrap();
+    server.set_submodule_operate_deliver(module_bus).unwrap();
     server.set_instruct_sender(instruct_tx).unwrap();
     server.set_manipulate_sender(manipulate_tx).unwrap();
-    server.start().unwrap();
-    tokio::time::sleep(Duration::from_secs(10)).await;
-    info!("Start Receiver");
-    spawn(async move {
-        while let Some(operate) = module_rx.recv().await {
-            info!("Module Operate: {:?}", operate);
-        }
-    });
-    spawn(async move {
-        while let Some(instruct) = instruct_rx.recv().await {
-            info!("Instruct: {:?}", instruct);
-        }
-    });
-    spawn(async move {
-        while let Some(manipulate) = manipulate_rx.recv().await {
-            info!("Manipulate: {:?}", manipulate);
-        }
+    let instruct_matcher = Arc::new(InstructMatcher::new());
+    server
+        .set_instruct_matcher(instruct_matcher.clone())
+        .unwrap();
+    let subscriber_hub = Arc::new(SubscriberHub::default());
+    server.set_subscriber_hub(subscriber_hub.clone()).unwrap();
+    server.add_middleware(Arc::new(RejectInstruct)).unwrap();
+    let mut handle = server.start().unwrap();
+    let local_addr = handle.ready().await.unwrap().unwrap();
+
+    let mut client = GrpcClient::init(GrpcClientConfig {
+        server_address: format!("http://{}", local_addr),
+        ..Default::default()
     });
+    client
+        .set_authenticator(Arc::new(NoopAuthenticator))
+        .unwrap();
+    client
+        .set_submodule_info(SubmoduleInfo {
+            default_instruct: vec![String::from("test_instruct")],
+            conn_params: ConnParams {
+                connection_type: ConnectionType::GrpcType,
+                client_type: ClientType::NotReceiveType,
+                conn_config: HashMap::new(),
+            },
+        })
+        .unwrap();
+    client.connection_submodule_operate_server().await.unwrap();
+    client.connection_instruct_server().await.unwrap();
+    client.connection_manipulate_server().await.unwrap();
+    client.connection_subscribe_server().await.unwrap();
+
+    // 注册经事件总线送达，并更新指令匹配
+    let resp = client.register().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let register = tokio::time::timeout(Duration::from_secs(5), register_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(register.name, "test");
+    let matched =
+        instruct_matcher.match_instruct(&InstructEntity::new_text(String::from("test_instruct")));
+    assert_eq!(matched[0].submodule_name, "test");
+
+    let mut push_rx = client.subscribe().await.unwrap();
+    while !subscriber_hub.is_subscribed("test") {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let mut manipulate = ManipulateEntity::new_text(String::from("pushed manipulate"));
+    manipulate.info.use_module_name = String::from("test");
+    subscriber_hub.push(manipulate).unwrap();
+    let pushed = tokio::time::timeout(Duration::from_secs(5), push_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let PushEntity::Manipulate(manipulate) = pushed else {
+        panic!("Unexpected Push Entity: {:?}", pushed);
+    };
+    assert!(
+        matches!(manipulate.manipulate, ManipulateData::Text(text) if text == "pushed manipulate")
+    );
+
+    // 中间件拒绝的指令不转发至核心模块
+    let instruct = InstructEntity::new_text(String::from("test send rejected instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::UnableToProcess));
+    let instruct = InstructEntity::new_text(String::from("test_instruct"));
+    let resp = client.text_instruct(instruct).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let InstructData::Text(text) = recv(&mut instruct_rx).await.instruct;
+    assert_eq!(text, "test_instruct");
+
+    let manipulate = ManipulateEntity::new_text(String::from("text_display_manipulate"));
+    let resp = client.text_display_manipulate(manipulate).await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    assert!(matches!(
+        recv(&mut manipulate_rx).await.manipulate,
+        ManipulateData::Text(text) if text == "text_display_manipulate"
+    ));
+
+    let resp = client.offline().await.unwrap();
+    assert!(matches!(resp.code(), ResponseCode::Success));
+    let offline = tokio::time::timeout(Duration::from_secs(5), offline_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(offline.name, "test");
+    handle.shutdown().await.unwrap();
 }
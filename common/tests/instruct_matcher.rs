@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use nihility_common::{
+    set_default_receiver_submodule, set_submodule_name, ClientType, ConnParams, ConnectionType,
+    InstructEntity, InstructMatcher, MatchKind, ModuleOperate, OperateType, SubmoduleInfo,
+};
+
+fn operate(name: &str, operate_type: OperateType, default_instruct: &[&str]) -> ModuleOperate {
+    let mut operate = ModuleOperate::default();
+    operate.name = name.to_string();
+    operate.operate_type = operate_type;
+    operate.info = Some(SubmoduleInfo {
+        default_instruct: default_instruct.iter().map(|s| s.to_string()).collect(),
+        conn_params: ConnParams {
+            connection_type: ConnectionType::GrpcType,
+            client_type: ClientType::BothType,
+            conn_config: HashMap::new(),
+        },
+    });
+    operate
+}
+
+fn matched(matcher: &InstructMatcher, text: &str) -> Vec<(String, MatchKind)> {
+    matcher
+        .match_instruct(&InstructEntity::new_text(text.to_string()))
+        .into_iter()
+        .map(|candidate| (candidate.submodule_name, candidate.kind))
+        .collect()
+}
+
+#[test]
+fn test_instruct_matcher() {
+    set_submodule_name("matcher");
+    set_default_receiver_submodule("matcher");
+    let matcher = InstructMatcher::new();
+    matcher.apply(&operate(
+        "weather",
+        OperateType::Register,
+        &["天气", "天气*"],
+    ));
+    matcher.apply(&operate(
+        "music",
+        OperateType::Register,
+        &["播放*", "re:^来一首.+$"],
+    ));
+    matcher.apply(&operate("timer", OperateType::Register, &["*分钟后提醒?"]));
+    matcher.apply(&operate(
+        "search",
+        OperateType::Register,
+        &["re:.*", "re:(["],
+    ));
+
+    assert_eq!(
+        matched(&matcher, " 天气 "),
+        vec![
+            ("weather".to_string(), MatchKind::Exact),
+            ("search".to_string(), MatchKind::Regex),
+        ]
+    );
+    assert_eq!(
+        matched(&matcher, "天气怎么样")[0],
+        ("weather".to_string(), MatchKind::Prefix)
+    );
+    assert_eq!(
+        matched(&matcher, "来一首歌")[0],
+        ("music".to_string(), MatchKind::Regex)
+    );
+    assert_eq!(
+        matched(&matcher, "十分钟后提醒我")[0],
+        ("timer".to_string(), MatchKind::Glob)
+    );
+    assert_eq!(matched(&matcher, "十分钟后提醒我们").len(), 1);
+
+    matcher.apply(&operate("music", OperateType::Update, &["播放音乐"]));
+    assert_eq!(
+        matched(&matcher, "播放音乐")[0],
+        ("music".to_string(), MatchKind::Exact)
+    );
+    assert_eq!(matched(&matcher, "播放视频").len(), 1);
+
+    matcher.apply(&operate("search", OperateType::Heartbeat, &[]));
+    matcher.apply(&operate("search", OperateType::Offline, &[]));
+    assert!(matched(&matcher, "随便说点什么").is_empty());
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::info;
+
+use nihility_common::{Log, LogConfig, LogFormat, LogOutType, LogRotation, LogSpanEvents};
+
+#[tokio::test]
+async fn test_log_json_format() {
+    let log_dir = std::env::temp_dir().join(format!("nihility-log-{}", std::process::id()));
+    let log_config = LogConfig {
+        out_type: LogOutType::File(log_dir.to_str().unwrap().to_string()),
+        format: LogFormat::Json,
+        utc_offset: String::from("+00:00"),
+        ansi: true,
+        span_events: LogSpanEvents::Close,
+        file_prefix: String::from("json"),
+        rotation: LogRotation::Hourly,
+        ..Default::default()
+    };
+    let _log_handle = Log::init(&[log_config]).unwrap();
+    tracing::info_span!("json_span").in_scope(|| info!(field = 1, "json message"));
+
+    let lines = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let content = fs::read_dir(&log_dir)
+                .into_iter()
+                .flatten()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap().to_str().unwrap();
+                    name.starts_with("json.") && name.ends_with(".log")
+                })
+                .filter_map(|path| fs::read_to_string(path).ok())
+                .collect::<String>();
+            if content.lines().count() >= 2 {
+                return content.lines().map(String::from).collect::<Vec<_>>();
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let event: Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(event["level"], "INFO");
+    assert_eq!(event["fields"]["message"], "json message");
+    assert_eq!(event["fields"]["field"], 1);
+    assert_eq!(event["span"]["name"], "json_span");
+    // 文件输出忽略ansi
+    assert!(!lines[0].contains('\u{1b}'));
+    let close: Value = serde_json::from_str(&lines[1]).unwrap();
+    assert_eq!(close["fields"]["message"], "close");
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::Path;
+
+use tracing::info;
+
+use nihility_common::{Log, LogConfig, LogOutType, NihilityCommonError};
+
+fn read_logs(dir: &Path) -> String {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_log_init() {
+    let log_dir = std::env::temp_dir().join(format!("nihility-init-{}", std::process::id()));
+    let first_dir = log_dir.join("first");
+    let second_dir = log_dir.join("second");
+    let file_config = |dir: &Path| LogConfig {
+        out_type: LogOutType::File(dir.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    let handle = Log::init(&[file_config(&first_dir), file_config(&second_dir)]).unwrap();
+    assert!(matches!(
+        Log::init(&[LogConfig::default()]),
+        Err(NihilityCommonError::LogConfig)
+    ));
+    Log::init_once(&[LogConfig::default()]).unwrap();
+    Log::init_once(&[LogConfig::default()]).unwrap();
+
+    info!("flushed on drop");
+    // drop后缓冲中的日志已写入各文件
+    drop(handle);
+    assert!(read_logs(&first_dir).contains("flushed on drop"));
+    assert!(read_logs(&second_dir).contains("flushed on drop"));
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use tracing::{debug, info};
+
+use nihility_common::{Log, LogConfig, LogOutType, LogRotation};
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_log_rotation_and_filter() {
+    let log_dir = std::env::temp_dir().join(format!("nihility-rotation-{}", std::process::id()));
+    let log_config = LogConfig {
+        out_type: LogOutType::File(log_dir.to_str().unwrap().to_string()),
+        filter: String::from("log_rotation::quiet=error,log_rotation::verbose=debug"),
+        rotation: LogRotation::Size(1024),
+        max_files: 3,
+        ..Default::default()
+    };
+    let _log_handle = Log::init(&[log_config]).unwrap();
+
+    for index in 0..100 {
+        info!("rotation message {}", index);
+    }
+    info!(target: "log_rotation::quiet", "quiet message");
+    debug!(target: "log_rotation::verbose", "verbose message");
+    debug!("debug message");
+    info!("last message");
+    let read_all = || {
+        file_names(&log_dir)
+            .iter()
+            .filter_map(|name| fs::read_to_string(log_dir.join(name)).ok())
+            .collect::<String>()
+    };
+    wait_until(|| read_all().contains("last message")).await;
+    assert_eq!(
+        file_names(&log_dir),
+        ["nihility.1.log", "nihility.2.log", "nihility.log"]
+    );
+    for name in file_names(&log_dir) {
+        assert!(fs::metadata(log_dir.join(name)).unwrap().len() <= 1024);
+    }
+    let content = read_all();
+    assert!(content.contains("verbose message"));
+    assert!(!content.contains("quiet message"));
+    assert!(!content.contains("debug message"));
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use nihility_common::{metrics, MetricsSide};
+
+#[tokio::test]
+async fn test_metrics() {
+    metrics().record_request(MetricsSide::Server, "register", "metrics", "Success");
+    metrics().record_request(MetricsSide::Server, "register", "metrics", "Success");
+    metrics().record_request(MetricsSide::Client, "heartbeat", "me\"trics", "Unavailable");
+    metrics().observe_duration(MetricsSide::Server, "register", Duration::from_millis(20));
+    metrics().record_verification_failure("heartbeat", "metrics");
+    metrics().set_queue_depth("subscription:metrics", 3);
+
+    let text = metrics().render();
+    assert!(text.contains("# TYPE nihility_requests_total counter"));
+    assert!(text.contains(
+        "nihility_requests_total{side=\"server\",rpc=\"register\",submodule=\"metrics\",code=\"Success\"} 2"
+    ));
+    assert!(text.contains(
+        "nihility_requests_total{side=\"client\",rpc=\"heartbeat\",submodule=\"me\\\"trics\",code=\"Unavailable\"} 1"
+    ));
+    assert!(text.contains(
+        "nihility_request_duration_seconds_bucket{side=\"server\",rpc=\"register\",le=\"0.01\"} 0"
+    ));
+    assert!(text.contains(
+        "nihility_request_duration_seconds_bucket{side=\"server\",rpc=\"register\",le=\"0.025\"} 1"
+    ));
+    assert!(text
+        .contains("nihility_request_duration_seconds_count{side=\"server\",rpc=\"register\"} 1"));
+    assert!(text.contains(
+        "nihility_verification_failures_total{rpc=\"heartbeat\",submodule=\"metrics\"} 1"
+    ));
+    assert!(text.contains("nihility_queue_depth{queue=\"subscription:metrics\"} 3"));
+
+    #[cfg(feature = "prometheus")]
+    test_serve_metrics().await;
+}
+
+#[cfg(feature = "prometheus")]
+async fn test_serve_metrics() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio_util::sync::CancellationToken;
+
+    let cancellation_token = CancellationToken::new();
+    let server = tokio::spawn(nihility_common::serve_metrics(
+        "127.0.0.1:5059".parse().unwrap(),
+        cancellation_token.clone(),
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut stream = TcpStream::connect("127.0.0.1:5059").await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("nihility_queue_depth{queue=\"subscription:metrics\"} 3"));
+    cancellation_token.cancel();
+    server.await.unwrap().unwrap();
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use nihility_common::{RateLimitRule, RateLimiter};
+
+fn rule(submodule_name: Option<&str>, rpc: Option<&str>, capacity: u32) -> RateLimitRule {
+    RateLimitRule {
+        submodule_name: submodule_name.map(String::from),
+        rpc: rpc.map(String::from),
+        capacity,
+        refill_per_second: 10.0,
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let limiter = RateLimiter::new(vec![
+        rule(None, None, 3),
+        rule(Some("noisy"), Some("send_text_instruct"), 1),
+    ]);
+    assert!(limiter.check("quiet", "send_text_instruct"));
+    assert!(limiter.check("quiet", "register"));
+    assert!(limiter.check("quiet", "heartbeat"));
+    assert!(!limiter.check("quiet", "heartbeat"));
+
+    assert!(limiter.check("noisy", "send_text_instruct"));
+    assert!(!limiter.check("noisy", "send_text_instruct"));
+    assert!(limiter.check("noisy", "heartbeat"));
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(limiter.check("quiet", "heartbeat"));
+    assert!(limiter.check("noisy", "send_text_instruct"));
+
+    limiter.set_rules(vec![]);
+    assert!(limiter.rules().is_empty());
+    for _ in 0..10 {
+        assert!(limiter.check("noisy", "send_text_instruct"));
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_rejected_not_consume() {
+    let limiter = RateLimiter::new(vec![
+        rule(None, None, 2),
+        rule(None, Some("send_text_instruct"), 1),
+    ]);
+    assert!(limiter.check("quiet", "send_text_instruct"));
+    // 第二条规则已耗尽，第一条规则的令牌不被消耗
+    assert!(!limiter.check("quiet", "send_text_instruct"));
+    assert!(!limiter.check("quiet", "send_text_instruct"));
+    assert!(limiter.check("quiet", "heartbeat"));
+    assert!(!limiter.check("quiet", "heartbeat"));
+}
+
+#[tokio::test]
+async fn test_rate_limit_sweep_full_bucket() {
+    let limiter = RateLimiter::new(vec![rule(None, None, 1)]);
+    for index in 0..=1024 {
+        assert!(limiter.check(&format!("peer-{}", index), "heartbeat"));
+    }
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(limiter.check("quiet", "heartbeat"));
+    assert_eq!(limiter.bucket_count(), 1);
+}
+
+#[tokio::test]
+async fn test_rate_limit_peer() {
+    let limiter = RateLimiter::new(vec![
+        rule(None, Some("send_text_instruct"), 2),
+        rule(Some("noisy"), None, 1),
+    ]);
+    let peer = "127.0.0.1".parse().unwrap();
+    // 签名校验前按来源IP计数，与子模块的令牌桶互不影响，指定子模块的规则不参与
+    assert!(limiter.check_peer(peer, "send_text_instruct"));
+    assert!(limiter.check_peer(peer, "send_text_instruct"));
+    assert!(!limiter.check_peer(peer, "send_text_instruct"));
+    assert!(limiter.check_peer("127.0.0.2".parse().unwrap(), "send_text_instruct"));
+    assert!(limiter.check_peer(peer, "heartbeat"));
+    assert!(limiter.check_peer(peer, "heartbeat"));
+    assert!(limiter.check("noisy", "send_text_instruct"));
+    assert!(!limiter.check("noisy", "send_text_instruct"));
+}
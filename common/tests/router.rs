@@ -0,0 +1,67 @@
+use tokio::sync::mpsc;
+
+use nihility_common::{
+    set_default_receiver_submodule, set_submodule_name, InstructEntity, InstructType,
+    ManipulateEntity, ManipulateType, Router,
+};
+
+fn instruct(receiver: &str, instruct_type: InstructType) -> InstructEntity {
+    let mut entity = InstructEntity::new_text(String::from("router test instruct"));
+    entity.info.receive_manipulate_submodule = receiver.to_string();
+    entity.info.instruct_type = instruct_type;
+    entity
+}
+
+#[test]
+fn test_router() {
+    set_submodule_name("router");
+    set_default_receiver_submodule("router");
+    let (fallback_tx, mut fallback_rx) = mpsc::unbounded_channel();
+    let router = Router::new(fallback_tx);
+    let mut display_rx = router.subscribe_target("display");
+    let mut special_rx = router.subscribe_kind(InstructType::SpecialType);
+
+    router
+        .route(instruct("display", InstructType::SpecialType))
+        .unwrap();
+    router
+        .route(instruct("speaker", InstructType::SpecialType))
+        .unwrap();
+    router
+        .route(instruct("speaker", InstructType::DefaultType))
+        .unwrap();
+    assert!(display_rx.try_recv().is_ok());
+    assert_eq!(
+        special_rx
+            .try_recv()
+            .unwrap()
+            .info
+            .receive_manipulate_submodule,
+        "speaker"
+    );
+    assert!(matches!(
+        fallback_rx.try_recv().unwrap().info.instruct_type,
+        InstructType::DefaultType
+    ));
+
+    drop(display_rx);
+    router
+        .route(instruct("display", InstructType::DefaultType))
+        .unwrap();
+    assert!(fallback_rx.try_recv().is_ok());
+    assert!(special_rx.try_recv().is_err());
+
+    let (fallback_tx, mut fallback_rx) = mpsc::unbounded_channel();
+    let router = Router::new(fallback_tx);
+    let mut confirm_rx = router.subscribe_kind(ManipulateType::ConfirmType);
+    let mut confirm = ManipulateEntity::new_simple();
+    confirm.info.manipulate_type = ManipulateType::ConfirmType;
+    router.route(confirm).unwrap();
+    router
+        .route(ManipulateEntity::new_text(String::from(
+            "router test manipulate",
+        )))
+        .unwrap();
+    assert!(confirm_rx.try_recv().is_ok());
+    assert!(fallback_rx.try_recv().is_ok());
+}
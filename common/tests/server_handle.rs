@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::{Channel, Endpoint};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+
+use nihility_common::{BindAddr, GrpcClientConfig, GrpcServer, GrpcServerConfig, NihilityServer};
+
+fn bind_addrs(addrs: &[&str]) -> Vec<BindAddr> {
+    addrs
+        .iter()
+        .map(|addr| BindAddr::from_str(addr).unwrap())
+        .collect()
+}
+
+fn ephemeral_server(addrs: &[&str], bind_port: u16) -> GrpcServer {
+    let server_config = GrpcServerConfig {
+        bind_addrs: bind_addrs(addrs),
+        bind_port,
+        ..Default::default()
+    };
+    let mut server = GrpcServer::init(server_config, CancellationToken::new());
+    let (instruct_tx, _instruct_rx) = mpsc::unbounded_channel();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    server
+}
+
+async fn check_serving(channel: Channel) {
+    let resp = HealthClient::new(channel)
+        .check(HealthCheckRequest {
+            service: String::from("instruct.Instruct"),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.status(), ServingStatus::Serving);
+}
+
+async fn tcp_channel(server_address: String) -> Channel {
+    Channel::from_shared(server_address)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_server_handle() {
+    let mut handle = ephemeral_server(&["127.0.0.1"], 0).start().unwrap();
+    let local_addr = handle.local_addr().unwrap();
+    assert_ne!(local_addr.port(), 0);
+    assert_eq!(handle.ready().await.unwrap(), Some(local_addr));
+
+    check_serving(tcp_channel(format!("http://{}", local_addr)).await).await;
+
+    assert!(ephemeral_server(&["127.0.0.1"], local_addr.port())
+        .start()
+        .is_err());
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_server_multiple_bind_addrs() {
+    let socket_path = std::env::temp_dir().join(format!("nihility-{}.sock", std::process::id()));
+    let unix_addr = format!("unix:{}", socket_path.display());
+    let mut handle = ephemeral_server(&["::1", "127.0.0.1", &unix_addr], 0)
+        .start()
+        .unwrap();
+    handle.ready().await.unwrap();
+    let local_addrs = handle.local_addrs().to_vec();
+    assert_eq!(local_addrs.len(), 2);
+    assert!(local_addrs[0].is_ipv6());
+    assert!(local_addrs[1].is_ipv4());
+    for local_addr in &local_addrs {
+        check_serving(tcp_channel(format!("http://{}", local_addr)).await).await;
+    }
+    let unix_channel = Endpoint::from_static("http://localhost")
+        .connect_with_connector(tower::service_fn({
+            let socket_path = socket_path.clone();
+            move |_| UnixStream::connect(socket_path.clone())
+        }))
+        .await
+        .unwrap();
+    check_serving(unix_channel).await;
+
+    // IPv6地址仅接受IPv6连接，同一端口仍可绑定IPv4地址
+    let ipv6_port = local_addrs[0].port();
+    let ipv4_handle = ephemeral_server(&["127.0.0.1"], ipv6_port).start().unwrap();
+    ipv4_handle.shutdown().await.unwrap();
+
+    handle.shutdown().await.unwrap();
+    assert!(!socket_path.exists());
+}
+
+#[test]
+fn test_connection_params() {
+    let server_config = GrpcServerConfig {
+        bind_addrs: bind_addrs(&["127.0.0.1", "::", "0.0.0.0", "unix:/run/nihility.sock"]),
+        bind_port: 5050,
+        ..Default::default()
+    };
+    let connection_params = server_config.create_connection_params();
+    let server_addresses = GrpcClientConfig::server_addresses(&connection_params);
+    assert_eq!(server_addresses[0], "http://127.0.0.1:5050");
+    assert!(server_addresses.contains(&String::from("http://[::1]:5050")));
+    assert!(server_addresses.contains(&String::from("unix:/run/nihility.sock")));
+    assert_eq!(
+        server_addresses
+            .iter()
+            .filter(|address| *address == "http://127.0.0.1:5050")
+            .count(),
+        1
+    );
+    assert!(server_addresses
+        .iter()
+        .all(|address| !address.starts_with("http://[fe80")));
+    let client_config = GrpcClientConfig::try_from(connection_params).unwrap();
+    assert_eq!(client_config.server_address, "http://127.0.0.1:5050");
+
+    assert!(BindAddr::from_str("unix:").is_err());
+    assert!(BindAddr::from_str("localhost").is_err());
+}
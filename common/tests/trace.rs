@@ -0,0 +1,113 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use nihility_common::{
+    set_default_receiver_submodule, BindAddr, GrpcClient, GrpcClientConfig, GrpcServer,
+    GrpcServerConfig, InstructEntity, Log, LogConfig, ManipulateEntity, NihilityClient,
+    NihilityServer, NoopAuthenticator, TraceContext,
+};
+
+async fn recv<T>(receiver: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_trace_propagation() {
+    Log::init_once(&[LogConfig::default()]).unwrap();
+    set_default_receiver_submodule("display");
+    let server_config = GrpcServerConfig {
+        bind_addrs: vec![BindAddr::from_str("127.0.0.1").unwrap()],
+        bind_port: 0,
+        ..Default::default()
+    };
+    let mut server = GrpcServer::init(server_config, CancellationToken::new());
+    server
+        .set_authenticator(Arc::new(NoopAuthenticator))
+        .unwrap();
+    let (instruct_tx, mut instruct_rx) = mpsc::unbounded_channel();
+    server.set_instruct_sender(instruct_tx).unwrap();
+    let (manipulate_tx, mut manipulate_rx) = mpsc::unbounded_channel();
+    server.set_manipulate_sender(manipulate_tx).unwrap();
+    let mut handle = server.start().unwrap();
+    let local_addr = handle.ready().await.unwrap().unwrap();
+
+    for session_mode in [false, true] {
+        let mut client = GrpcClient::init(GrpcClientConfig {
+            server_address: format!("http://{}", local_addr),
+            session_mode,
+            ..Default::default()
+        });
+        client
+            .set_authenticator(Arc::new(NoopAuthenticator))
+            .unwrap();
+        client.connection_instruct_server().await.unwrap();
+        client.connection_manipulate_server().await.unwrap();
+
+        // 在追踪上下文的span中发送
+        let trace = TraceContext::new_root();
+        client
+            .text_instruct(InstructEntity::new_text(String::from("traced")))
+            .instrument(trace.span("voice"))
+            .await
+            .unwrap();
+        let instruct = recv(&mut instruct_rx).await;
+        assert_eq!(instruct.trace_id.as_deref(), Some(trace.trace_id.as_str()));
+
+        // 核心模块转发时沿用收到的trace_id
+        let mut manipulate = ManipulateEntity::new_text(String::from("display"));
+        manipulate.trace_id = instruct.trace_id.clone();
+        client.text_display_manipulate(manipulate).await.unwrap();
+        assert_eq!(recv(&mut manipulate_rx).await.trace_id, instruct.trace_id);
+
+        client
+            .text_instruct(InstructEntity::new_text(String::from("untraced")))
+            .await
+            .unwrap();
+        let untraced = recv(&mut instruct_rx).await.trace_id.unwrap();
+        assert_ne!(untraced, trace.trace_id);
+        assert!(TraceContext::from_trace_id(&untraced).is_some());
+    }
+    handle.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_traceparent() {
+    let trace =
+        TraceContext::from_traceparent("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01")
+            .unwrap();
+    assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(trace.span_id, "00f067aa0ba902b7");
+    assert_eq!(
+        trace.to_traceparent(),
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+    );
+    let child = trace.child();
+    assert_eq!(child.trace_id, trace.trace_id);
+    assert_ne!(child.span_id, trace.span_id);
+    let root = TraceContext::new_root();
+    assert_eq!(
+        TraceContext::from_traceparent(&root.to_traceparent()),
+        Some(root)
+    );
+
+    for invalid in [
+        "",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+        "00-4bf92f3577b34da6a3ce929d0e0e47zz-00f067aa0ba902b7-01",
+    ] {
+        assert_eq!(TraceContext::from_traceparent(invalid), None, "{}", invalid);
+    }
+    assert_eq!(TraceContext::from_trace_id("trace"), None);
+    assert_eq!(TraceContext::current(), None);
+}